@@ -18,20 +18,128 @@ fn main() {
             .unwrap();
         std::fs::write("src/app/shader.vert.spv", binary_result.as_binary_u8()).unwrap();
         let fragment_shader_template = include_str!("src/app/shader.frag");
-        let content = include_str!("src/app/default.glsl");
-        let map = [("content".to_string(), content.to_string())]
-            .into_iter()
-            .collect::<std::collections::HashMap<String, String>>();
-        let fragment_shader = strfmt::strfmt(fragment_shader_template, &map).unwrap();
-        let binary_result = compiler
-            .compile_into_spirv(
-                &fragment_shader,
-                shaderc::ShaderKind::Fragment,
-                "shader.frag",
-                "main",
-                Some(&compile_options),
-            )
-            .unwrap();
-        std::fs::write("src/app/shader.frag.spv", binary_result.as_binary_u8()).unwrap();
+        examples::build(&compiler, &compile_options, fragment_shader_template);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn fill_template(template: &str, is_buffer: bool, common: &str, content: &str) -> String {
+    let defines = if is_buffer { "#define BUFFER_PASS" } else { "" };
+    let map = [
+        ("defines".to_string(), defines.to_string()),
+        // The examples declare no uniforms of their own.
+        ("uniforms".to_string(), "float _none;".to_string()),
+        ("common".to_string(), common.to_string()),
+        ("content".to_string(), content.to_string()),
+    ]
+    .into_iter()
+    .collect::<std::collections::HashMap<String, String>>();
+    strfmt::strfmt(template, &map).unwrap()
+}
+
+/// Precompiled passes and decoded images of the bundled examples, for the web
+/// version; see `src/app/examples.rs`.
+#[cfg(not(target_arch = "wasm32"))]
+mod examples {
+    use std::fmt::Write as _;
+    use std::path::{Path, PathBuf};
+
+    const DIRECTORY: &str = "src/app/examples";
+
+    pub fn build(
+        compiler: &shaderc::Compiler,
+        options: &shaderc::CompileOptions<'_>,
+        template: &str,
+    ) {
+        let out_dir = PathBuf::from(std::env::var("OUT_DIR").unwrap());
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join(DIRECTORY);
+        let mut shaders = String::from("const PRECOMPILED: &[(bool, &str, &str, &[u8])] = &[\n");
+        let mut media = String::from("const MEDIA: &[(&str, u32, u32, &[u8])] = &[\n");
+        let mut directories = vec![root.clone()];
+        while let Some(directory) = directories.pop() {
+            let common = directory.join("common.glsl");
+            let common = common.exists().then_some(common);
+            let mut paths: Vec<PathBuf> = std::fs::read_dir(&directory)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .collect();
+            paths.sort();
+            for path in paths {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                let flat_name = path
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace(['/', '\\'], "_");
+                if path.is_dir() {
+                    directories.push(path);
+                } else if name.ends_with(".glsl") && Some(&path) != common.as_ref() {
+                    let is_buffer = name.starts_with("buffer_");
+                    let content = std::fs::read_to_string(&path).unwrap();
+                    let common_code = common
+                        .as_ref()
+                        .map(|common| std::fs::read_to_string(common).unwrap())
+                        .unwrap_or_default();
+                    let source = super::fill_template(template, is_buffer, &common_code, &content);
+                    let spirv = compiler
+                        .compile_into_spirv(
+                            &source,
+                            shaderc::ShaderKind::Fragment,
+                            &name,
+                            "main",
+                            Some(options),
+                        )
+                        .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+                    let spirv_path = out_dir.join(format!("example_{flat_name}.spv"));
+                    std::fs::write(&spirv_path, spirv.as_binary_u8()).unwrap();
+                    let common_code = match &common {
+                        Some(common) => format!("include_str!({:?})", common),
+                        None => "\"\"".to_string(),
+                    };
+                    writeln!(
+                        shaders,
+                        "    ({is_buffer}, {common_code}, include_str!({path:?}), include_bytes!({spirv_path:?})),"
+                    )
+                    .unwrap();
+                } else if name.ends_with(".png") {
+                    let (width, height, rgba) = decode_png(&path);
+                    let rgba_path = out_dir.join(format!("example_{flat_name}.rgba"));
+                    std::fs::write(&rgba_path, rgba).unwrap();
+                    writeln!(
+                        media,
+                        "    ({name:?}, {width}, {height}, include_bytes!({rgba_path:?})),"
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        shaders.push_str("];\n");
+        media.push_str("];\n");
+        std::fs::write(out_dir.join("example_shaders.rs"), shaders).unwrap();
+        std::fs::write(out_dir.join("example_media.rs"), media).unwrap();
+    }
+
+    /// Size and RGBA8 pixels of the PNG image at `path`.
+    fn decode_png(path: &Path) -> (u32, u32, Vec<u8>) {
+        let mut decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+        buffer.truncate(info.buffer_size());
+        let rgba = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer
+                .chunks(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buffer
+                .chunks(2)
+                .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+                .collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+            png::ColorType::Indexed => panic!("{}: palette wasn't expanded", path.display()),
+        };
+        (info.width, info.height, rgba)
     }
 }
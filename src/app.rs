@@ -4,58 +4,221 @@ use eframe::egui;
 use eframe::egui_wgpu;
 use eframe::egui_wgpu::RenderState;
 use eframe::epaint::PaintCallbackInfo;
-use eframe::wgpu::util::DeviceExt;
 use egui::panel::Side;
 use egui::Id;
 use egui_wgpu::wgpu;
 use egui_wgpu::wgpu::{CommandBuffer, CommandEncoder, Device, Queue, RenderPass};
 use egui_wgpu::{CallbackResources, ScreenDescriptor};
 use instant::Instant;
-use log::{error, info};
-#[cfg(not(target_arch = "wasm32"))]
-use notify::Watcher;
+use log::{error, info, warn};
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 
+pub mod compat;
+pub mod complete;
+pub mod cursors;
+pub mod dropped;
+pub mod examples;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod export;
+pub mod file;
+pub mod find;
+pub mod format;
+pub mod gallery;
+pub mod highlight;
+pub mod history;
+pub mod include;
+pub mod navigate;
+pub mod passes;
+pub mod project;
+pub mod recovery;
+pub mod render;
 mod shader;
+pub mod shadertoy;
+pub mod share;
+pub mod state;
+pub mod tweak;
+pub mod uniforms;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod watch;
 pub use shader::*;
 
 pub(crate) type Result<T> = anyhow::Result<T>;
 
-/// We derive Deserialize/Serialize so we can persist app state on shutdown.
+const OPEN_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+const SAVE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+const SAVE_AS_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::SHIFT),
+    egui::Key::S,
+);
+/// Id of the code editor's text field.
+const EDITOR_ID: &str = "shader_code";
+
+/// The editor window. Its state outlives restarts through [`state::State`].
 pub struct TemplateApp {
     wgpu_callback: WgpuCallback,
     render_state: RenderState,
     shader_dirty: bool,
     show_logger: bool,
     shader_editor: bool,
-    shader_content: String,
+    /// Whether saving formats the code first.
+    format_on_save: bool,
+    project: project::Project,
+    /// Tab of the editor that is shown.
+    selected_tab: EditorTab,
+    media: passes::MediaCache,
+    file: file::ShaderFile,
+    window_title: String,
     start_time: Instant,
     #[cfg(not(target_arch = "wasm32"))]
-    _vertex_shader_file_watcher: notify::RecommendedWatcher,
+    recent_files: file::RecentFiles,
+    #[cfg(target_arch = "wasm32")]
+    upload_tx: std::sync::mpsc::Sender<(String, String)>,
+    #[cfg(target_arch = "wasm32")]
+    upload_rx: std::sync::mpsc::Receiver<(String, String)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    export: export::ExportDialog,
+    /// Watches the open file, the files it includes and, when running from
+    /// the repo, the shader templates.
+    #[cfg(not(target_arch = "wasm32"))]
+    watcher: Option<watch::FileWatcher>,
+    /// Files the compiled passes `#include`.
     #[cfg(not(target_arch = "wasm32"))]
-    vertex_shader_file_watch_rx: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    included_files: Vec<std::path::PathBuf>,
+    /// The project as it is on disk, when it changed while the editor had
+    /// unsaved changes to the same files.
     #[cfg(not(target_arch = "wasm32"))]
-    _fragment_shader_file_watcher: notify::RecommendedWatcher,
+    file_conflict: Option<project::Project>,
+    /// Autosaves the editor. `None` where there is nowhere to keep snapshots.
+    recovery: Option<recovery::Recovery>,
+    /// Snapshot left by a session that crashed, until restored or discarded.
+    recovered: Option<state::State>,
+    /// The project as of the latest autosave.
+    autosaved: Option<project::Project>,
+    /// `egui` time of the latest autosave.
+    last_autosave: f64,
+    /// Every successful compile.
+    history: history::History,
+    /// The latest revision and its program, until its thumbnail is rendered.
     #[cfg(not(target_arch = "wasm32"))]
-    fragment_shader_file_watch_rx: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    pending_thumbnail: Option<(u64, passes::Program)>,
+    history_window: history::HistoryWindow,
+    gallery_window: gallery::GalleryWindow,
+    channel_prompt: dropped::ChannelPrompt,
+    completion: complete::Completion,
+    tweaker: tweak::Tweaker,
+    cursors: cursors::Cursors,
+    find_bar: find::FindBar,
+    scan: SourceScan,
+    /// Errors of the latest compile that point at a line.
+    diagnostics: Vec<navigate::Diagnostic>,
+    /// Selection the editor makes next.
+    jump: Option<navigate::Jump>,
 }
 
+/// Code of a newly added buffer pass.
+const NEW_BUFFER_CODE: &str = "void mainImage( out vec4 fragColor, in vec2 fragCoord )
+{
+    vec2 uv = fragCoord / iResolution.xy;
+    fragColor = vec4(uv, 0.0, 1.0);
+}
+";
+
+const NEW_COMMON_CODE: &str = "// Code shared by every pass.
+";
+
+/// Source tabs of the code editor.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EditorTab {
+    Common,
+    Pass(project::PassKind),
+}
+
+/// What the editor looks up in the project's sources: the uniform names to
+/// highlight, the completions and the declared uniforms. Kept until the
+/// sources or the names of the uniform values change, rather than found
+/// again every frame.
+#[derive(Default)]
+struct SourceScan {
+    /// Code of the sources these were found in.
+    sources: Vec<String>,
+    /// Names of the project's uniform values at the time.
+    uniform_names: Vec<String>,
+    user_uniforms: Vec<String>,
+    completions: Vec<complete::Item>,
+    declarations: Vec<uniforms::Declaration>,
+}
+
+impl SourceScan {
+    /// Scan `project` again if it changed since the last time.
+    fn update(&mut self, project: &project::Project) {
+        let unchanged = project
+            .sources()
+            .map(|source| &source.code)
+            .eq(&self.sources)
+            && project.uniforms.keys().eq(&self.uniform_names);
+        if unchanged {
+            return;
+        }
+        self.sources = project
+            .sources()
+            .map(|source| source.code.clone())
+            .collect();
+        self.uniform_names = project.uniforms.keys().cloned().collect();
+        self.user_uniforms = highlight::user_uniforms(project);
+        self.completions = complete::items(project);
+        self.declarations = uniforms::project_declarations(project);
+    }
+}
+
+/// Templates that are watched for changes when running from the repo.
+#[cfg(not(target_arch = "wasm32"))]
+const SHADER_TEMPLATES: [&str; 2] = ["src/app/shader.vert", "src/app/shader.frag"];
+
+/// Uniforms at binding 0, then the four channel textures at 1 to 4 and their
+/// samplers at 5 to 8, matching `shader.frag`.
 fn create_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty,
+        count: None,
+    };
+    let buffer = wgpu::BindingType::Buffer {
+        ty: wgpu::BufferBindingType::Uniform,
+        has_dynamic_offset: false,
+        min_binding_size: None,
+    };
+    let mut entries = vec![entry(0, buffer)];
+    for channel in 0..4 {
+        entries.push(entry(
+            1 + channel,
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+        ));
+    }
+    for channel in 0..4 {
+        entries.push(entry(
+            5 + channel,
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        ));
+    }
+    // The uniforms the shader declares itself.
+    entries.push(entry(9, buffer));
     device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
         label: Some("bind_group_layout"),
-        entries: &[wgpu::BindGroupLayoutEntry {
-            binding: 0,
-            visibility: wgpu::ShaderStages::FRAGMENT,
-            ty: wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            count: None,
-        }],
+        entries: &entries,
     })
 }
 
+/// Create the render pipeline for `target_format`: the swapchain format for
+/// the live view, or one of the offscreen formats (including `Rgba32Float`)
+/// for exports. No blending is set, so float targets work as well.
 fn create_pipeline(
     device: &wgpu::Device,
     vertex_spirv: Cow<'_, [u32]>,
@@ -106,104 +269,1120 @@ impl TemplateApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         egui_logger::builder().init().unwrap();
         let render_state = cc.wgpu_render_state.as_ref().expect("WGPU enabled");
-
-        let device = &render_state.device;
-
-        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: None,
-            contents: &[0u8; std::mem::size_of::<WgpuUniform>()],
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
-        });
-        let bind_group_layout = create_bind_group_layout(device);
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: None,
-            layout: &bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
-        });
         render_state
             .renderer
             .write()
             .callback_resources
-            .insert(TriangleRenderResources {
-                pipeline: None,
-                bind_group,
-                uniform_buffer,
-            });
+            .insert(TriangleRenderResources { renderer: None });
+        let example = &examples::EXAMPLES[0];
+        let project = example.project();
 
         #[cfg(not(target_arch = "wasm32"))]
         {
-            let mut vertex_shader_file_watcher;
-            let vertex_shader_file_watch_rx;
-            {
-                let (tx, rx) = std::sync::mpsc::channel();
-                vertex_shader_file_watcher =
-                    notify::RecommendedWatcher::new(tx, notify::Config::default()).unwrap();
-                vertex_shader_file_watcher
-                    .watch(
-                        std::path::Path::new("src/app/shader.vert"),
-                        notify::RecursiveMode::NonRecursive,
-                    )
-                    .unwrap();
-                vertex_shader_file_watch_rx = rx;
-            }
-            let mut fragment_shader_file_watcher;
-            let fragment_shader_file_watch_rx;
-            {
-                let (tx, rx) = std::sync::mpsc::channel();
-                fragment_shader_file_watcher =
-                    notify::RecommendedWatcher::new(tx, notify::Config::default()).unwrap();
-                fragment_shader_file_watcher
-                    .watch(
-                        std::path::Path::new("src/app/shader.frag"),
-                        notify::RecursiveMode::NonRecursive,
-                    )
-                    .unwrap();
-                fragment_shader_file_watch_rx = rx;
-            }
-            Self {
+            let watcher = match watch::FileWatcher::new() {
+                Ok(mut watcher) => {
+                    for template in SHADER_TEMPLATES.map(std::path::Path::new) {
+                        if template.exists() {
+                            if let Err(e) = watcher.watch(template) {
+                                warn!("Error watching {}: {}", template.display(), e);
+                            }
+                        }
+                    }
+                    Some(watcher)
+                }
+                Err(e) => {
+                    error!("Error creating file watcher: {}", e);
+                    None
+                }
+            };
+            let mut app = Self {
                 wgpu_callback: WgpuCallback::default(),
                 render_state: render_state.clone(),
                 shader_dirty: true,
                 show_logger: true,
                 shader_editor: true,
-                shader_content: include_str!("app/default.glsl").to_string(),
+                format_on_save: false,
+                file: file::ShaderFile::new(example.file_name, None, &project),
+                project,
+                selected_tab: EditorTab::Pass(project::PassKind::Image),
+                media: passes::MediaCache::default(),
+                window_title: String::new(),
                 start_time: Instant::now(),
-                _vertex_shader_file_watcher: vertex_shader_file_watcher,
-                vertex_shader_file_watch_rx,
-                _fragment_shader_file_watcher: fragment_shader_file_watcher,
-                fragment_shader_file_watch_rx,
-            }
+                recent_files: file::RecentFiles::default(),
+                export: export::ExportDialog::default(),
+                watcher,
+                file_conflict: None,
+                included_files: Vec::new(),
+                recovery: recovery::Recovery::new(),
+                recovered: None,
+                autosaved: None,
+                last_autosave: 0.0,
+                history: cc.storage.map(history::History::load).unwrap_or_default(),
+                pending_thumbnail: None,
+                history_window: history::HistoryWindow::default(),
+                gallery_window: gallery::GalleryWindow::default(),
+                channel_prompt: dropped::ChannelPrompt::default(),
+                completion: complete::Completion::default(),
+                tweaker: tweak::Tweaker::default(),
+                cursors: cursors::Cursors::default(),
+                find_bar: find::FindBar::default(),
+                scan: SourceScan::default(),
+                diagnostics: Vec::new(),
+                jump: None,
+            };
+            app.restore(cc.storage);
+            app.start_recovery();
+            app
         }
         #[cfg(target_arch = "wasm32")]
         {
-            Self {
+            let (upload_tx, upload_rx) = std::sync::mpsc::channel();
+            let mut app = Self {
                 wgpu_callback: WgpuCallback::default(),
                 render_state: render_state.clone(),
                 shader_dirty: true,
                 show_logger: true,
                 shader_editor: false,
+                format_on_save: false,
                 start_time: Instant::now(),
-                shader_content: include_str!("app/default.glsl").to_string(),
+                file: file::ShaderFile::new(example.file_name, None, &project),
+                project,
+                selected_tab: EditorTab::Pass(project::PassKind::Image),
+                media: passes::MediaCache::default(),
+                window_title: String::new(),
+                upload_tx,
+                upload_rx,
+                recovery: recovery::Recovery::new(),
+                recovered: None,
+                autosaved: None,
+                last_autosave: 0.0,
+                history: cc.storage.map(history::History::load).unwrap_or_default(),
+                history_window: history::HistoryWindow::default(),
+                gallery_window: gallery::GalleryWindow::default(),
+                channel_prompt: dropped::ChannelPrompt::default(),
+                completion: complete::Completion::default(),
+                tweaker: tweak::Tweaker::default(),
+                cursors: cursors::Cursors::default(),
+                find_bar: find::FindBar::default(),
+                scan: SourceScan::default(),
+                diagnostics: Vec::new(),
+                jump: None,
+            };
+            app.restore(cc.storage);
+            app.start_recovery();
+            if let Some((name, project)) = share::take_from_location() {
+                info!("Opened {name} from the share link");
+                app.load_project(name, None, project);
+            }
+            app
+        }
+    }
+
+    /// Bring back the editor as it was left, from the state `save` stored.
+    /// Unusable state is logged and ignored.
+    fn restore(&mut self, storage: Option<&dyn eframe::Storage>) {
+        let state = match storage.map(state::State::load) {
+            Some(Ok(Some(state))) => state,
+            Some(Err(e)) => {
+                warn!("Ignoring the stored editor state: {:#}", e);
+                return;
+            }
+            Some(Ok(None)) | None => return,
+        };
+        self.apply_state(state);
+    }
+
+    /// Open the project of `state` and lay out the editor as it describes.
+    fn apply_state(&mut self, state: state::State) {
+        self.show_logger = state.show_logger;
+        self.shader_editor = state.shader_editor;
+        self.history_window.open = state.show_history;
+        self.format_on_save = state.format_on_save;
+        self.gallery_window.directory = state.gallery_directory.clone();
+        let project = state.project();
+        let saved = if state.dirty {
+            project::Project::default()
+        } else {
+            project.clone()
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let (project, saved, path) = {
+            self.recent_files = file::RecentFiles::new(state.recent_files.clone());
+            match &state.file_path {
+                Some(file_path) => match file::read(file_path) {
+                    // Unchanged editors follow edits made while closed.
+                    Ok(disk) if !state.dirty => (disk.clone(), disk, state.file_path.clone()),
+                    Ok(disk) => (project, disk, state.file_path.clone()),
+                    Err(e) => {
+                        warn!(
+                            "Restoring {} without its file: {:#}",
+                            file_path.display(),
+                            e
+                        );
+                        (project, project::Project::default(), None)
+                    }
+                },
+                None => (project, saved, None),
+            }
+        };
+        #[cfg(target_arch = "wasm32")]
+        let path = state.file_path.clone();
+        self.set_file(file::ShaderFile {
+            path,
+            name: state.file_name,
+            saved,
+        });
+        self.project = project;
+        self.shader_dirty = true;
+    }
+
+    /// Replace the open project with `project`, loaded from the file `name`.
+    fn load_project(
+        &mut self,
+        name: String,
+        path: Option<std::path::PathBuf>,
+        project: project::Project,
+    ) {
+        self.set_file(file::ShaderFile::new(name, path, &project));
+        self.project = project;
+        self.selected_tab = EditorTab::Pass(project::PassKind::Image);
+        self.shader_dirty = true;
+    }
+
+    /// The editor as [`state::State`], for storage and autosaves.
+    fn state(&self) -> state::State {
+        let mut state = state::State::new(&self.project);
+        state.show_logger = self.show_logger;
+        state.shader_editor = self.shader_editor;
+        state.show_history = self.history_window.open;
+        state.format_on_save = self.format_on_save;
+        state.gallery_directory = self.gallery_window.directory.clone();
+        state.file_name = self.file.name.clone();
+        state.file_path = self.file.path.clone();
+        state.dirty = self.file.is_dirty(&self.project);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            state.recent_files = self.recent_files.paths().to_vec();
+        }
+        state
+    }
+
+    /// Mark the session as running, and hold on to the snapshot of the
+    /// previous one if it crashed with changes the editor doesn't have.
+    fn start_recovery(&mut self) {
+        let Some(recovery) = &self.recovery else {
+            return;
+        };
+        match recovery.start() {
+            Ok(Some(snapshot)) if snapshot.dirty && snapshot.project() != self.project => {
+                warn!("An earlier session didn't shut down cleanly");
+                self.recovered = Some(snapshot);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Ignoring the autosave of the last session: {:#}", e),
+        }
+    }
+
+    /// Snapshot the editor every [`recovery::AUTOSAVE_INTERVAL`] seconds
+    /// while it has unsaved changes. Waits while a recovered snapshot is
+    /// pending, so it isn't overwritten.
+    fn autosave(&mut self, now: f64) {
+        if self.recovered.is_some() || now - self.last_autosave < recovery::AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_autosave = now;
+        let Some(recovery) = &self.recovery else {
+            return;
+        };
+        if self.autosaved.as_ref() == Some(&self.project) {
+            return;
+        }
+        let result = if self.file.is_dirty(&self.project) {
+            recovery.snapshot(&self.state())
+        } else {
+            recovery.discard()
+        };
+        match result {
+            Ok(()) => self.autosaved = Some(self.project.clone()),
+            Err(e) => error!("Error autosaving: {:#}", e),
+        }
+    }
+
+    /// Open the gallery entry at `index`.
+    fn open_gallery_entry(&mut self, index: usize) {
+        let Some(entry) = self.gallery_window.gallery().entries.get(index) else {
+            return;
+        };
+        match &entry.origin {
+            gallery::Origin::Bundled => {
+                let (name, project) = (entry.name.clone(), entry.project.clone());
+                info!("Opened {}", name);
+                self.load_project(name, None, project);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            gallery::Origin::File(path) => self.open_path(path.clone()),
+            #[cfg(target_arch = "wasm32")]
+            gallery::Origin::File(_) => {}
+        }
+    }
+
+    /// Offer the snapshot of a session that crashed.
+    fn recovery_ui(&mut self, ctx: &egui::Context) {
+        let Some(snapshot) = &self.recovered else {
+            return;
+        };
+        let mut restore = None;
+        egui::Window::new("Recover unsaved work")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "An earlier session didn't shut down cleanly. It autosaved changes to {} that weren't saved.",
+                    snapshot.file_name
+                ));
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Restore")
+                        .on_hover_text("Open the autosaved version in place of the current one")
+                        .clicked()
+                    {
+                        restore = Some(true);
+                    }
+                    if ui
+                        .button("Discard")
+                        .on_hover_text("Delete the autosave")
+                        .clicked()
+                    {
+                        restore = Some(false);
+                    }
+                });
+            });
+        match restore {
+            Some(true) => {
+                let snapshot = self.recovered.take().unwrap();
+                info!("Restored the autosave of {}", snapshot.file_name);
+                self.apply_state(snapshot);
+                self.autosaved = None;
+            }
+            Some(false) => {
+                self.recovered = None;
+                if let Some(recovery) = &self.recovery {
+                    if let Err(e) = recovery.discard() {
+                        error!("Error deleting the autosave: {:#}", e);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Bind the editor to `file`, moving the file watches over to its files.
+    fn set_file(&mut self, file: file::ShaderFile) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(watcher) = &mut self.watcher {
+            for path in self.file.paths() {
+                watcher.unwatch(&path);
+            }
+            for path in file.paths() {
+                if let Err(e) = watcher.watch(&path) {
+                    warn!("Error watching {}: {}", path.display(), e);
+                }
+            }
+            self.file_conflict = None;
+        }
+        self.file = file;
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_file_changes(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let changed = watcher.changed_files();
+        let open_files: Vec<_> = self
+            .file
+            .paths()
+            .iter()
+            .filter_map(|path| watch::normalize(path).ok())
+            .collect();
+        for path in changed {
+            if open_files.contains(&path) {
+                self.reload_from_disk(&path);
+            } else if self.included_files.contains(&path) {
+                info!("Included file {} modified", path.display());
+                self.shader_dirty = true;
+            } else {
+                info!("Shader template {} modified", path.display());
+                self.shader_dirty = true;
+            }
+        }
+    }
+
+    /// Pick up a change to one of the project's files made by another editor.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_from_disk(&mut self, path: &std::path::Path) {
+        let directory = self.file.directory().to_path_buf();
+        let is_source = |source: &project::Source| {
+            watch::normalize(&directory.join(&source.path)).is_ok_and(|p| p == path)
+        };
+        let mut disk = self.file.saved.clone();
+        let mut changed_source = None;
+        let index = disk.sources().position(is_source);
+        if let Some(index) = index {
+            match std::fs::read_to_string(path) {
+                Ok(code) => disk.sources_mut().nth(index).unwrap().code = code,
+                Err(e) => {
+                    error!("Error reloading {}: {}", path.display(), e);
+                    return;
+                }
+            }
+            changed_source = Some(index);
+        } else {
+            match project::Project::load(path) {
+                Ok(project) => disk = project,
+                Err(e) => {
+                    error!("Error reloading {}: {:#}", path.display(), e);
+                    return;
+                }
+            }
+        }
+        if disk == self.file.saved {
+            // Our own save, or a touch without changes.
+            return;
+        }
+        let source_is_clean = changed_source.is_some_and(|index| {
+            self.project.sources().nth(index) == self.file.saved.sources().nth(index)
+        });
+        if disk == self.project {
+            // Both sides made the same change.
+            self.file.saved = disk;
+        } else if !self.file.is_dirty(&self.project) {
+            info!("Reloaded {} from disk", path.display());
+            self.file.saved = disk.clone();
+            self.project = disk;
+            self.shader_dirty = true;
+        } else if let (true, Some(index)) = (source_is_clean, changed_source) {
+            // Other files have unsaved changes, but not this one.
+            info!("Reloaded {} from disk", path.display());
+            let code = disk.sources().nth(index).unwrap().code.clone();
+            self.project.sources_mut().nth(index).unwrap().code = code;
+            self.file.saved = disk;
+            self.shader_dirty = true;
+        } else {
+            warn!(
+                "{} changed on disk while the editor has unsaved changes",
+                path.display()
+            );
+            self.file_conflict = Some(disk);
+        }
+    }
+
+    /// Ask which side wins after the open file changed on disk and in the editor.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file_conflict_ui(&mut self, ctx: &egui::Context) {
+        let Some(disk) = &self.file_conflict else {
+            return;
+        };
+        enum Resolution {
+            Reload,
+            KeepEditor,
+            Overwrite,
+        }
+        let mut resolution = None;
+        egui::Window::new("File changed on disk")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} was changed by another program, and the editor has unsaved changes.",
+                    self.file.name
+                ));
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("Reload from disk")
+                        .on_hover_text("Discard the changes in the editor")
+                        .clicked()
+                    {
+                        resolution = Some(Resolution::Reload);
+                    }
+                    if ui
+                        .button("Keep editor version")
+                        .on_hover_text("Keep editing; saving will overwrite the file")
+                        .clicked()
+                    {
+                        resolution = Some(Resolution::KeepEditor);
+                    }
+                    if ui
+                        .button("Overwrite file")
+                        .on_hover_text("Save the editor version now")
+                        .clicked()
+                    {
+                        resolution = Some(Resolution::Overwrite);
+                    }
+                });
+            });
+        match resolution {
+            Some(Resolution::Reload) => {
+                self.project = disk.clone();
+                self.file.saved = disk.clone();
+                self.shader_dirty = true;
+            }
+            Some(Resolution::KeepEditor) => {
+                self.file.saved = disk.clone();
+            }
+            Some(Resolution::Overwrite) => {
+                self.file.saved = disk.clone();
+                self.save_file(false);
+            }
+            None => return,
+        }
+        self.file_conflict = None;
+    }
+
+    /// Open the first shader of a Shadertoy export as an unsaved project.
+    /// Media is looked up in `directory`.
+    fn open_shadertoy(&mut self, json: &str, directory: &std::path::Path) -> Result<()> {
+        let mut imports = shadertoy::import(json, directory)?;
+        if imports.len() > 1 {
+            warn!(
+                "The export holds {} shaders, opening the first one",
+                imports.len()
+            );
+        }
+        let shadertoy::Import {
+            mut project,
+            warnings,
+            ..
+        } = imports.swap_remove(0);
+        for warning in warnings {
+            warn!("{warning}");
+        }
+        // Unsaved projects resolve their paths against the working directory.
+        project.relocate(directory, std::path::Path::new("."));
+        let name = format!("{}.{}", project.name, project::PROJECT_EXTENSION);
+        info!("Imported {} from Shadertoy", project.name);
+        self.load_project(name, None, project);
+        // Not saved anywhere yet.
+        self.file.saved = project::Project::default();
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_path(&mut self, path: std::path::PathBuf) {
+        if file::is_project_path(&path) {
+            let shadertoy_json = std::fs::read_to_string(&path)
+                .ok()
+                .filter(|json| !project::is_manifest(json) && shadertoy::is_shadertoy_json(json));
+            if let Some(json) = shadertoy_json {
+                if let Err(e) = self.open_shadertoy(&json, project::directory(&path)) {
+                    error!("Error importing {}: {:#}", path.display(), e);
+                }
+                return;
+            }
+        }
+        match file::read(&path) {
+            Ok(project) => {
+                info!("Opened {}", path.display());
+                self.recent_files.add(path.clone());
+                self.load_project(file_name(&path), Some(path), project);
+            }
+            Err(e) => {
+                error!("Error opening {}: {:#}", path.display(), e);
+                self.recent_files.remove(&path);
+            }
+        }
+    }
+
+    fn open_file(&mut self) {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = file::open_dialog() {
+            self.open_path(path);
+        }
+        #[cfg(target_arch = "wasm32")]
+        file::upload(self.upload_tx.clone());
+    }
+
+    /// Write the project as a Shadertoy export, listing what it can't hold.
+    fn export_shadertoy(&mut self) {
+        let export = match shadertoy::export(&self.project) {
+            Ok(export) => export,
+            Err(e) => {
+                error!("Error exporting to Shadertoy: {:#}", e);
+                return;
+            }
+        };
+        for warning in &export.warnings {
+            warn!("{warning}");
+        }
+        let mut name = std::path::PathBuf::from(&self.file.name);
+        name.set_extension("shadertoy.json");
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = match file::shadertoy_dialog(&self.file, &name) {
+            Some(path) => std::fs::write(&path, &export.json).map(|()| path),
+            None => return,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let result = file::download(&name.to_string_lossy(), &export.json).map(|()| name);
+        match result {
+            Ok(path) => info!("Exported {} for Shadertoy", path.display()),
+            Err(e) => error!("Error exporting to Shadertoy: {:#}", e),
+        }
+    }
+
+    /// Open the shader or project among `files`, and ask which channels the
+    /// images go to. Images dropped with a project are its media instead.
+    fn open_dropped(&mut self, files: &[egui::DroppedFile]) {
+        use dropped::Kind;
+        for file in files {
+            let name = dropped::name(file);
+            if let (Some(Kind::Image), Some(bytes)) = (Kind::of(&name), &file.bytes) {
+                self.media.add_file(&name, bytes.clone());
+            }
+        }
+        let project = files
+            .iter()
+            .find(|file| Kind::of(&dropped::name(file)) == Some(Kind::Project));
+        if let Some(file) = project {
+            if let Err(e) = self.open_dropped_project(file, files) {
+                error!("Error opening {}: {:#}", dropped::name(file), e);
+            }
+            return;
+        }
+        let mut opened = false;
+        for file in files {
+            let name = dropped::name(file);
+            match Kind::of(&name) {
+                Some(Kind::Shader) if opened => {
+                    warn!("Ignoring {name}: only one shader opens at a time");
+                }
+                Some(Kind::Shader) => {
+                    opened = true;
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(path) = file.path.clone() {
+                        self.open_path(path);
+                        continue;
+                    }
+                    match dropped::read_text(file) {
+                        Ok(code) => {
+                            info!("Opened {}", name);
+                            let project = project::Project::single(&name, code);
+                            self.load_project(name, None, project);
+                        }
+                        Err(e) => error!("Error opening {}: {:#}", name, e),
+                    }
+                }
+                Some(Kind::Image) => {
+                    let path = match &file.path {
+                        Some(path) => project::relative_path(path, self.file.directory()),
+                        None => std::path::PathBuf::from(name),
+                    };
+                    self.channel_prompt.push(path);
+                }
+                Some(Kind::Project) => {}
+                None => warn!("Ignoring {name}: not a shader, project or image"),
+            }
+        }
+    }
+
+    /// Open the manifest or Shadertoy export `file`, with the sources among
+    /// `files` on the web.
+    fn open_dropped_project(
+        &mut self,
+        file: &egui::DroppedFile,
+        files: &[egui::DroppedFile],
+    ) -> Result<()> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = file.path.clone() {
+            self.open_path(path);
+            return Ok(());
+        }
+        let name = dropped::name(file);
+        let json = dropped::read_text(file)?;
+        if !project::is_manifest(&json) && shadertoy::is_shadertoy_json(&json) {
+            return self.open_shadertoy(&json, std::path::Path::new("."));
+        }
+        let project = dropped::project(&json, files)?;
+        info!("Opened {}", name);
+        self.load_project(name, None, project);
+        Ok(())
+    }
+
+    /// Copy a link to this page that opens the project; see [`share`].
+    #[cfg(target_arch = "wasm32")]
+    fn copy_share_link(&self, ctx: &egui::Context) {
+        let link = match share::encode(&self.file.name, &self.project) {
+            Ok(fragment) => share::link(&fragment),
+            Err(e) => {
+                error!("Error creating the share link: {:#}", e);
+                return;
+            }
+        };
+        let Some(link) = link else {
+            error!("Error creating the share link: the page has no URL");
+            return;
+        };
+        for file in share::missing_files(&self.project) {
+            warn!("{file} isn't part of the share link");
+        }
+        if link.len() > share::MAX_LINK_LENGTH {
+            warn!(
+                "The share link is {} characters long, some sites may cut it short",
+                link.len()
+            );
+        }
+        info!("Copied the share link ({} characters)", link.len());
+        ctx.copy_text(link);
+    }
+
+    fn save_file(&mut self, save_as: bool) {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let single = self.project.is_single_file();
+            let path = match (&self.file.path, save_as) {
+                // A lone shader that gained passes has to become a project.
+                (Some(path), false) if single || self.file.is_project() => Some(path.clone()),
+                _ => file::save_dialog(&self.file, !single),
+            };
+            let Some(mut path) = path else {
+                return;
+            };
+            // Only once there's a path, so cancelling leaves the code as it is.
+            self.format_before_saving();
+            let result = if single && !file::is_project_path(&path) {
+                self.project.passes[0].source.path = file_name(&path).into();
+                std::fs::write(&path, &self.project.image().source.code).map_err(Into::into)
+            } else {
+                path.set_extension(project::PROJECT_EXTENSION);
+                self.project
+                    .relocate(self.file.directory(), project::directory(&path));
+                self.project.save(&path)
+            };
+            match result {
+                Ok(()) => {
+                    info!("Saved {}", path.display());
+                    self.set_file(file::ShaderFile::new(
+                        file_name(&path),
+                        Some(path.clone()),
+                        &self.project,
+                    ));
+                    self.recent_files.add(path);
+                }
+                Err(e) => error!("Error saving {}: {:#}", path.display(), e),
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = save_as;
+            self.format_before_saving();
+            if !self.project.is_single_file() {
+                warn!("Only the image pass can be downloaded on the web");
+            }
+            match file::download(&self.file.name, &self.project.image().source.code) {
+                Ok(()) => self.file.saved = self.project.clone(),
+                Err(e) => error!("Error downloading {}: {}", self.file.name, e),
+            }
+        }
+    }
+
+    /// Tabs choosing the pass shown in the editor, and a menu to add buffers.
+    fn pass_tabs_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            let mut remove_common = false;
+            if self.project.common.is_some() {
+                ui.selectable_value(&mut self.selected_tab, EditorTab::Common, "Common")
+                    .context_menu(|ui| {
+                        if ui.button("Remove common code").clicked() {
+                            remove_common = true;
+                            ui.close_menu();
+                        }
+                    });
+            }
+            if remove_common {
+                self.project.common = None;
+                self.selected_tab = EditorTab::Pass(project::PassKind::Image);
+                self.shader_dirty = true;
+            }
+            let mut removed = None;
+            for kind in self.project.passes.iter().map(|pass| pass.kind) {
+                let tab =
+                    ui.selectable_value(&mut self.selected_tab, EditorTab::Pass(kind), kind.name());
+                if kind != project::PassKind::Image {
+                    tab.context_menu(|ui| {
+                        if ui.button("Remove pass").clicked() {
+                            removed = Some(kind);
+                            ui.close_menu();
+                        }
+                    });
+                }
+            }
+            if let Some(kind) = removed {
+                self.project.remove_pass(kind);
+                self.shader_dirty = true;
+            }
+            ui.menu_button("+", |ui| {
+                if ui
+                    .add_enabled(self.project.common.is_none(), egui::Button::new("Common"))
+                    .clicked()
+                {
+                    self.project.common =
+                        Some(project::Source::new("common.glsl", NEW_COMMON_CODE));
+                    self.selected_tab = EditorTab::Common;
+                    self.shader_dirty = true;
+                    ui.close_menu();
+                }
+                for kind in project::PassKind::ALL {
+                    if !kind.is_buffer() {
+                        continue;
+                    }
+                    let button = egui::Button::new(kind.name());
+                    if ui
+                        .add_enabled(self.project.pass(kind).is_none(), button)
+                        .clicked()
+                    {
+                        self.project.add_pass(kind, NEW_BUFFER_CODE);
+                        self.selected_tab = EditorTab::Pass(kind);
+                        self.shader_dirty = true;
+                        ui.close_menu();
+                    }
+                }
+            });
+        });
+    }
+
+    /// Source of the selected editor tab, falling back to the image pass when
+    /// the tab is gone.
+    fn selected_source(&mut self) -> &mut project::Source {
+        let exists = match self.selected_tab {
+            EditorTab::Common => self.project.common.is_some(),
+            EditorTab::Pass(kind) => self.project.pass(kind).is_some(),
+        };
+        if !exists {
+            self.selected_tab = EditorTab::Pass(project::PassKind::Image);
+        }
+        match self.selected_tab {
+            EditorTab::Common => self.project.common.as_mut().unwrap(),
+            EditorTab::Pass(kind) => &mut self.project.pass_mut(kind).unwrap().source,
+        }
+    }
+
+    /// Position of the selected source in [`project::Project::sources`].
+    fn selected_source_index(&mut self) -> usize {
+        let selected: *const project::Source = self.selected_source();
+        self.project
+            .sources()
+            .position(|source| std::ptr::eq(source, selected))
+            .unwrap_or_default()
+    }
+
+    /// Show the source at `jump.source` in [`project::Project::sources`] and
+    /// make the selection once the editor shows it.
+    fn jump_to(&mut self, jump: navigate::Jump) {
+        let common = usize::from(self.project.common.is_some());
+        self.selected_tab = match jump.source.checked_sub(common) {
+            None => EditorTab::Common,
+            Some(pass) => EditorTab::Pass(
+                self.project
+                    .passes
+                    .get(pass)
+                    .map_or(project::PassKind::Image, |pass| pass.kind),
+            ),
+        };
+        self.shader_editor = true;
+        self.jump = Some(jump);
+    }
+
+    /// Put the cursor at the start of line `line` of source `source`.
+    fn go_to_line(&mut self, source: usize, line: usize) {
+        let Some(code) = self.project.sources().nth(source).map(|s| &s.code) else {
+            return;
+        };
+        if let Some(range) = navigate::line_range(code, line) {
+            self.jump_to(navigate::Jump {
+                source,
+                range: range.start..range.start,
+                focus: true,
+            });
+        }
+    }
+
+    /// Select the name of the function or macro `name` where it's defined.
+    fn go_to_definition(&mut self, name: &str) {
+        match navigate::definition(&self.project, name) {
+            Some((source, range)) => self.jump_to(navigate::Jump {
+                source,
+                range,
+                focus: true,
+            }),
+            None => info!("{name} isn't defined in the project"),
+        }
+    }
+
+    fn go_to_definition_at_cursor(&mut self, ctx: &egui::Context) {
+        let code = &self.selected_source().code;
+        let selection = navigate::selection(ctx, Id::new(EDITOR_ID), code);
+        let name = code[complete::word_at(code, selection.start)].to_string();
+        if !name.is_empty() {
+            self.go_to_definition(&name);
+        }
+    }
+
+    /// Format every source, if formatting on save is on.
+    fn format_before_saving(&mut self) {
+        if self.format_on_save {
+            for source in 0..self.project.sources().count() {
+                self.format_source(source);
+            }
+        }
+    }
+
+    /// Format the code of source `source`, in [`project::Project::sources`]
+    /// order, if that doesn't change the compiled shader.
+    fn format_source(&mut self, source: usize) {
+        let includes = include::Includes::new(self.file.directory(), &self.project.include_paths);
+        match format::verified(&self.project, source, &includes) {
+            Ok(Some(code)) => {
+                if let Some(source) = self.project.sources_mut().nth(source) {
+                    source.code = code;
+                }
+                self.cursors.clear();
+                self.shader_dirty = true;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                let name = self
+                    .project
+                    .sources()
+                    .nth(source)
+                    .map(|s| s.path.display().to_string());
+                warn!("Not formatting {}: {:#}", name.unwrap_or_default(), e);
+            }
+        }
+    }
+
+    /// Render the thumbnail of the latest revision, if it's still missing.
+    /// Waiting until the revision settles spares a render for every compile
+    /// while typing.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn render_pending_thumbnail(&mut self) {
+        let Some((revision, program)) = self.pending_thumbnail.take() else {
+            return;
+        };
+        match history::render_thumbnail(
+            &self.render_state.device,
+            &self.render_state.queue,
+            &program,
+        ) {
+            Ok(thumbnail) => self.history.set_thumbnail(revision, thumbnail),
+            Err(e) => warn!("Error rendering the revision thumbnail: {:#}", e),
+        }
+    }
+
+    fn open_find_bar(&mut self, mode: find::Mode) {
+        self.shader_editor = true;
+        self.find_bar.open(mode);
+    }
+
+    /// The find bar above the editor.
+    fn find_bar_ui(&mut self, ui: &mut egui::Ui) {
+        let id = Id::new(EDITOR_ID);
+        let index = self.selected_source_index();
+        let Some(source) = self.project.sources_mut().nth(index) else {
+            return;
+        };
+        let selection = navigate::selection(ui.ctx(), id, &source.code);
+        let (action, changed) = self.find_bar.ui(ui, &mut source.code, selection);
+        if changed {
+            self.cursors.clear();
+            self.shader_dirty = true;
+        }
+        match action {
+            Some(find::FindAction::Select(range)) => {
+                self.jump = Some(navigate::Jump {
+                    source: index,
+                    range,
+                    focus: false,
+                });
+            }
+            Some(find::FindAction::GoToLine(line)) => self.go_to_line(index, line),
+            Some(find::FindAction::Close) => ui.memory_mut(|memory| memory.request_focus(id)),
+            None => {}
+        }
+    }
+
+    /// Compile errors, which go to their line when clicked.
+    fn diagnostics_ui(&mut self, ui: &mut egui::Ui) {
+        let mut clicked = None;
+        for diagnostic in &self.diagnostics {
+            let text = format!(
+                "{}:{}: {}",
+                diagnostic.file, diagnostic.line, diagnostic.message
+            );
+            let text = egui::RichText::new(text).color(ui.visuals().error_fg_color);
+            match navigate::source_index(&self.project, &diagnostic.file) {
+                Some(source) => {
+                    if ui.link(text).on_hover_text("Go to the line").clicked() {
+                        clicked = Some((source, diagnostic.line));
+                    }
+                }
+                // In an included file.
+                None => {
+                    ui.label(text);
+                }
             }
         }
+        if let Some((source, line)) = clicked {
+            self.go_to_line(source, line);
+        }
+    }
+
+    /// Values of the uniforms the shader declares, like `uniform float speed;`.
+    fn uniforms_ui(&mut self, ui: &mut egui::Ui) {
+        let declared = &self.scan.declarations;
+        if declared.is_empty() {
+            return;
+        }
+        egui::CollapsingHeader::new("Uniforms")
+            .id_salt("uniforms")
+            .show(ui, |ui| {
+                uniforms::ui(ui, declared, &mut self.project.uniforms);
+            });
+    }
+
+    /// Inputs and sampler settings of the channels of the selected pass.
+    fn channels_ui(&mut self, ui: &mut egui::Ui) {
+        let buffers: Vec<project::PassKind> = self
+            .project
+            .passes
+            .iter()
+            .map(|pass| pass.kind)
+            .filter(|kind| kind.is_buffer())
+            .collect();
+        #[cfg(not(target_arch = "wasm32"))]
+        let directory = self.file.directory().to_path_buf();
+        let EditorTab::Pass(selected) = self.selected_tab else {
+            return;
+        };
+        let Some(pass) = self.project.pass_mut(selected) else {
+            return;
+        };
+        let mut changed = false;
+        egui::CollapsingHeader::new("Channels")
+            .id_salt("channels")
+            .show(ui, |ui| {
+                egui::Grid::new("channel_grid").show(ui, |ui| {
+                    for (index, channel) in pass.channels.iter_mut().enumerate() {
+                        ui.label(format!("iChannel{index}"));
+                        let selected = channel
+                            .as_ref()
+                            .map_or("None".to_string(), |channel| channel.input.label());
+                        egui::ComboBox::from_id_salt(("channel_input", index))
+                            .selected_text(selected)
+                            .show_ui(ui, |ui| {
+                                if ui.selectable_label(channel.is_none(), "None").clicked() {
+                                    *channel = None;
+                                    changed = true;
+                                }
+                                for &kind in &buffers {
+                                    let input = project::ChannelInput::Buffer { pass: kind };
+                                    let is_selected =
+                                        channel.as_ref().is_some_and(|c| c.input == input);
+                                    if ui.selectable_label(is_selected, kind.name()).clicked() {
+                                        *channel = Some(project::Channel {
+                                            input,
+                                            sampler: project::Sampler::buffer(),
+                                        });
+                                        changed = true;
+                                    }
+                                }
+                                #[cfg(not(target_arch = "wasm32"))]
+                                if ui.button("Texture…").clicked() {
+                                    if let Some(path) = file::image_dialog(&directory) {
+                                        *channel = Some(project::Channel {
+                                            input: project::ChannelInput::Texture {
+                                                path: project::relative_path(&path, &directory),
+                                            },
+                                            sampler: project::Sampler::texture(),
+                                        });
+                                        changed = true;
+                                    }
+                                }
+                            });
+                        if let Some(channel) = channel {
+                            let sampler = &mut channel.sampler;
+                            egui::ComboBox::from_id_salt(("channel_filter", index))
+                                .selected_text(sampler.filter.name())
+                                .show_ui(ui, |ui| {
+                                    for filter in project::Filter::ALL {
+                                        changed |= ui
+                                            .selectable_value(
+                                                &mut sampler.filter,
+                                                filter,
+                                                filter.name(),
+                                            )
+                                            .changed();
+                                    }
+                                });
+                            egui::ComboBox::from_id_salt(("channel_wrap", index))
+                                .selected_text(sampler.wrap.name())
+                                .show_ui(ui, |ui| {
+                                    for wrap in project::Wrap::ALL {
+                                        changed |= ui
+                                            .selectable_value(&mut sampler.wrap, wrap, wrap.name())
+                                            .changed();
+                                    }
+                                });
+                            changed |= ui.checkbox(&mut sampler.vflip, "vflip").changed();
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        if changed {
+            self.shader_dirty = true;
+        }
+    }
+}
+
+/// Dim the window while files are dragged over it.
+fn drop_overlay(ctx: &egui::Context) {
+    if ctx.input(|i| i.raw.hovered_files.is_empty()) {
+        return;
     }
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        Id::new("drop_overlay"),
+    ));
+    let rect = ctx.screen_rect();
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(192));
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        "Drop shaders, projects or images to open them",
+        egui::FontId::proportional(20.0),
+        egui::Color32::WHITE,
+    );
+}
+
+fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
 }
 
 struct TriangleRenderResources {
-    pipeline: Option<wgpu::RenderPipeline>,
-    bind_group: wgpu::BindGroup,
-    uniform_buffer: wgpu::Buffer,
+    renderer: Option<passes::PassRenderer>,
 }
 #[derive(Default, Clone)]
 struct WgpuCallback {
     uniform: WgpuUniform,
+    /// Values of the uniforms the shader declares itself.
+    uniform_values: BTreeMap<String, project::UniformValue>,
 }
 #[derive(Clone)]
 #[std140::repr_std140]
-struct WgpuUniform {
+pub struct WgpuUniform {
     resolution: std140::vec2,
     time: std140::float,
     time_delta: std140::float,
@@ -212,6 +1391,8 @@ struct WgpuUniform {
     mouse: std140::vec4,
     date: std140::vec4,
     sample_rate: std140::float,
+    channel_resolution: std140::array<std140::vec3, 4>,
+    tweak: std140::float,
 }
 impl Default for WgpuUniform {
     fn default() -> Self {
@@ -224,6 +1405,31 @@ impl Default for WgpuUniform {
             mouse: std140::vec4::zero(),
             date: std140::vec4::zero(),
             sample_rate: std140::float(0.0),
+            channel_resolution: std140::array![std140::vec3::zero(); 4],
+            tweak: std140::float(0.0),
+        }
+    }
+}
+impl WgpuUniform {
+    /// Uniform values for frame `frame` of a clock running at a fixed `fps`.
+    /// Used for offline rendering, where every frame must be reproducible.
+    pub fn at_frame(frame: u32, fps: u32, width: u32, height: u32) -> Self {
+        let time_delta = 1.0 / fps as f32;
+        Self {
+            resolution: std140::vec2(width as f32, height as f32),
+            time: std140::float(frame as f32 * time_delta),
+            time_delta: std140::float(time_delta),
+            frame: std140::float(frame as f32),
+            ..Default::default()
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            std::slice::from_raw_parts(
+                self as *const WgpuUniform as *const u8,
+                std::mem::size_of::<WgpuUniform>(),
+            )
         }
     }
 }
@@ -232,18 +1438,18 @@ impl egui_wgpu::CallbackTrait for WgpuCallback {
     fn prepare(
         &self,
         _device: &Device,
-        queue: &Queue,
+        _queue: &Queue,
         _screen_descriptor: &ScreenDescriptor,
-        _egui_encoder: &mut CommandEncoder,
+        egui_encoder: &mut CommandEncoder,
         callback_resources: &mut CallbackResources,
     ) -> Vec<CommandBuffer> {
-        let resources: &TriangleRenderResources = callback_resources.get().unwrap();
-        queue.write_buffer(&resources.uniform_buffer, 0, unsafe {
-            std::slice::from_raw_parts(
-                &self.uniform as *const WgpuUniform as *const u8,
-                std::mem::size_of::<WgpuUniform>(),
-            )
-        });
+        let resources: &mut TriangleRenderResources = callback_resources.get_mut().unwrap();
+        if let Some(renderer) = &mut resources.renderer {
+            let resolution = &self.uniform.resolution;
+            renderer.resize(resolution.0.round() as u32, resolution.1.round() as u32);
+            renderer.set_uniform_values(&self.uniform_values);
+            renderer.encode(egui_encoder, &self.uniform);
+        }
         Vec::new()
     }
 
@@ -264,10 +1470,8 @@ impl egui_wgpu::CallbackTrait for WgpuCallback {
         callback_resources: &CallbackResources,
     ) {
         let resources: &TriangleRenderResources = callback_resources.get().unwrap();
-        if let Some(pipeline) = &resources.pipeline {
-            render_pass.set_pipeline(pipeline);
-            render_pass.set_bind_group(0, &resources.bind_group, &[]);
-            render_pass.draw(0..6, 0..1);
+        if let Some(renderer) = &resources.renderer {
+            renderer.draw_image(render_pass);
         }
     }
 }
@@ -276,6 +1480,13 @@ impl eframe::App for TemplateApp {
     /// Called each time the UI needs repainting, which may be many times per second.
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         ctx.request_repaint();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_file_changes();
+        self.autosave(ctx.input(|i| i.time));
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.history.settled(history::unix_time()).is_some() {
+            self.render_pending_thumbnail();
+        }
         {
             let mut renderer = self.render_state.renderer.write();
 
@@ -283,52 +1494,138 @@ impl eframe::App for TemplateApp {
                 .callback_resources
                 .get_mut::<TriangleRenderResources>()
                 .unwrap();
-            #[cfg(not(target_arch = "wasm32"))]
-            {
-                if let Ok(Ok(notify::Event {
-                    kind: notify::EventKind::Modify(notify::event::ModifyKind::Data(_)),
-                    ..
-                })) = self.vertex_shader_file_watch_rx.try_recv()
-                {
-                    info!("Vertex shader file modified");
-                    self.shader_dirty = true;
-                    while let Ok(Ok(_)) = self.vertex_shader_file_watch_rx.try_recv() {}
-                }
-
-                if let Ok(Ok(notify::Event {
-                    kind: notify::EventKind::Modify(notify::event::ModifyKind::Data(_)),
-                    ..
-                })) = self.fragment_shader_file_watch_rx.try_recv()
-                {
-                    info!("Vertex shader file modified");
-                    self.shader_dirty = true;
-                    while let Ok(Ok(_)) = self.fragment_shader_file_watch_rx.try_recv() {}
-                }
+            if self.tweaker.drag(ctx, &mut self.project) {
+                self.shader_dirty = true;
             }
             if self.shader_dirty {
-                match (
-                    load_vertex_shader(),
-                    load_fragment_shader(&self.shader_content),
+                self.shader_dirty = false;
+                // While a literal is dragged, the shader reads it from a uniform.
+                let promoted = self.tweaker.promoted(&self.project);
+                match passes::Program::compile(
+                    promoted.as_ref().unwrap_or(&self.project),
+                    self.file.directory(),
+                    &mut self.media,
                 ) {
-                    (Ok(vertex_spirv), Ok(fragment_spirv)) => {
-                        triangle_render_resources.pipeline = Some(create_pipeline(
+                    Ok(program) => {
+                        #[cfg(not(target_arch = "wasm32"))]
+                        watch_included_files(
+                            &mut self.watcher,
+                            &mut self.included_files,
+                            program.included_files(),
+                        );
+                        let revision = promoted.is_none().then(|| {
+                            self.history.record(
+                                &self.project,
+                                &self.file.name,
+                                None,
+                                history::unix_time(),
+                            )
+                        });
+                        let resolution = &self.wgpu_callback.uniform.resolution;
+                        triangle_render_resources.renderer = Some(passes::PassRenderer::new(
                             &self.render_state.device,
-                            vertex_spirv,
-                            fragment_spirv,
+                            &self.render_state.queue,
+                            &program,
                             self.render_state.target_format,
+                            resolution.0 as u32,
+                            resolution.1 as u32,
                         ));
+                        if promoted.is_none() {
+                            self.diagnostics.clear();
+                        }
+                        // Thumbnails are rendered once the revision settles.
+                        #[cfg(not(target_arch = "wasm32"))]
+                        if let Some(revision) = revision.filter(|&id| {
+                            self.history.get(id).is_some_and(|r| r.thumbnail.is_none())
+                        }) {
+                            self.pending_thumbnail = Some((revision, program));
+                        }
+                        #[cfg(target_arch = "wasm32")]
+                        let _ = revision;
                         info!("Shader reloaded successfully");
                     }
-                    (Err(vertex_error), _) => {
-                        error!("Error loading vertex shader: {}", vertex_error);
+                    Err(e) if promoted.is_some() => {
+                        // Literals in constant expressions, like array sizes,
+                        // have to stay literals.
+                        info!("The literal can't be read from a uniform, recompiling as it changes: {:#}", e);
+                        self.tweaker.demote();
+                        self.shader_dirty = true;
                     }
-                    (_, Err(fragment_error)) => {
-                        error!("Error loading fragment shader: {}", fragment_error,);
+                    Err(e) => {
+                        // Fixing an included file compiles the shader again.
+                        #[cfg(not(target_arch = "wasm32"))]
+                        watch_included_files(
+                            &mut self.watcher,
+                            &mut self.included_files,
+                            included_files(&e),
+                        );
+                        self.diagnostics = navigate::diagnostics(&format!("{e:#}"));
+                        error!("Error loading shader: {:#}", e);
                     }
                 }
-                self.shader_dirty = false;
             }
         }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(job) = &mut self.export.job {
+            match job.step() {
+                Ok(true) => {
+                    info!(
+                        "Rendered {} frames to {}",
+                        job.frame_count(),
+                        self.export.settings.path.display()
+                    );
+                    self.export.job = None;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    error!("Error rendering frame {}: {}", job.frame(), e);
+                    self.export.job = None;
+                }
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        if let Ok((name, content)) = self.upload_rx.try_recv() {
+            if shadertoy::is_shadertoy_json(&content) {
+                if let Err(e) = self.open_shadertoy(&content, std::path::Path::new(".")) {
+                    error!("Error importing {}: {:#}", name, e);
+                }
+            } else {
+                info!("Opened {}", name);
+                let project = project::Project::single(&name, content);
+                self.load_project(name, None, project);
+            }
+        }
+        let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+        if !dropped_files.is_empty() {
+            self.open_dropped(&dropped_files);
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&SAVE_AS_SHORTCUT)) {
+            self.save_file(true);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&SAVE_SHORTCUT)) {
+            self.save_file(false);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&OPEN_SHORTCUT)) {
+            self.open_file();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&find::FIND_SHORTCUT)) {
+            self.open_find_bar(find::Mode::Find);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&find::REPLACE_SHORTCUT)) {
+            self.open_find_bar(find::Mode::Replace);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&find::GO_TO_LINE_SHORTCUT)) {
+            self.open_find_bar(find::Mode::GoToLine);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&navigate::GO_TO_DEFINITION_SHORTCUT)) {
+            self.go_to_definition_at_cursor(ctx);
+        } else if ctx.input_mut(|i| i.consume_shortcut(&format::FORMAT_SHORTCUT)) {
+            let source = self.selected_source_index();
+            self.format_source(source);
+        }
+        let title = self.file.title(&self.project);
+        if title != self.window_title {
+            #[cfg(not(target_arch = "wasm32"))]
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            #[cfg(target_arch = "wasm32")]
+            file::set_document_title(&title);
+            self.window_title = title;
+        }
         // Put your widgets into a `SidePanel`, `TopBottomPanel`, `CentralPanel`, `Window` or `Area`.
         // For inspiration and more examples, go to https://emilk.github.io/egui
 
@@ -336,20 +1633,185 @@ impl eframe::App for TemplateApp {
             // The top panel is often a good place for a menu bar:
 
             egui::menu::bar(ui, |ui| {
-                // NOTE: no File->Quit on web pages!
-                let is_web = cfg!(target_arch = "wasm32");
-                if !is_web {
-                    ui.menu_button("File", |ui| {
+                ui.menu_button("File", |ui| {
+                    let shortcut_button = |text: &str, shortcut: &egui::KeyboardShortcut| {
+                        egui::Button::new(text).shortcut_text(ctx.format_shortcut(shortcut))
+                    };
+                    if ui.add(shortcut_button("Open…", &OPEN_SHORTCUT)).clicked() {
+                        self.open_file();
+                        ui.close_menu();
+                    }
+                    #[cfg(not(target_arch = "wasm32"))]
+                    ui.menu_button("Open Recent", |ui| {
+                        if self.recent_files.paths().is_empty() {
+                            ui.label("No recent files");
+                        }
+                        let mut selected = None;
+                        for path in self.recent_files.paths() {
+                            if ui.button(path.display().to_string()).clicked() {
+                                selected = Some(path.clone());
+                            }
+                        }
+                        if let Some(path) = selected {
+                            self.open_path(path);
+                            ui.close_menu();
+                        }
+                    });
+                    if ui.button("Gallery…").clicked() {
+                        self.gallery_window.open = true;
+                        ui.close_menu();
+                    }
+                    if ui.add(shortcut_button("Save", &SAVE_SHORTCUT)).clicked() {
+                        self.save_file(false);
+                        ui.close_menu();
+                    }
+                    if ui
+                        .add(shortcut_button("Save As…", &SAVE_AS_SHORTCUT))
+                        .clicked()
+                    {
+                        self.save_file(true);
+                        ui.close_menu();
+                    }
+                    if ui.button("Export to Shadertoy…").clicked() {
+                        self.export_shadertoy();
+                        ui.close_menu();
+                    }
+                    #[cfg(target_arch = "wasm32")]
+                    if ui.button("Copy Share Link").clicked() {
+                        self.copy_share_link(ctx);
+                        ui.close_menu();
+                    }
+                    // NOTE: no File->Export or File->Quit on web pages!
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        ui.separator();
+                        if ui.button("Export…").clicked() {
+                            if let Some([width, height]) = self.project.resolution {
+                                self.export.settings.width = width;
+                                self.export.settings.height = height;
+                            }
+                            self.export.open = true;
+                            ui.close_menu();
+                        }
                         if ui.button("Quit").clicked() {
                             ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                         }
-                    });
-                    ui.add_space(16.0);
-                }
+                    }
+                });
+                ui.menu_button("Edit", |ui| {
+                    let shortcut_button = |text: &str, shortcut: &egui::KeyboardShortcut| {
+                        egui::Button::new(text).shortcut_text(ctx.format_shortcut(shortcut))
+                    };
+                    for (text, shortcut, mode) in [
+                        ("Find…", &find::FIND_SHORTCUT, find::Mode::Find),
+                        ("Replace…", &find::REPLACE_SHORTCUT, find::Mode::Replace),
+                        (
+                            "Go to Line…",
+                            &find::GO_TO_LINE_SHORTCUT,
+                            find::Mode::GoToLine,
+                        ),
+                    ] {
+                        if ui.add(shortcut_button(text, shortcut)).clicked() {
+                            self.open_find_bar(mode);
+                            ui.close_menu();
+                        }
+                    }
+                    let definition =
+                        shortcut_button("Go to Definition", &navigate::GO_TO_DEFINITION_SHORTCUT);
+                    if ui.add(definition).clicked() {
+                        self.go_to_definition_at_cursor(ctx);
+                        ui.close_menu();
+                    }
+                    ui.separator();
+                    if ui
+                        .add(shortcut_button("Format", &format::FORMAT_SHORTCUT))
+                        .clicked()
+                    {
+                        let source = self.selected_source_index();
+                        self.format_source(source);
+                        ui.close_menu();
+                    }
+                    ui.checkbox(&mut self.format_on_save, "Format on Save");
+                });
+                ui.menu_button("Examples", |ui| {
+                    for example in examples::EXAMPLES {
+                        if ui
+                            .button(example.title())
+                            .on_hover_text(example.file_name)
+                            .clicked()
+                        {
+                            info!("Opened the {} example", example.file_name);
+                            self.load_project(
+                                example.file_name.to_string(),
+                                None,
+                                example.project(),
+                            );
+                            ui.close_menu();
+                        }
+                    }
+                });
+                ui.add_space(16.0);
 
                 //egui::widgets::global_theme_preference_buttons(ui);
             });
         });
+        #[cfg(not(target_arch = "wasm32"))]
+        self.file_conflict_ui(ctx);
+        self.recovery_ui(ctx);
+        if let Some(gallery::GalleryAction::Open(index)) =
+            self.gallery_window.ui(ctx, &self.render_state)
+        {
+            self.open_gallery_entry(index);
+        }
+        let selected = match self.selected_tab {
+            EditorTab::Pass(kind) => Some(kind),
+            EditorTab::Common => None,
+        };
+        if let Some(action) = self.channel_prompt.ui(ctx, &self.project, selected) {
+            action.apply(&mut self.project);
+            self.shader_dirty = true;
+        }
+        drop_overlay(ctx);
+        if let Some(history::HistoryAction::Restore(id)) =
+            self.history_window.ui(ctx, &self.history)
+        {
+            if let Some(revision) = self.history.get(id) {
+                info!("Restored the revision of {}", revision.file_name);
+                self.project = revision.project();
+                self.shader_dirty = true;
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(action) = self.export.ui(ctx) {
+            match passes::Program::compile(&self.project, self.file.directory(), &mut self.media) {
+                Ok(program) => {
+                    let device = &self.render_state.device;
+                    let queue = &self.render_state.queue;
+                    let settings = &self.export.settings;
+                    match action {
+                        export::ExportAction::Render => {
+                            match export::RenderJob::new(
+                                device,
+                                queue,
+                                &program,
+                                settings,
+                                settings.frame_count(),
+                            ) {
+                                Ok(job) => self.export.job = Some(job),
+                                Err(e) => error!("Error starting render: {}", e),
+                            }
+                        }
+                        export::ExportAction::EstimateSize => {
+                            match export::estimate_size(device, queue, &program, settings) {
+                                Ok(bytes) => self.export.estimate = Some((settings.clone(), bytes)),
+                                Err(e) => error!("Error estimating export size: {}", e),
+                            }
+                        }
+                    }
+                }
+                Err(e) => error!("Error loading shader: {:#}", e),
+            }
+        }
         egui::SidePanel::new(Side::Right, Id::new("right_panel")).show(ctx, |ui| {
             ui.horizontal(|ui| {
                 if ui.button("🔄").clicked() {
@@ -361,40 +1823,94 @@ impl eframe::App for TemplateApp {
                 #[cfg(not(target_arch = "wasm32"))]
                 ui.checkbox(&mut self.shader_editor, "Shader Editor");
                 ui.checkbox(&mut self.show_logger, "Log");
+                ui.checkbox(&mut self.history_window.open, "History");
             });
             if self.shader_editor {
-                let theme = egui_extras::syntax_highlighting::CodeTheme::from_style(ui.style());
+                self.pass_tabs_ui(ui);
+                self.channels_ui(ui);
+                self.scan.update(&self.project);
+                self.uniforms_ui(ui);
+                self.find_bar_ui(ui);
+                // Taken out while the selected source is borrowed.
+                let scan = std::mem::take(&mut self.scan);
                 let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-                    let mut layout_job = egui_extras::syntax_highlighting::highlight(
-                        ui.ctx(),
-                        ui.style(),
-                        &theme,
-                        string,
-                        "c",
-                    );
+                    let mut layout_job =
+                        highlight::highlight(ui.ctx(), ui.style(), string, &scan.user_uniforms);
                     layout_job.wrap.max_width = wrap_width;
                     ui.fonts(|f| f.layout_job(layout_job))
                 };
+                let mut definition = None;
                 egui::ScrollArea::new(egui::Vec2b::new(true, true))
                     .id_salt(Id::new("shader_editor_scroll_area"))
                     .auto_shrink(egui::Vec2b::new(true, true))
                     .max_height(ui.available_height() / 4.0 * 3.0)
                     .show(ui, |ui| {
-                        if ui
-                            .add(
-                                egui::TextEdit::multiline(&mut self.shader_content)
-                                    .font(egui::TextStyle::Monospace)
-                                    .code_editor()
-                                    .lock_focus(true)
-                                    .desired_width(f32::INFINITY)
-                                    .desired_rows(10)
-                                    .layouter(&mut layouter),
-                            )
-                            .changed()
-                        {
+                        let id = Id::new(EDITOR_ID);
+                        // Taken out while the selected source is borrowed.
+                        let mut completion = std::mem::take(&mut self.completion);
+                        let mut tweaker = std::mem::take(&mut self.tweaker);
+                        let mut cursors = std::mem::take(&mut self.cursors);
+                        let key = completion.consume_keys(ui, id);
+                        let index = self.selected_source_index();
+                        let jump = self.jump.take().filter(|jump| jump.source == index);
+                        let found = self.project.sources().nth(index).map(|source| {
+                            let matches = self.find_bar.matches(&source.code);
+                            navigate::char_ranges(&source.code, matches)
+                        });
+                        let source = self.selected_source();
+                        if let Some(jump) = &jump {
+                            jump.select(ui.ctx(), id, &source.code);
+                        }
+                        let mut changed = tweaker.begin(ui, &source.code, index);
+                        changed |= cursors.input(ui, id, &mut source.code, index);
+                        let output = egui::TextEdit::multiline(&mut source.code)
+                            .id(id)
+                            .font(egui::TextStyle::Monospace)
+                            .code_editor()
+                            .lock_focus(true)
+                            .desired_width(f32::INFINITY)
+                            .desired_rows(10)
+                            .interactive(!tweaker.is_dragging())
+                            .layouter(&mut layouter)
+                            .show(ui);
+                        tweaker.laid_out(&output);
+                        if let Some(mut found) = found {
+                            let color = ui.visuals().selection.bg_fill;
+                            cursors::paint_ranges(ui, &output, &found, color.linear_multiply(0.4));
+                            // The editor only shows its selection while it has the focus.
+                            let current = output.cursor_range.map(|range| {
+                                let [start, end] = range.as_ccursor_range().sorted();
+                                start.index..end.index
+                            });
+                            found.retain(|range| Some(range) == current.as_ref());
+                            if !output.response.has_focus() {
+                                cursors::paint_ranges(ui, &output, &found, color);
+                            }
+                        }
+                        cursors.paint(ui, &output);
+                        if let Some(jump) = &jump {
+                            jump.scroll(ui, &output, &source.code);
+                        }
+                        if output.response.clicked() && ui.input(|i| i.modifiers.command) {
+                            let selection = navigate::selection(ui.ctx(), id, &source.code);
+                            let word = complete::word_at(&source.code, selection.start);
+                            definition = Some(source.code[word].to_string());
+                        }
+                        changed |= output.response.changed();
+                        let code = &mut source.code;
+                        changed |= completion.ui(ui, &output, code, &scan.completions, key);
+                        self.completion = completion;
+                        self.tweaker = tweaker;
+                        self.cursors = cursors;
+                        if changed {
                             self.shader_dirty = true;
                         }
                     });
+                self.scan = scan;
+                if let Some(name) = definition.filter(|name| !name.is_empty()) {
+                    self.go_to_definition(&name);
+                }
+                self.diagnostics_ui(ui);
             }
             if self.show_logger {
                 egui_logger::logger_ui().show(ui);
@@ -403,22 +1919,28 @@ impl eframe::App for TemplateApp {
 
         egui::CentralPanel::default().show(ctx, |ui| {
             // allocate rect as big as possible
-            let rect = ui.available_rect_before_wrap();
+            let mut rect = ui.available_rect_before_wrap();
+            if let Some([width, height]) = self.project.resolution {
+                // Letterbox to the project's aspect ratio.
+                let aspect = width as f32 / height.max(1) as f32;
+                let size = if rect.width() / rect.height() > aspect {
+                    egui::vec2(rect.height() * aspect, rect.height())
+                } else {
+                    egui::vec2(rect.width(), rect.width() / aspect)
+                };
+                rect = egui::Rect::from_center_size(rect.center(), size);
+            }
             let (width, height) = (rect.width(), rect.height());
-            //let aspect = 4.0 / 3.0;
-            //let (width, height) = if rect.width() / rect.height() > aspect {
-            //    (rect.height() * aspect, rect.height())
-            //} else {
-            //    (rect.width(), rect.width() / aspect)
-            //};
-            //rect.set_width(width);
-            //rect.set_height(height);
             self.wgpu_callback.uniform.resolution = std140::vec2(
                 width * ctx.pixels_per_point(),
                 height * ctx.pixels_per_point(),
             );
             self.wgpu_callback.uniform.time =
                 std140::float(Instant::now().duration_since(self.start_time).as_secs_f32());
+            self.wgpu_callback.uniform.tweak = std140::float(self.tweaker.value().unwrap_or(0.0));
+            self.wgpu_callback
+                .uniform_values
+                .clone_from(&self.project.uniforms);
             ui.painter().add(egui_wgpu::Callback::new_paint_callback(
                 rect,
                 self.wgpu_callback.clone(),
@@ -429,5 +1951,43 @@ impl eframe::App for TemplateApp {
     }
 
     /// Called by the frame work to save state before shutdown.
-    fn save(&mut self, _storage: &mut dyn eframe::Storage) {}
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.render_pending_thumbnail();
+        self.state().save(storage);
+        self.history.save(storage);
+    }
+
+    /// Called on a clean shutdown, after `save`.
+    fn on_exit(&mut self) {
+        if let Some(recovery) = &self.recovery {
+            if let Err(e) = recovery.finish() {
+                error!("Error clearing the autosave: {:#}", e);
+            }
+        }
+    }
+}
+
+/// Watch `files` instead of the previously `included` files.
+#[cfg(not(target_arch = "wasm32"))]
+fn watch_included_files(
+    watcher: &mut Option<watch::FileWatcher>,
+    included: &mut Vec<std::path::PathBuf>,
+    files: &[std::path::PathBuf],
+) {
+    if let Some(watcher) = watcher {
+        for path in included.iter() {
+            if !files.contains(path) {
+                watcher.unwatch(path);
+            }
+        }
+        for path in files {
+            if !included.contains(path) {
+                if let Err(e) = watcher.watch(path) {
+                    warn!("Error watching {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+    *included = files.to_vec();
 }
@@ -0,0 +1,569 @@
+//! Rewrites GLSL ES 3.00 code, as written for Shadertoy and WebGL, into code
+//! the `#version 450` template accepts.
+//!
+//! The rewrite works on tokens and never adds or removes line breaks, so
+//! compiler errors keep pointing at the lines the user wrote.
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+/// One kind of change applied to the code, and where.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Rewrite {
+    /// 1-based lines of the code the change was made on.
+    pub lines: Vec<usize>,
+    pub description: String,
+}
+
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self.lines.iter().map(usize::to_string).collect();
+        let plural = if self.lines.len() > 1 { "s" } else { "" };
+        write!(f, "line{plural} {}: {}", lines.join(", "), self.description)
+    }
+}
+
+/// Code ready for the template and the changes made to get there.
+pub struct Rewritten {
+    pub code: String,
+    pub rewrites: Vec<Rewrite>,
+}
+
+/// Uniforms and channels the template declares. Shaders that declare them
+/// again, as they must outside Shadertoy, would not compile.
+const BUILTINS: [&str; 13] = [
+    "iResolution",
+    "iTime",
+    "iTimeDelta",
+    "iFrame",
+    "iChannelTime",
+    "iMouse",
+    "iDate",
+    "iSampleRate",
+    "iChannelResolution",
+    "iChannel0",
+    "iChannel1",
+    "iChannel2",
+    "iChannel3",
+];
+
+/// Texture functions of GLSL ES 1.00 and its extensions, still accepted by
+/// WebGL 2, and what they are called in GLSL 4.50.
+const LEGACY_FUNCTIONS: [(&str, &str); 9] = [
+    ("texture2D", "texture"),
+    ("texture2DLod", "textureLod"),
+    ("texture2DLodEXT", "textureLod"),
+    ("texture2DGradEXT", "textureGrad"),
+    ("texture2DProj", "textureProj"),
+    ("texture2DProjLod", "textureProjLod"),
+    ("textureCube", "texture"),
+    ("textureCubeLod", "textureLod"),
+    ("textureCubeLodEXT", "textureLod"),
+];
+
+/// Words that are free identifiers in GLSL ES 3.00 but keywords in GLSL 4.50
+/// with Vulkan semantics.
+const DESKTOP_KEYWORDS: [&str; 18] = [
+    "sample",
+    "patch",
+    "buffer",
+    "shared",
+    "coherent",
+    "volatile",
+    "restrict",
+    "readonly",
+    "writeonly",
+    "subroutine",
+    "noperspective",
+    "atomic_uint",
+    "sampler",
+    "samplerShadow",
+    "texture1D",
+    "texture2D",
+    "texture3D",
+    "textureCube",
+];
+
+/// Extensions WebGL needs enabled and GLSL 4.50 has built in.
+const WEBGL_EXTENSIONS: [&str; 4] = [
+    "GL_OES_standard_derivatives",
+    "GL_EXT_shader_texture_lod",
+    "GL_EXT_frag_depth",
+    "GL_EXT_draw_buffers",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    Identifier,
+    Number,
+    Space,
+    Comment,
+    /// A preprocessor line, without its line break.
+    Directive,
+    Punctuation,
+}
+
+struct Token {
+    kind: Kind,
+    text: String,
+    line: usize,
+}
+
+/// Rewrite ES-style `code` for the template, reporting every change.
+pub fn rewrite(code: &str) -> Rewritten {
+    let mut rewriter = Rewriter {
+        tokens: tokenize(code),
+        rewrites: BTreeMap::new(),
+    };
+    rewriter.directives();
+    rewriter.precision();
+    rewriter.builtin_declarations();
+    rewriter.sampler_parameters();
+    rewriter.legacy_functions();
+    rewriter.keywords();
+    Rewritten {
+        code: rewriter
+            .tokens
+            .iter()
+            .map(|token| token.text.as_str())
+            .collect(),
+        rewrites: rewriter
+            .rewrites
+            .into_iter()
+            .map(|(description, lines)| Rewrite { lines, description })
+            .collect(),
+    }
+}
+
+fn tokenize(code: &str) -> Vec<Token> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    // Only whitespace since the last line break, so `#` starts a directive.
+    let mut line_start = true;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let kind = if c.is_whitespace() {
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+            Kind::Space
+        } else if c == '/' && chars.get(i + 1) == Some(&'/') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            Kind::Comment
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i = (i + 2).min(chars.len());
+            Kind::Comment
+        } else if c == '#' && line_start {
+            // Directives continue over escaped line breaks.
+            while i < chars.len() && (chars[i] != '\n' || chars[i - 1] == '\\') {
+                i += 1;
+            }
+            Kind::Directive
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            Kind::Identifier
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            Kind::Number
+        } else {
+            i += 1;
+            Kind::Punctuation
+        };
+        let text: String = chars[start..i].iter().collect();
+        match kind {
+            Kind::Space => line_start |= text.contains('\n'),
+            Kind::Comment => {}
+            _ => line_start = false,
+        }
+        let breaks = text.matches('\n').count();
+        tokens.push(Token { kind, text, line });
+        line += breaks;
+        if kind == Kind::Comment && breaks > 0 {
+            line_start = true;
+        }
+    }
+    tokens
+}
+
+struct Rewriter {
+    tokens: Vec<Token>,
+    /// Lines of each kind of change, by description.
+    rewrites: BTreeMap<String, Vec<usize>>,
+}
+
+impl Rewriter {
+    fn report(&mut self, line: usize, description: impl Into<String>) {
+        let lines = self.rewrites.entry(description.into()).or_default();
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+
+    /// Index of the first token at or after `i` that isn't space or a comment.
+    fn next_significant(&self, mut i: usize) -> Option<usize> {
+        while i < self.tokens.len() {
+            if !matches!(self.tokens[i].kind, Kind::Space | Kind::Comment) {
+                return Some(i);
+            }
+            i += 1;
+        }
+        None
+    }
+
+    fn is(&self, i: Option<usize>, text: &str) -> bool {
+        i.is_some_and(|i| self.tokens[i].text == text)
+    }
+
+    /// Index of the `close` matching the `open` at `i`.
+    fn matching(&self, i: usize, open: &str, close: &str) -> Option<usize> {
+        let mut depth = 0;
+        for (j, token) in self.tokens.iter().enumerate().skip(i) {
+            if token.kind != Kind::Punctuation {
+                continue;
+            }
+            if token.text == open {
+                depth += 1;
+            } else if token.text == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(j);
+                }
+            }
+        }
+        None
+    }
+
+    /// Remove the tokens `range`, keeping their line breaks.
+    fn blank(&mut self, range: std::ops::RangeInclusive<usize>) {
+        for token in &mut self.tokens[range] {
+            token.text = "\n".repeat(token.text.matches('\n').count());
+        }
+    }
+
+    fn directives(&mut self) {
+        for i in 0..self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.kind != Kind::Directive {
+                continue;
+            }
+            let words: Vec<&str> = token.text[1..].split_whitespace().collect();
+            let description = match words.as_slice() {
+                ["version", ..] => "removed #version, the template sets it",
+                ["extension", name, ..]
+                    if WEBGL_EXTENSIONS.contains(&name.trim_end_matches(':')) =>
+                {
+                    "removed a WebGL extension GLSL 4.50 has built in"
+                }
+                _ => continue,
+            };
+            let line = token.line;
+            self.blank(i..=i);
+            self.report(line, description);
+        }
+    }
+
+    /// Precision statements and qualifiers. They are accepted but would make
+    /// Vulkan drivers compute at reduced precision where WebGL doesn't.
+    fn precision(&mut self) {
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.kind != Kind::Identifier {
+                i += 1;
+                continue;
+            }
+            let line = token.line;
+            match token.text.as_str() {
+                "precision" => {
+                    let end = (i..self.tokens.len()).find(|&j| self.tokens[j].text == ";");
+                    if let Some(end) = end {
+                        self.blank(i..=end);
+                        self.report(line, "removed precision statement");
+                        i = end;
+                    }
+                }
+                "lowp" | "mediump" | "highp" => {
+                    self.blank(i..=i);
+                    // Don't leave a double space behind.
+                    if self
+                        .tokens
+                        .get(i + 1)
+                        .is_some_and(|t| t.kind == Kind::Space && t.text == " ")
+                    {
+                        self.blank(i + 1..=i + 1);
+                    }
+                    self.report(line, "removed precision qualifier");
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// `uniform vec3 iResolution;` and the like.
+    fn builtin_declarations(&mut self) {
+        let mut depth = 0;
+        let mut i = 0;
+        while i < self.tokens.len() {
+            let token = &self.tokens[i];
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                "uniform" if depth == 0 && token.kind == Kind::Identifier => {
+                    let end = (i..self.tokens.len())
+                        .find(|&j| matches!(self.tokens[j].text.as_str(), ";" | "{"));
+                    let Some(mut end) = end else {
+                        break;
+                    };
+                    // A whole block, like the one in our own template.
+                    if self.tokens[end].text == "{" {
+                        let Some(close) = self.matching(end, "{", "}") else {
+                            break;
+                        };
+                        end = (close..self.tokens.len())
+                            .find(|&j| self.tokens[j].text == ";")
+                            .unwrap_or(close);
+                    }
+                    let declares_builtin = self.tokens[i..end]
+                        .iter()
+                        .any(|t| t.kind == Kind::Identifier && BUILTINS.contains(&t.text.as_str()));
+                    if declares_builtin {
+                        let line = token.line;
+                        self.blank(i..=end);
+                        self.report(
+                            line,
+                            "removed declaration of a uniform the template provides",
+                        );
+                    }
+                    i = end;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Functions taking `sampler2D` parameters. The template's channels are
+    /// separate textures and samplers, which Vulkan GLSL only lets be combined
+    /// right where they're sampled, so such parameters are split in two and
+    /// callers pass both halves.
+    fn sampler_parameters(&mut self) {
+        // Function name to the positions of its sampler parameters.
+        let mut functions: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut definitions = Vec::new();
+        let mut depth = 0;
+        for i in 0..self.tokens.len() {
+            match self.tokens[i].text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                "(" if depth == 0 => {
+                    let Some(name) = (0..i)
+                        .rev()
+                        .find(|&j| !matches!(self.tokens[j].kind, Kind::Space | Kind::Comment))
+                        .filter(|&j| self.tokens[j].kind == Kind::Identifier)
+                    else {
+                        continue;
+                    };
+                    let Some(close) = self.matching(i, "(", ")") else {
+                        continue;
+                    };
+                    let parameters = self.split_arguments(i, close);
+                    let samplers: Vec<(usize, std::ops::Range<usize>)> = parameters
+                        .into_iter()
+                        .enumerate()
+                        .filter(|(_, range)| {
+                            self.tokens[range.clone()]
+                                .iter()
+                                .any(|t| t.kind == Kind::Identifier && t.text == "sampler2D")
+                        })
+                        .collect();
+                    if samplers.is_empty() {
+                        continue;
+                    }
+                    let positions = functions.entry(self.tokens[name].text.clone()).or_default();
+                    for (position, _) in &samplers {
+                        if !positions.contains(position) {
+                            positions.push(*position);
+                        }
+                    }
+                    definitions.push((close, samplers));
+                }
+                _ => {}
+            }
+        }
+        if functions.is_empty() {
+            return;
+        }
+
+        // Call sites first, while the argument names are still intact.
+        for i in 0..self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.kind != Kind::Identifier {
+                continue;
+            }
+            let Some(positions) = functions.get(&token.text) else {
+                continue;
+            };
+            let open = self.next_significant(i + 1);
+            if !self.is(open, "(") {
+                continue;
+            }
+            let open = open.unwrap_or_default();
+            let Some(close) = self.matching(open, "(", ")") else {
+                continue;
+            };
+            for (position, range) in self.split_arguments(open, close).into_iter().enumerate() {
+                if !positions.contains(&position) {
+                    continue;
+                }
+                let names: Vec<usize> = range
+                    .filter(|&j| !matches!(self.tokens[j].kind, Kind::Space | Kind::Comment))
+                    .collect();
+                // Only plain names can be split; anything else is left for
+                // the compiler to complain about.
+                if let [j] = names.as_slice() {
+                    let argument = &mut self.tokens[*j];
+                    if argument.kind == Kind::Identifier {
+                        argument.text = format!("_{0}_texture, _{0}_sampler", argument.text);
+                        argument.kind = Kind::Punctuation;
+                    }
+                }
+            }
+        }
+
+        // Then the parameters, and their uses inside the function bodies.
+        for (close, samplers) in definitions {
+            let body = self
+                .next_significant(close + 1)
+                .filter(|&open| self.tokens[open].text == "{")
+                .and_then(|open| Some((open, self.matching(open, "{", "}")?)));
+            for (_, range) in samplers {
+                let Some(name) = range
+                    .clone()
+                    .rev()
+                    .find(|&j| self.tokens[j].kind == Kind::Identifier)
+                else {
+                    continue;
+                };
+                let name_text = self.tokens[name].text.clone();
+                let line = self.tokens[name].line;
+                let mut qualifiers = String::new();
+                for token in &self.tokens[range.clone()] {
+                    if token.kind == Kind::Identifier
+                        && matches!(token.text.as_str(), "in" | "const")
+                    {
+                        qualifiers += &token.text;
+                        qualifiers.push(' ');
+                    }
+                }
+                let first = self
+                    .next_significant(range.start)
+                    .unwrap_or(range.start)
+                    .min(name);
+                self.blank(first..=name);
+                self.tokens[first].kind = Kind::Punctuation;
+                if name_text == "sampler2D" {
+                    // An unnamed parameter of a prototype.
+                    self.tokens[first].text = format!("{qualifiers}texture2D, {qualifiers}sampler");
+                    self.report(
+                        line,
+                        "split sampler2D parameter into a texture and a sampler",
+                    );
+                    continue;
+                }
+                self.tokens[first].text = format!(
+                    "{qualifiers}texture2D _{name_text}_texture, {qualifiers}sampler _{name_text}_sampler"
+                );
+                self.report(
+                    line,
+                    format!("split sampler2D parameter {name_text} into a texture and a sampler"),
+                );
+                let Some((open, end)) = body else {
+                    continue;
+                };
+                for token in &mut self.tokens[open..end] {
+                    if token.kind == Kind::Identifier && token.text == name_text {
+                        token.text = format!("sampler2D(_{0}_texture, _{0}_sampler)", name_text);
+                        token.kind = Kind::Punctuation;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Token ranges of the comma separated arguments between the parentheses
+    /// at `open` and `close`. Empty for `()` and `(void)`.
+    fn split_arguments(&self, open: usize, close: usize) -> Vec<std::ops::Range<usize>> {
+        let mut arguments = Vec::new();
+        let mut depth = 0;
+        let mut start = open + 1;
+        for j in open + 1..close {
+            match self.tokens[j].text.as_str() {
+                "(" | "[" => depth += 1,
+                ")" | "]" => depth -= 1,
+                "," if depth == 0 => {
+                    arguments.push(start..j);
+                    start = j + 1;
+                }
+                _ => {}
+            }
+        }
+        arguments.push(start..close);
+        let is_empty = |range: &std::ops::Range<usize>| {
+            self.tokens[range.clone()]
+                .iter()
+                .all(|t| matches!(t.kind, Kind::Space | Kind::Comment) || t.text == "void")
+        };
+        if arguments.len() == 1 && is_empty(&arguments[0]) {
+            arguments.clear();
+        }
+        arguments
+    }
+
+    fn legacy_functions(&mut self) {
+        for i in 0..self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.kind != Kind::Identifier {
+                continue;
+            }
+            let Some(&(old, new)) = LEGACY_FUNCTIONS.iter().find(|(old, _)| *old == token.text)
+            else {
+                continue;
+            };
+            if !self.is(self.next_significant(i + 1), "(") {
+                continue;
+            }
+            let line = token.line;
+            self.tokens[i].text = new.to_string();
+            self.report(line, format!("replaced {old} with {new}"));
+        }
+    }
+
+    /// Identifiers that GLSL 4.50 reserves get an underscore appended.
+    fn keywords(&mut self) {
+        for i in 0..self.tokens.len() {
+            let token = &mut self.tokens[i];
+            if token.kind != Kind::Identifier || !DESKTOP_KEYWORDS.contains(&token.text.as_str()) {
+                continue;
+            }
+            let description = format!("renamed {0} to {0}_, a keyword in GLSL 4.50", token.text);
+            token.text.push('_');
+            let line = token.line;
+            self.report(line, description);
+        }
+    }
+}
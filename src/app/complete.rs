@@ -0,0 +1,453 @@
+//! Completion and hover documentation in the code editor, for GLSL builtins,
+//! the inputs the shader template declares, and the functions and defines
+//! of the project's own code.
+use super::highlight::{self, Token};
+use super::project::Project;
+use eframe::egui;
+use egui::text::{CCursor, CCursorRange};
+
+/// What a completion inserts, and what it shows next to it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Item {
+    pub name: String,
+    /// Signature of a function, declaration of a uniform or text of a define.
+    pub detail: String,
+    pub doc: Option<&'static str>,
+}
+
+/// GLSL built-in functions: name, signature and description. Overloads are
+/// written with `genType`, standing for `float` and the `vec` types.
+pub const BUILTINS: &[(&str, &str, &str)] = &[
+    // Angles and trigonometry.
+    ("radians", "genType radians(genType degrees)", "Converts degrees to radians."),
+    ("degrees", "genType degrees(genType radians)", "Converts radians to degrees."),
+    ("sin", "genType sin(genType angle)", "Sine of an angle in radians."),
+    ("cos", "genType cos(genType angle)", "Cosine of an angle in radians."),
+    ("tan", "genType tan(genType angle)", "Tangent of an angle in radians."),
+    ("asin", "genType asin(genType x)", "Arc sine, in [-π/2, π/2]."),
+    ("acos", "genType acos(genType x)", "Arc cosine, in [0, π]."),
+    ("atan", "genType atan(genType y, genType x)\ngenType atan(genType y_over_x)", "Arc tangent. The two-argument form uses the signs of `x` and `y` to return an angle in [-π, π]."),
+    ("sinh", "genType sinh(genType x)", "Hyperbolic sine."),
+    ("cosh", "genType cosh(genType x)", "Hyperbolic cosine."),
+    ("tanh", "genType tanh(genType x)", "Hyperbolic tangent."),
+    ("asinh", "genType asinh(genType x)", "Inverse hyperbolic sine."),
+    ("acosh", "genType acosh(genType x)", "Inverse hyperbolic cosine."),
+    ("atanh", "genType atanh(genType x)", "Inverse hyperbolic tangent."),
+    // Exponentials.
+    ("pow", "genType pow(genType x, genType y)", "`x` raised to the power `y`. Undefined for `x < 0`."),
+    ("exp", "genType exp(genType x)", "e raised to the power `x`."),
+    ("log", "genType log(genType x)", "Natural logarithm."),
+    ("exp2", "genType exp2(genType x)", "2 raised to the power `x`."),
+    ("log2", "genType log2(genType x)", "Base 2 logarithm."),
+    ("sqrt", "genType sqrt(genType x)", "Square root."),
+    ("inversesqrt", "genType inversesqrt(genType x)", "1 / sqrt(x)."),
+    // Common functions.
+    ("abs", "genType abs(genType x)", "Absolute value."),
+    ("sign", "genType sign(genType x)", "-1, 0 or 1 depending on the sign of `x`."),
+    ("floor", "genType floor(genType x)", "Nearest integer less than or equal to `x`."),
+    ("trunc", "genType trunc(genType x)", "`x` rounded towards zero."),
+    ("round", "genType round(genType x)", "Nearest integer to `x`."),
+    ("roundEven", "genType roundEven(genType x)", "Nearest integer to `x`, halves going to the even one."),
+    ("ceil", "genType ceil(genType x)", "Nearest integer greater than or equal to `x`."),
+    ("fract", "genType fract(genType x)", "x - floor(x)."),
+    ("mod", "genType mod(genType x, genType y)\ngenType mod(genType x, float y)", "x - y * floor(x / y), which has the sign of `y`."),
+    ("modf", "genType modf(genType x, out genType i)", "Fractional part of `x`, with the integer part written to `i`."),
+    ("min", "genType min(genType x, genType y)\ngenType min(genType x, float y)", "The smaller of `x` and `y`."),
+    ("max", "genType max(genType x, genType y)\ngenType max(genType x, float y)", "The larger of `x` and `y`."),
+    ("clamp", "genType clamp(genType x, genType lo, genType hi)\ngenType clamp(genType x, float lo, float hi)", "min(max(x, lo), hi)."),
+    ("mix", "genType mix(genType x, genType y, genType a)\ngenType mix(genType x, genType y, float a)", "Linear blend: x * (1 - a) + y * a."),
+    ("step", "genType step(genType edge, genType x)\ngenType step(float edge, genType x)", "0 where `x < edge`, 1 elsewhere."),
+    ("smoothstep", "genType smoothstep(genType edge0, genType edge1, genType x)\ngenType smoothstep(float edge0, float edge1, genType x)", "Hermite interpolation from 0 to 1 as `x` goes from `edge0` to `edge1`."),
+    ("isnan", "genBType isnan(genType x)", "Whether `x` is NaN."),
+    ("isinf", "genBType isinf(genType x)", "Whether `x` is infinite."),
+    ("fma", "genType fma(genType a, genType b, genType c)", "a * b + c, computed as one operation."),
+    ("floatBitsToInt", "genIType floatBitsToInt(genType x)", "The bits of `x` as a signed integer."),
+    ("floatBitsToUint", "genUType floatBitsToUint(genType x)", "The bits of `x` as an unsigned integer."),
+    ("intBitsToFloat", "genType intBitsToFloat(genIType x)", "The float with the bits of `x`."),
+    ("uintBitsToFloat", "genType uintBitsToFloat(genUType x)", "The float with the bits of `x`."),
+    ("packUnorm2x16", "uint packUnorm2x16(vec2 v)", "Packs two values in [0, 1] into 16 bits each."),
+    ("unpackUnorm2x16", "vec2 unpackUnorm2x16(uint p)", "Unpacks two 16-bit values to [0, 1]."),
+    ("packHalf2x16", "uint packHalf2x16(vec2 v)", "Packs two half floats."),
+    ("unpackHalf2x16", "vec2 unpackHalf2x16(uint p)", "Unpacks two half floats."),
+    // Geometry.
+    ("length", "float length(genType x)", "Length of the vector `x`."),
+    ("distance", "float distance(genType p0, genType p1)", "Distance between `p0` and `p1`."),
+    ("dot", "float dot(genType x, genType y)", "Dot product of `x` and `y`."),
+    ("cross", "vec3 cross(vec3 x, vec3 y)", "Cross product of `x` and `y`."),
+    ("normalize", "genType normalize(genType x)", "`x` scaled to a length of 1."),
+    ("faceforward", "genType faceforward(genType n, genType i, genType nref)", "`n` if dot(nref, i) < 0, -n otherwise."),
+    ("reflect", "genType reflect(genType i, genType n)", "Reflection of the incident vector `i` off a surface with the normalized normal `n`."),
+    ("refract", "genType refract(genType i, genType n, float eta)", "Refraction of the normalized incident vector `i` through a surface with normal `n` and ratio of indices of refraction `eta`."),
+    // Matrices.
+    ("matrixCompMult", "mat matrixCompMult(mat x, mat y)", "Component-wise product of two matrices."),
+    ("outerProduct", "mat outerProduct(vec c, vec r)", "Matrix product of the column `c` and the row `r`."),
+    ("transpose", "mat transpose(mat m)", "Transpose of `m`."),
+    ("determinant", "float determinant(mat m)", "Determinant of `m`."),
+    ("inverse", "mat inverse(mat m)", "Inverse of `m`. Undefined if `m` is singular."),
+    // Vector relations.
+    ("lessThan", "bvec lessThan(vec x, vec y)", "Component-wise x < y."),
+    ("lessThanEqual", "bvec lessThanEqual(vec x, vec y)", "Component-wise x <= y."),
+    ("greaterThan", "bvec greaterThan(vec x, vec y)", "Component-wise x > y."),
+    ("greaterThanEqual", "bvec greaterThanEqual(vec x, vec y)", "Component-wise x >= y."),
+    ("equal", "bvec equal(vec x, vec y)", "Component-wise x == y."),
+    ("notEqual", "bvec notEqual(vec x, vec y)", "Component-wise x != y."),
+    ("any", "bool any(bvec x)", "Whether any component of `x` is true."),
+    ("all", "bool all(bvec x)", "Whether all components of `x` are true."),
+    ("not", "bvec not(bvec x)", "Component-wise logical complement."),
+    // Integers.
+    ("bitCount", "genIType bitCount(genIType value)", "Number of bits set in `value`."),
+    ("findLSB", "genIType findLSB(genIType value)", "Index of the lowest bit set, -1 for 0."),
+    ("findMSB", "genIType findMSB(genIType value)", "Index of the highest bit set, -1 for 0."),
+    ("bitfieldExtract", "genIType bitfieldExtract(genIType value, int offset, int bits)", "The `bits` bits of `value` starting at `offset`."),
+    ("bitfieldInsert", "genIType bitfieldInsert(genIType base, genIType insert, int offset, int bits)", "`base` with `bits` bits starting at `offset` replaced by those of `insert`."),
+    ("bitfieldReverse", "genIType bitfieldReverse(genIType value)", "`value` with the order of its bits reversed."),
+    // Textures.
+    ("texture", "vec4 texture(sampler2D sampler, vec2 uv)\nvec4 texture(sampler2D sampler, vec2 uv, float bias)", "Samples a texture at normalized coordinates, with mipmaps picked from the derivatives of `uv`."),
+    ("textureLod", "vec4 textureLod(sampler2D sampler, vec2 uv, float lod)", "Samples a texture at an explicit mipmap level."),
+    ("textureGrad", "vec4 textureGrad(sampler2D sampler, vec2 uv, vec2 dx, vec2 dy)", "Samples a texture with explicit derivatives."),
+    ("textureOffset", "vec4 textureOffset(sampler2D sampler, vec2 uv, ivec2 offset)", "Samples a texture with a constant offset in texels."),
+    ("textureProj", "vec4 textureProj(sampler2D sampler, vec3 uvq)", "Samples a texture at uvq.xy / uvq.z."),
+    ("texelFetch", "vec4 texelFetch(sampler2D sampler, ivec2 texel, int lod)", "Reads one texel by integer coordinates, without filtering."),
+    ("texelFetchOffset", "vec4 texelFetchOffset(sampler2D sampler, ivec2 texel, int lod, ivec2 offset)", "texelFetch with a constant offset."),
+    ("textureSize", "ivec2 textureSize(sampler2D sampler, int lod)", "Size of a mipmap level of the texture, in texels."),
+    ("textureQueryLod", "vec2 textureQueryLod(sampler2D sampler, vec2 uv)", "The mipmap level that sampling at `uv` would use."),
+    ("textureQueryLevels", "int textureQueryLevels(sampler2D sampler)", "Number of mipmap levels of the texture."),
+    ("textureGather", "vec4 textureGather(sampler2D sampler, vec2 uv, int comp)", "One component of the four texels bilinear filtering at `uv` would read."),
+    // Derivatives.
+    ("dFdx", "genType dFdx(genType p)", "Derivative of `p` along the x axis of the screen."),
+    ("dFdy", "genType dFdy(genType p)", "Derivative of `p` along the y axis of the screen."),
+    ("fwidth", "genType fwidth(genType p)", "abs(dFdx(p)) + abs(dFdy(p))."),
+    ("dFdxFine", "genType dFdxFine(genType p)", "dFdx computed from the neighbouring pixel."),
+    ("dFdyFine", "genType dFdyFine(genType p)", "dFdy computed from the neighbouring pixel."),
+    ("dFdxCoarse", "genType dFdxCoarse(genType p)", "dFdx computed once per 2×2 block of pixels."),
+    ("dFdyCoarse", "genType dFdyCoarse(genType p)", "dFdy computed once per 2×2 block of pixels."),
+];
+
+/// Descriptions of the inputs of the shader template, by name.
+const TEMPLATE_DOCS: &[(&str, &str)] = &[
+    ("iResolution", "Size of the view in pixels."),
+    ("iTime", "Seconds since the shader started."),
+    ("iTimeDelta", "Seconds the previous frame took."),
+    ("iFrame", "Number of the frame, starting at 0."),
+    ("iChannelTime", "Playback time of each channel's input, in seconds."),
+    ("iMouse", "Mouse position in pixels. `xy` follows the mouse while a button is down, `zw` is where it was pressed, negative once released."),
+    ("iDate", "Year, month (from 0), day and seconds since midnight."),
+    ("iSampleRate", "Sample rate of audio inputs, in Hz."),
+    ("iChannelResolution", "Size of each channel's input in pixels."),
+    ("iChannel0", "Input of channel 0."),
+    ("iChannel1", "Input of channel 1."),
+    ("iChannel2", "Input of channel 2."),
+    ("iChannel3", "Input of channel 3."),
+];
+
+/// Words the completion offers at least this many characters in.
+const MIN_PREFIX: usize = 2;
+/// Completions listed at once.
+const MAX_ITEMS: usize = 10;
+
+/// The built-in function `name`.
+pub fn builtin(name: &str) -> Option<Item> {
+    BUILTINS
+        .iter()
+        .find(|(n, ..)| *n == name)
+        .map(|&(name, signature, doc)| Item {
+            name: name.to_string(),
+            detail: signature.to_string(),
+            doc: Some(doc),
+        })
+}
+
+/// The inputs `shader.frag` declares before the project's code, with their
+/// declarations.
+pub fn template_uniforms() -> Vec<Item> {
+    let template = include_str!("shader.frag");
+    let block = template
+        .split_once("uniform Uniforms {{")
+        .and_then(|(_, rest)| rest.split_once("}};"))
+        .map_or("", |(block, _)| block);
+    let members = block.lines().filter_map(|line| {
+        let declaration = line.trim().strip_suffix(';')?;
+        let name = declaration.split_whitespace().nth(1)?;
+        // Members starting with `_` are the editor's own.
+        let name = name
+            .split('[')
+            .next()
+            .filter(|name| !name.starts_with('_'))?;
+        Some((name, declaration.to_string()))
+    });
+    let channels = template.lines().filter_map(|line| {
+        let name = line.strip_prefix("#define ")?.split_whitespace().next()?;
+        Some((name, format!("sampler2D {name}")))
+    });
+    members
+        .chain(channels)
+        .map(|(name, detail)| Item {
+            name: name.to_string(),
+            detail,
+            doc: TEMPLATE_DOCS
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, doc)| *doc),
+        })
+        .collect()
+}
+
+/// Functions defined and macros `#define`d in `code`, in order.
+pub fn symbols(code: &str) -> Vec<Item> {
+    let mut items: Vec<Item> = Vec::new();
+    for line in code.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let item = if let Some(define) = line.strip_prefix("#define") {
+            define
+                .split(|c: char| !is_word_char(c))
+                .find(|word| !word.is_empty())
+                .map(|name| (name, line.to_string()))
+        } else {
+            function(line)
+        };
+        if let Some((name, detail)) = item {
+            if !items.iter().any(|item| item.name == name) {
+                items.push(Item {
+                    name: name.to_string(),
+                    detail,
+                    doc: None,
+                });
+            }
+        }
+    }
+    items
+}
+
+/// Name and signature of the function `line` starts the definition or
+/// declaration of, like `float sdBox(vec3 p, vec3 b) {`.
+pub fn function(line: &str) -> Option<(&str, String)> {
+    let (head, parameters) = line.split_once('(')?;
+    let mut words = head.split_whitespace().rev();
+    let name = words.next()?;
+    let ty = words.next()?;
+    let words_are_names = [name, ty]
+        .iter()
+        .all(|word| !word.is_empty() && word.chars().all(is_word_char));
+    let is_function = words_are_names
+        && highlight::classify(name, &[]) == Token::Plain
+        && matches!(highlight::classify(ty, &[]), Token::Type | Token::Plain)
+        && words.all(|word| highlight::classify(word, &[]) == Token::Qualifier);
+    if !is_function {
+        return None;
+    }
+    // Parameters going on past the line are cut short.
+    let parameters = match parameters.split_once(')') {
+        Some((parameters, _)) => parameters.to_string(),
+        None => format!("{} …", parameters.trim_end()),
+    };
+    Some((name, format!("{head}({parameters})")))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Everything that can be completed in `project`: its own functions and
+/// defines, then the template's inputs and the builtins.
+pub fn items(project: &Project) -> Vec<Item> {
+    let mut items: Vec<Item> = project
+        .sources()
+        .flat_map(|source| symbols(&source.code))
+        .collect();
+    items.extend(template_uniforms());
+    items.extend(BUILTINS.iter().filter_map(|(name, ..)| builtin(name)));
+    items
+}
+
+/// Byte range of the word `byte` is in or right after.
+pub fn word_at(code: &str, byte: usize) -> std::ops::Range<usize> {
+    let start = code[..byte]
+        .rfind(|c: char| !is_word_char(c))
+        .map_or(0, |i| i + 1);
+    let end = code[byte..]
+        .find(|c: char| !is_word_char(c))
+        .map_or(code.len(), |i| byte + i);
+    start..end
+}
+
+/// Items starting with `prefix`, those matching its case first.
+pub fn candidates<'a>(prefix: &str, items: &'a [Item]) -> Vec<&'a Item> {
+    let lower = prefix.to_ascii_lowercase();
+    let mut matches: Vec<&Item> = items
+        .iter()
+        .filter(|item| item.name.to_ascii_lowercase().starts_with(&lower))
+        .collect();
+    matches.sort_by_key(|item| !item.name.starts_with(prefix));
+    matches.dedup_by(|a, b| a.name == b.name);
+    matches
+}
+
+/// Keys the popup takes from the editor while it's open.
+#[derive(Clone, Copy)]
+pub enum Key {
+    Up,
+    Down,
+    Accept,
+    Close,
+}
+
+/// The completion popup of one editor.
+#[derive(Default)]
+pub struct Completion {
+    open: bool,
+    selected: usize,
+    /// Where the popup was last shown. Clicking it takes the focus away from
+    /// the editor, which mustn't close it.
+    popup: Option<egui::Rect>,
+}
+
+impl Completion {
+    /// Take the keys the popup handles before the editor with `id` sees them.
+    /// Call before showing the editor.
+    pub fn consume_keys(&self, ui: &egui::Ui, id: egui::Id) -> Option<Key> {
+        if !self.open || !ui.memory(|m| m.has_focus(id)) {
+            return None;
+        }
+        ui.input_mut(|input| {
+            let none = egui::Modifiers::NONE;
+            [
+                (egui::Key::ArrowUp, Key::Up),
+                (egui::Key::ArrowDown, Key::Down),
+                (egui::Key::Enter, Key::Accept),
+                (egui::Key::Tab, Key::Accept),
+                (egui::Key::Escape, Key::Close),
+            ]
+            .into_iter()
+            .find(|(key, _)| input.consume_key(none, *key))
+            .map(|(_, key)| key)
+        })
+    }
+
+    /// Show the popup and documentation for the editor `output` of `code`,
+    /// acting on `key`. Returns whether `code` changed.
+    pub fn ui(
+        &mut self,
+        ui: &egui::Ui,
+        output: &egui::text_edit::TextEditOutput,
+        code: &mut String,
+        items: &[Item],
+        key: Option<Key>,
+    ) -> bool {
+        let response = &output.response;
+        if response.hovered() {
+            hover_ui(ui, output, code, items);
+        }
+        let over_popup = self.popup.zip(ui.ctx().pointer_hover_pos());
+        let over_popup = over_popup.is_some_and(|(rect, pointer)| rect.contains(pointer));
+        let cursor = output
+            .cursor_range
+            .filter(|range| range.is_empty() && (response.has_focus() || over_popup))
+            .map(|range| range.primary.ccursor);
+        self.popup = None;
+        let Some(cursor) = cursor else {
+            self.open = false;
+            return false;
+        };
+        let byte = byte_index(code, cursor.index);
+        let word = word_at(code, byte);
+        let prefix = &code[word.start..byte];
+        if response.changed() && prefix.len() >= MIN_PREFIX && word.end == byte {
+            if !self.open {
+                self.selected = 0;
+            }
+            self.open = true;
+        }
+        let candidates = candidates(prefix, items);
+        let complete = candidates.len() == 1 && candidates[0].name == prefix;
+        if prefix.is_empty() || candidates.is_empty() || complete {
+            self.open = false;
+        }
+        if !self.open {
+            return false;
+        }
+        self.selected = self.selected.min(candidates.len() - 1);
+        match key {
+            Some(Key::Up) => self.selected = self.selected.saturating_sub(1),
+            Some(Key::Down) => self.selected = (self.selected + 1).min(candidates.len() - 1),
+            Some(Key::Close) => self.open = false,
+            Some(Key::Accept) => {
+                let name = candidates[self.selected].name.clone();
+                return self.accept(ui.ctx(), response.id, code, word.start..byte, &name);
+            }
+            None => {}
+        }
+        let first = self.selected.saturating_sub(MAX_ITEMS - 1);
+        let caret = output.galley.pos_from_ccursor(cursor);
+        let position = output.galley_pos + caret.left_bottom().to_vec2();
+        let mut clicked = None;
+        let popup = egui::Area::new(response.id.with("completion"))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(position)
+            .show(ui.ctx(), |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    for (index, item) in candidates.iter().enumerate().skip(first).take(MAX_ITEMS) {
+                        let text = egui::RichText::new(&item.detail).monospace();
+                        let label = ui.selectable_label(index == self.selected, text);
+                        if label.clicked() {
+                            clicked = Some(item.name.clone());
+                        }
+                        if index == self.selected {
+                            if let Some(doc) = item.doc {
+                                label.on_hover_text(doc);
+                            }
+                        }
+                    }
+                });
+            });
+        self.popup = Some(popup.response.rect);
+        match clicked {
+            Some(name) => self.accept(ui.ctx(), response.id, code, word.start..byte, &name),
+            None => false,
+        }
+    }
+
+    /// Replace `range` of `code` with `name` and put the cursor after it.
+    fn accept(
+        &mut self,
+        ctx: &egui::Context,
+        id: egui::Id,
+        code: &mut String,
+        range: std::ops::Range<usize>,
+        name: &str,
+    ) -> bool {
+        self.open = false;
+        let start = code[..range.start].chars().count();
+        code.replace_range(range, name);
+        if let Some(mut state) = egui::TextEdit::load_state(ctx, id) {
+            let cursor = CCursor::new(start + name.chars().count());
+            state.cursor.set_char_range(Some(CCursorRange::one(cursor)));
+            state.store(ctx, id);
+        }
+        ctx.memory_mut(|memory| memory.request_focus(id));
+        true
+    }
+}
+
+/// Signature and documentation of the word under the pointer.
+fn hover_ui(ui: &egui::Ui, output: &egui::text_edit::TextEditOutput, code: &str, items: &[Item]) {
+    let Some(pointer) = ui.ctx().pointer_hover_pos() else {
+        return;
+    };
+    let cursor = output.galley.cursor_from_pos(pointer - output.galley_pos);
+    let word = &code[word_at(code, byte_index(code, cursor.ccursor.index))];
+    let Some(item) = items.iter().find(|item| item.name == word) else {
+        return;
+    };
+    output.response.clone().on_hover_ui_at_pointer(|ui| {
+        ui.label(egui::RichText::new(&item.detail).monospace());
+        if let Some(doc) = item.doc {
+            ui.label(doc);
+        }
+    });
+}
+
+fn byte_index(code: &str, char_index: usize) -> usize {
+    code.char_indices()
+        .nth(char_index)
+        .map_or(code.len(), |(i, _)| i)
+}
@@ -0,0 +1,300 @@
+//! Several cursors in the editor at once. Ctrl+D selects the word under the
+//! cursor, then adds a selection at the next occurrence of the selected text,
+//! and Ctrl+Alt+Up or Down add a cursor on the line above or below. Typing
+//! goes to every cursor; Escape, a click or moving the cursor go back to one.
+use eframe::egui;
+use egui::text::{CCursor, CCursorRange};
+use std::ops::Range;
+
+pub const NEXT_OCCURRENCE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::D);
+pub const CURSOR_ABOVE_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::ALT),
+    egui::Key::ArrowUp,
+);
+pub const CURSOR_BELOW_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::COMMAND.plus(egui::Modifiers::ALT),
+    egui::Key::ArrowDown,
+);
+
+/// An edit made at every cursor.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Edit {
+    /// Replace the selection with text.
+    Insert(String),
+    /// Delete the selection or the character before the cursor.
+    Backspace,
+    /// Delete the selection or the character after the cursor.
+    Delete,
+}
+
+/// Make `edit` at each of `selections`, char ranges of `code`. Overlapping
+/// selections are edited as one. Returns where each cursor is after it.
+pub fn apply(code: &mut String, selections: &[Range<usize>], edit: &Edit) -> Vec<usize> {
+    let bytes: Vec<usize> = code
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain([code.len()])
+        .collect();
+    let count = bytes.len() - 1;
+    let removals: Vec<Range<usize>> = selections
+        .iter()
+        .map(|selection| {
+            let selection = selection.start.min(count)..selection.end.min(count);
+            match edit {
+                _ if !selection.is_empty() => selection,
+                Edit::Insert(_) => selection,
+                Edit::Backspace => selection.start.saturating_sub(1)..selection.end,
+                Edit::Delete => selection.start..(selection.end + 1).min(count),
+            }
+        })
+        .collect();
+    let insert = match edit {
+        Edit::Insert(text) => text.as_str(),
+        Edit::Backspace | Edit::Delete => "",
+    };
+    let mut order: Vec<usize> = (0..removals.len()).collect();
+    order.sort_by_key(|&i| (removals[i].start, removals[i].end));
+    let mut edited = String::with_capacity(code.len());
+    let mut edited_chars = 0;
+    // Chars of `code` copied or removed so far.
+    let mut done = 0;
+    let mut last_start = None;
+    let mut cursors = vec![0; removals.len()];
+    for i in order {
+        let removal = &removals[i];
+        let touching = removal.start == done && removal.is_empty() && last_start.is_some();
+        if removal.start < done || touching || last_start == Some(removal.start) {
+            // Part of the edit before.
+            done = done.max(removal.end);
+            cursors[i] = edited_chars;
+            continue;
+        }
+        edited.push_str(&code[bytes[done]..bytes[removal.start]]);
+        edited.push_str(insert);
+        edited_chars += removal.start - done + insert.chars().count();
+        cursors[i] = edited_chars;
+        done = removal.end;
+        last_start = Some(removal.start);
+    }
+    edited.push_str(&code[bytes[done]..]);
+    *code = edited;
+    cursors
+}
+
+/// The next occurrence of `text` in `code` at or after char `after`, going
+/// around to the start, by char range.
+pub fn next_occurrence(code: &str, text: &str, after: usize) -> Option<Range<usize>> {
+    if text.is_empty() {
+        return None;
+    }
+    let byte = code
+        .char_indices()
+        .nth(after)
+        .map_or(code.len(), |(i, _)| i);
+    let found = code[byte..]
+        .find(text)
+        .map(|i| byte + i)
+        .or_else(|| code.find(text))?;
+    let start = code[..found].chars().count();
+    Some(start..start + text.chars().count())
+}
+
+/// Line and column of char `index` of `code`, from 0.
+fn line_column(code: &str, index: usize) -> (usize, usize) {
+    let before: String = code.chars().take(index).collect();
+    let line = before.matches('\n').count();
+    let column = before.chars().rev().take_while(|&c| c != '\n').count();
+    (line, column)
+}
+
+/// Char index of `column` on `line`, or of its end if it's shorter.
+fn char_index(code: &str, line: usize, column: usize) -> Option<usize> {
+    let mut index = 0;
+    for (number, text) in code.split('\n').enumerate() {
+        let length = text.chars().count();
+        if number == line {
+            return Some(index + column.min(length));
+        }
+        index += length + 1;
+    }
+    None
+}
+
+/// The extra cursors of one editor.
+#[derive(Default)]
+pub struct Cursors {
+    /// Selections besides the editor's own, by char range.
+    extra: Vec<Range<usize>>,
+    /// Position in [`super::project::Project::sources`] of the code they're in.
+    source: usize,
+}
+
+impl Cursors {
+    pub fn clear(&mut self) {
+        self.extra.clear();
+    }
+
+    /// Take the shortcuts and, with several cursors, the typing of the
+    /// editor with `id` showing `code`, the source at `source` in
+    /// [`super::project::Project::sources`]. Call before showing the editor;
+    /// returns whether `code` changed.
+    pub fn input(&mut self, ui: &egui::Ui, id: egui::Id, code: &mut String, source: usize) -> bool {
+        if source != self.source {
+            self.extra.clear();
+            self.source = source;
+        }
+        if !ui.memory(|memory| memory.has_focus(id)) {
+            return false;
+        }
+        let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), id) else {
+            return false;
+        };
+        let Some(range) = state.cursor.char_range() else {
+            return false;
+        };
+        let [start, end] = range.sorted();
+        let primary = start.index..end.index;
+        let mut selections = vec![primary.clone()];
+        selections.extend(self.extra.iter().cloned());
+        let mut edits = Vec::new();
+        let mut collapse = false;
+        ui.input_mut(|input| {
+            if input.consume_shortcut(&NEXT_OCCURRENCE_SHORTCUT) {
+                if primary.is_empty() {
+                    let byte = code
+                        .char_indices()
+                        .nth(primary.start)
+                        .map_or(code.len(), |(i, _)| i);
+                    let word = super::complete::word_at(code, byte);
+                    let start = code[..word.start].chars().count();
+                    selections[0] = start..start + code[word].chars().count();
+                } else {
+                    let text: String = code
+                        .chars()
+                        .skip(primary.start)
+                        .take(primary.len())
+                        .collect();
+                    let last = selections.last().map_or(0, |last| last.end);
+                    let next = next_occurrence(code, &text, last);
+                    if let Some(next) = next.filter(|next| !selections.contains(next)) {
+                        selections.push(next);
+                    }
+                }
+            }
+            let below = input.consume_shortcut(&CURSOR_BELOW_SHORTCUT);
+            if below || input.consume_shortcut(&CURSOR_ABOVE_SHORTCUT) {
+                let from = match below {
+                    true => selections.iter().map(|s| s.end).max(),
+                    false => selections.iter().map(|s| s.start).min(),
+                };
+                let (line, column) = line_column(code, from.unwrap_or_default());
+                let line = if below {
+                    line.checked_add(1)
+                } else {
+                    line.checked_sub(1)
+                };
+                if let Some(index) = line.and_then(|line| char_index(code, line, column)) {
+                    if !selections.contains(&(index..index)) {
+                        selections.push(index..index);
+                    }
+                }
+            }
+            if selections.len() == 1 {
+                return;
+            }
+            input.events.retain(|event| match event {
+                egui::Event::Text(text) | egui::Event::Paste(text) => {
+                    edits.push(Edit::Insert(text.clone()));
+                    false
+                }
+                egui::Event::Key {
+                    key,
+                    pressed: true,
+                    modifiers,
+                    ..
+                } if !modifiers.command && !modifiers.alt => {
+                    let edit = match key {
+                        egui::Key::Backspace => Edit::Backspace,
+                        egui::Key::Delete => Edit::Delete,
+                        egui::Key::Enter => Edit::Insert("\n".to_string()),
+                        egui::Key::Tab if !modifiers.shift => Edit::Insert("\t".to_string()),
+                        egui::Key::Escape => {
+                            collapse = true;
+                            return false;
+                        }
+                        _ => {
+                            collapse = true;
+                            return true;
+                        }
+                    };
+                    edits.push(edit);
+                    false
+                }
+                egui::Event::Key { pressed: true, .. }
+                | egui::Event::PointerButton { pressed: true, .. } => {
+                    collapse = true;
+                    true
+                }
+                _ => true,
+            });
+        });
+        let changed = !edits.is_empty();
+        for edit in &edits {
+            let cursors = apply(code, &selections, edit);
+            selections = cursors.into_iter().map(|cursor| cursor..cursor).collect();
+        }
+        let primary = selections.remove(0);
+        selections.retain(|selection| *selection != primary);
+        selections.dedup();
+        self.extra = if collapse { Vec::new() } else { selections };
+        state.cursor.set_char_range(Some(CCursorRange::two(
+            CCursor::new(primary.start),
+            CCursor::new(primary.end),
+        )));
+        state.store(ui.ctx(), id);
+        changed
+    }
+
+    /// Draw the extra cursors over the editor `output`.
+    pub fn paint(&self, ui: &egui::Ui, output: &egui::text_edit::TextEditOutput) {
+        let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+        let stroke = ui.visuals().text_cursor.stroke;
+        paint_ranges(ui, output, &self.extra, ui.visuals().selection.bg_fill);
+        for selection in &self.extra {
+            let caret = output.galley.pos_from_ccursor(CCursor::new(selection.end));
+            let caret = caret.translate(output.galley_pos.to_vec2());
+            painter.line_segment([caret.center_top(), caret.center_bottom()], stroke);
+        }
+    }
+}
+
+/// Fill the background of `ranges`, by char index, of the editor `output`.
+pub fn paint_ranges(
+    ui: &egui::Ui,
+    output: &egui::text_edit::TextEditOutput,
+    ranges: &[Range<usize>],
+    color: egui::Color32,
+) {
+    let painter = ui.painter().with_clip_rect(output.text_clip_rect);
+    let galley = &output.galley;
+    for range in ranges.iter().filter(|range| !range.is_empty()) {
+        let start = galley.from_ccursor(CCursor::new(range.start));
+        let end = galley.from_ccursor(CCursor::new(range.end));
+        for row in start.rcursor.row..=end.rcursor.row {
+            let Some(rect) = galley.rows.get(row).map(|row| row.rect) else {
+                continue;
+            };
+            let left = match row == start.rcursor.row {
+                true => galley.pos_from_cursor(&start).left(),
+                false => rect.left(),
+            };
+            let right = match row == end.rcursor.row {
+                true => galley.pos_from_cursor(&end).left(),
+                false => rect.right(),
+            };
+            let rect = egui::Rect::from_x_y_ranges(left..=right, rect.y_range());
+            painter.rect_filled(rect.translate(output.galley_pos.to_vec2()), 0.0, color);
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Files dropped onto the window. Shaders open in the editor, project
+//! manifests load with the files they list, and images become channel inputs
+//! once a channel is picked for them.
+//!
+//! Native builds get the paths of dropped files. The web version gets their
+//! content instead, so a manifest has to be dropped along with its sources.
+use super::file::{IMAGE_EXTENSIONS, SHADER_EXTENSIONS};
+use super::project::{Channel, ChannelInput, PassKind, Project, Sampler, PROJECT_EXTENSION};
+use anyhow::{anyhow, Context as _};
+use eframe::egui;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Shader,
+    /// A project manifest or a Shadertoy export.
+    Project,
+    Image,
+}
+
+impl Kind {
+    /// What the file `name` is, by its extension. `None` for files that
+    /// can't be opened.
+    pub fn of(name: &str) -> Option<Self> {
+        let extension = Path::new(name)
+            .extension()?
+            .to_string_lossy()
+            .to_ascii_lowercase();
+        if SHADER_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Kind::Shader)
+        } else if extension == PROJECT_EXTENSION {
+            Some(Kind::Project)
+        } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Kind::Image)
+        } else {
+            None
+        }
+    }
+}
+
+/// File name of `file`. Native builds only fill in its path.
+pub fn name(file: &egui::DroppedFile) -> String {
+    match &file.path {
+        Some(path) if file.name.is_empty() => path.file_name().map_or_else(
+            || path.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+        _ => file.name.clone(),
+    }
+}
+
+/// Content of `file`, as egui got it or from disk.
+pub fn read(file: &egui::DroppedFile) -> crate::app::Result<Arc<[u8]>> {
+    if let Some(bytes) = &file.bytes {
+        return Ok(bytes.clone());
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = &file.path {
+        return Ok(std::fs::read(path)?.into());
+    }
+    Err(anyhow!("{} has no content", name(file)))
+}
+
+/// Content of `file` as text.
+pub fn read_text(file: &egui::DroppedFile) -> crate::app::Result<String> {
+    let bytes = read(file)?;
+    String::from_utf8(bytes.to_vec()).with_context(|| format!("{} isn't text", name(file)))
+}
+
+/// The project of `manifest`, with the code of its sources taken from the
+/// `files` dropped with it, matched by file name.
+pub fn project(manifest: &str, files: &[egui::DroppedFile]) -> crate::app::Result<Project> {
+    let mut project = Project::from_manifest(manifest)?;
+    for source in project.sources_mut() {
+        let file_name = source.path.file_name().map(|n| n.to_string_lossy());
+        let file = files
+            .iter()
+            .find(|file| Some(name(file).as_str()) == file_name.as_deref())
+            .ok_or_else(|| anyhow!("{} wasn't dropped with the manifest", source.path.display()))?;
+        source.code = read_text(file)?;
+    }
+    Ok(project)
+}
+
+/// Asks which channel of which pass dropped images go to, one at a time.
+#[derive(Default)]
+pub struct ChannelPrompt {
+    /// Paths of the images waiting for a channel, as channel inputs read them.
+    pending: VecDeque<PathBuf>,
+    pass: Option<PassKind>,
+}
+
+pub enum ChannelAction {
+    /// Read the image at `path` in channel `index` of `pass`.
+    Assign {
+        pass: PassKind,
+        index: usize,
+        path: PathBuf,
+    },
+}
+
+impl ChannelAction {
+    /// Make the change to `project`.
+    pub fn apply(self, project: &mut Project) {
+        let ChannelAction::Assign { pass, index, path } = self;
+        if let Some(pass) = project.pass_mut(pass) {
+            pass.channels[index] = Some(Channel {
+                input: ChannelInput::Texture { path },
+                sampler: Sampler::texture(),
+            });
+        }
+    }
+}
+
+impl ChannelPrompt {
+    /// Ask about the image at `path` after the ones already waiting.
+    pub fn push(&mut self, path: PathBuf) {
+        self.pending.push_back(path);
+    }
+
+    /// `selected` is the pass offered first, the image pass if it's `None`.
+    pub fn ui(
+        &mut self,
+        ctx: &egui::Context,
+        project: &Project,
+        selected: Option<PassKind>,
+    ) -> Option<ChannelAction> {
+        let path = self.pending.front()?.clone();
+        let kinds: Vec<PassKind> = project
+            .passes
+            .iter()
+            .map(|pass| pass.kind)
+            .filter(|kind| kind.is_buffer() || *kind == PassKind::Image)
+            .collect();
+        let mut kind = self
+            .pass
+            .or(selected)
+            .filter(|kind| kinds.contains(kind))
+            .unwrap_or(PassKind::Image);
+        let mut action = None;
+        let mut cancel = false;
+        egui::Window::new("Add image")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Read {} in a channel of", path.display()));
+                    egui::ComboBox::from_id_salt("dropped_image_pass")
+                        .selected_text(kind.name())
+                        .show_ui(ui, |ui| {
+                            for &k in &kinds {
+                                ui.selectable_value(&mut kind, k, k.name());
+                            }
+                        });
+                });
+                let Some(pass) = project.pass(kind) else {
+                    return;
+                };
+                egui::Grid::new("dropped_image_channels").show(ui, |ui| {
+                    for (index, channel) in pass.channels.iter().enumerate() {
+                        if ui.button(format!("iChannel{index}")).clicked() {
+                            action = Some(ChannelAction::Assign {
+                                pass: kind,
+                                index,
+                                path: path.clone(),
+                            });
+                        }
+                        match channel {
+                            Some(channel) => {
+                                ui.label(format!("replaces {}", channel.input.label()))
+                            }
+                            None => ui.weak("empty"),
+                        };
+                        ui.end_row();
+                    }
+                });
+                if ui.button("Cancel").clicked() {
+                    cancel = true;
+                }
+            });
+        self.pass = Some(kind);
+        if action.is_some() || cancel {
+            self.pending.pop_front();
+        }
+        action
+    }
+}
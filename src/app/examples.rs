@@ -0,0 +1,124 @@
+//! Example shaders and projects built into the app, listed in the Examples
+//! menu and first in the gallery. `build.rs` compiles their passes ahead of
+//! time and decodes their images, so the web version can run them without a
+//! shader compiler or image decoders.
+use super::gallery::Metadata;
+use super::passes::TextureImage;
+use super::project::Project;
+use std::path::Path;
+
+/// A bundled shader or project.
+pub struct Example {
+    /// Name of the file it opens as.
+    pub file_name: &'static str,
+    /// The shader, or the manifest followed by the files it lists, by path
+    /// relative to the manifest.
+    files: &'static [(&'static str, &'static str)],
+}
+
+/// An example made of one file in `examples/`.
+macro_rules! single {
+    ($file:literal) => {
+        Example {
+            file_name: $file,
+            files: &[($file, include_str!(concat!("examples/", $file)))],
+        }
+    };
+}
+
+/// An example project in `examples/<directory>/`, with its manifest and sources.
+macro_rules! project {
+    ($directory:literal, $manifest:literal, [$($source:literal),+]) => {
+        Example {
+            file_name: $manifest,
+            files: &[
+                ($manifest, include_str!(concat!("examples/", $directory, "/", $manifest))),
+                $(($source, include_str!(concat!("examples/", $directory, "/", $source))),)+
+            ],
+        }
+    };
+}
+
+/// The examples in menu order. The first one is open when the app starts.
+pub const EXAMPLES: &[Example] = &[
+    single!("default.glsl"),
+    single!("pattern.glsl"),
+    single!("raymarcher.glsl"),
+    project!("fluid", "fluid.json", ["buffer_a.glsl", "image.glsl"]),
+    project!("texture", "texture.json", ["image.glsl"]),
+    project!("audio", "audio.json", ["buffer_a.glsl", "image.glsl"]),
+];
+
+impl Example {
+    pub fn project(&self) -> Project {
+        let (_, first) = self.files[0];
+        if !super::file::is_project_path(Path::new(self.file_name)) {
+            return Project::single(self.file_name, first);
+        }
+        let mut project = Project::from_manifest(first).expect("Bundled manifests are valid");
+        for source in project.sources_mut() {
+            if let Some((_, code)) = self
+                .files
+                .iter()
+                .find(|(path, _)| source.path == Path::new(path))
+            {
+                source.code = code.to_string();
+            }
+        }
+        project
+    }
+
+    /// Header metadata of the image pass; see [`Metadata`].
+    pub fn metadata(&self) -> Metadata {
+        Metadata::parse(&self.project().image().source.code)
+    }
+
+    /// The title from the header comments, or the file name.
+    pub fn title(&self) -> String {
+        self.metadata()
+            .title
+            .unwrap_or_else(|| self.file_name.to_string())
+    }
+}
+
+// `MEDIA: &[(&str, u32, u32, &[u8])]`: file name, size and RGBA8 pixels of
+// every image in `examples/`, top row first.
+include!(concat!(env!("OUT_DIR"), "/example_media.rs"));
+
+/// The bundled image `path` ends with, for example projects opened from
+/// memory, where their images aren't on disk.
+pub fn texture(path: &Path, vflip: bool) -> Option<TextureImage> {
+    let (_, width, height, rgba) = MEDIA.iter().find(|(name, ..)| path.ends_with(name))?;
+    let mut rgba = rgba.to_vec();
+    if vflip {
+        let row = *width as usize * 4;
+        let rows: Vec<&[u8]> = rgba.chunks(row).rev().collect();
+        rgba = rows.concat();
+    }
+    Some(TextureImage {
+        width: *width,
+        height: *height,
+        rgba,
+    })
+}
+
+// `PRECOMPILED: &[(bool, &str, &str, &[u8])]`: whether the pass is a buffer,
+// its common code, its code and the SPIR-V of the filled-in template, for
+// every pass of every example.
+#[cfg(target_arch = "wasm32")]
+include!(concat!(env!("OUT_DIR"), "/example_shaders.rs"));
+
+/// SPIR-V compiled by `build.rs` for a pass with exactly this code.
+#[cfg(target_arch = "wasm32")]
+pub fn precompiled(
+    kind: super::project::PassKind,
+    common: &str,
+    content: &str,
+) -> Option<Vec<u32>> {
+    PRECOMPILED
+        .iter()
+        .find(|(is_buffer, c, code, _)| {
+            *is_buffer == kind.is_buffer() && *c == common && *code == content
+        })
+        .map(|(.., spirv)| bytemuck::pod_collect_to_vec(spirv))
+}
@@ -0,0 +1,33 @@
+// Stands in for a Shadertoy audio input until those are supported: the
+// spectrum in the bottom half, read at y = 0.25, and the waveform in the top
+// half, read at y = 0.75, both in [0, 1]. The "music" is a kick drum on every
+// beat, a bass line and hi-hats on the off-beats.
+
+const float BPM = 120.0;
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    float x = fragCoord.x / iResolution.x;
+    float beat = iTime * BPM / 60.0;
+    float sinceBeat = fract(beat);
+    float kick = exp(-8.0 * sinceBeat);
+    float hat = exp(-30.0 * fract(beat + 0.5));
+    float bass = 0.5 + 0.5 * sin(3.14159 * floor(beat / 2.0));
+    float value;
+    if (fragCoord.y < 0.5 * iResolution.y) {
+        // Energy per frequency, low to high.
+        float low = kick * exp(-40.0 * x);
+        float mid = 0.6 * bass * exp(-80.0 * abs(x - 0.08 - 0.04 * bass));
+        float high = 0.5 * hat * smoothstep(0.5, 0.9, x);
+        float noise = 0.1 * fract(sin(x * 9871.0 + floor(iTime * 30.0)) * 43758.5453);
+        value = clamp(low + mid + high + noise * (0.2 + kick), 0.0, 1.0);
+    } else {
+        // The last few milliseconds of the signal.
+        float t = iTime + 0.02 * x;
+        float signal = kick * sin(6.28318 * 55.0 * t)
+            + 0.4 * bass * sin(6.28318 * 110.0 * t)
+            + 0.2 * hat * sin(6.28318 * 3000.0 * t);
+        value = 0.5 + 0.35 * signal;
+    }
+    fragColor = vec4(value, value, value, 1.0);
+}
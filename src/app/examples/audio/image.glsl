@@ -0,0 +1,22 @@
+// Title: Audio visualizer
+// Author: shadertoy_rs
+// Tags: audio, music, spectrum, buffer
+// License: CC0
+//
+// Spectrum bars and a waveform drawn from iChannel0 laid out like a
+// Shadertoy audio input. Buffer A synthesizes it for now.
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 uv = fragCoord / iResolution.xy;
+    const float BARS = 32.0;
+    float bar = floor(uv.x * BARS);
+    float level = texture(iChannel0, vec2((bar + 0.5) / BARS, 0.25)).x;
+    float inBar = step(0.1, fract(uv.x * BARS)) * step(uv.y, level * 0.8);
+    vec3 color = inBar * mix(vec3(0.1, 0.4, 1.0), vec3(1.0, 0.2, 0.4), uv.y);
+
+    float wave = texture(iChannel0, vec2(uv.x, 0.75)).x;
+    float line = smoothstep(0.012, 0.0, abs(uv.y - (0.5 + 0.6 * (wave - 0.5))));
+    color += line * vec3(1.0, 0.9, 0.6);
+    fragColor = vec4(color, 1.0);
+}
@@ -0,0 +1,40 @@
+// Velocity in xy and dye in z, carried along by the velocity every frame.
+// w keeps the divergence, which stands in for pressure in the next frame.
+
+vec4 fetch(vec2 p)
+{
+    return texture(iChannel0, p / iResolution.xy);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    if (iFrame < 1.0) {
+        fragColor = vec4(0.0);
+        return;
+    }
+    // Semi-Lagrangian advection: take what flowed in from upstream.
+    vec4 state = fetch(fragCoord - fetch(fragCoord).xy);
+    vec4 n = fetch(fragCoord + vec2(0.0, 1.0));
+    vec4 s = fetch(fragCoord - vec2(0.0, 1.0));
+    vec4 e = fetch(fragCoord + vec2(1.0, 0.0));
+    vec4 w = fetch(fragCoord - vec2(1.0, 0.0));
+    float divergence = 0.5 * (e.x - w.x + n.y - s.y);
+    // Push out of converging areas and smooth out the rest.
+    vec2 velocity = mix(state.xy, 0.25 * (n.xy + s.xy + e.xy + w.xy), 0.2);
+    velocity += 0.4 * vec2(e.w - w.w, n.w - s.w);
+
+    // Two jets circling the center.
+    float dye = 0.995 * state.z;
+    for (int i = 0; i < 2; i++) {
+        float angle = 0.7 * iTime + 3.14159 * float(i);
+        vec2 jet = 0.5 * iResolution.xy + 0.25 * iResolution.y * vec2(cos(angle), sin(angle));
+        vec2 offset = fragCoord - jet;
+        float splat = exp(-dot(offset, offset) / (0.0005 * iResolution.y * iResolution.y));
+        velocity += 2.0 * splat * vec2(-sin(angle), cos(angle));
+        dye += splat;
+    }
+    // Still walls at the edges.
+    vec2 edge = min(fragCoord, iResolution.xy - fragCoord);
+    velocity *= smoothstep(0.0, 4.0, min(edge.x, edge.y));
+    fragColor = vec4(clamp(velocity, -4.0, 4.0), min(dye, 4.0), divergence);
+}
@@ -0,0 +1,16 @@
+// Title: Feedback fluid
+// Author: shadertoy_rs
+// Tags: buffer, feedback, fluid, simulation
+// License: CC0
+//
+// Buffer A reads its own previous frame to move dye along a velocity field.
+// The image colors the dye by the direction it flows in.
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec4 state = texture(iChannel0, fragCoord / iResolution.xy);
+    float heading = atan(state.y, state.x);
+    vec3 hue = 0.5 + 0.5 * cos(heading + vec3(0.0, 2.1, 4.2));
+    vec3 color = hue * (1.0 - exp(-1.5 * state.z));
+    fragColor = vec4(color, 1.0);
+}
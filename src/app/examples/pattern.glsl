@@ -0,0 +1,29 @@
+// Title: Truchet tiles
+// Author: shadertoy_rs
+// Tags: 2d, pattern, tiles
+// License: CC0
+//
+// Quarter circles in every tile of a grid, mirrored at random so that they
+// join up into wandering lines.
+
+float hash(vec2 p)
+{
+    return fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 uv = (fragCoord - 0.5 * iResolution.xy) / iResolution.y;
+    uv = uv * 6.0 + vec2(0.3, 0.2) * iTime;
+    vec2 cell = floor(uv);
+    vec2 local = fract(uv) - 0.5;
+    if (hash(cell) < 0.5) {
+        local.x = -local.x;
+    }
+    // Distance to the arc around the nearer of two opposite corners.
+    vec2 corner = local.x + local.y > 0.0 ? vec2(0.5) : vec2(-0.5);
+    float d = abs(length(local - corner) - 0.5);
+    float line = smoothstep(0.08, 0.05, d);
+    vec3 background = 0.5 + 0.5 * cos(0.5 * iTime + 0.2 * cell.xyx + vec3(0.0, 2.0, 4.0));
+    fragColor = vec4(mix(0.35 * background, vec3(1.0), line), 1.0);
+}
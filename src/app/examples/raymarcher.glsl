@@ -0,0 +1,84 @@
+// Title: Raymarcher
+// Author: shadertoy_rs
+// Tags: 3d, raymarching, sdf
+// License: CC0
+//
+// A bouncing sphere over a checkered floor, sphere traced against a signed
+// distance field, with soft shadows and fog.
+
+float sdScene(vec3 p)
+{
+    float bounce = abs(sin(1.5 * iTime));
+    float sphere = length(p - vec3(0.0, 1.0 + bounce, 0.0)) - 1.0;
+    return min(sphere, p.y);
+}
+
+vec3 sceneNormal(vec3 p)
+{
+    vec2 e = vec2(0.001, 0.0);
+    return normalize(vec3(
+        sdScene(p + e.xyy) - sdScene(p - e.xyy),
+        sdScene(p + e.yxy) - sdScene(p - e.yxy),
+        sdScene(p + e.yyx) - sdScene(p - e.yyx)));
+}
+
+// Distance along the ray to the scene, or -1 if it escapes.
+float march(vec3 origin, vec3 direction)
+{
+    float t = 0.0;
+    for (int i = 0; i < 128; i++) {
+        float d = sdScene(origin + direction * t);
+        if (d < 0.001) {
+            return t;
+        }
+        t += d;
+        if (t > 50.0) {
+            break;
+        }
+    }
+    return -1.0;
+}
+
+float softShadow(vec3 origin, vec3 direction)
+{
+    float light = 1.0;
+    float t = 0.02;
+    for (int i = 0; i < 48; i++) {
+        float h = sdScene(origin + direction * t);
+        light = min(light, 8.0 * h / t);
+        t += clamp(h, 0.02, 0.5);
+        if (light < 0.001 || t > 20.0) {
+            break;
+        }
+    }
+    return clamp(light, 0.0, 1.0);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 uv = (fragCoord - 0.5 * iResolution.xy) / iResolution.y;
+    float angle = 0.3 * iTime;
+    vec3 origin = vec3(5.0 * sin(angle), 2.5, 5.0 * cos(angle));
+    vec3 forward = normalize(vec3(0.0, 1.0, 0.0) - origin);
+    vec3 right = normalize(cross(forward, vec3(0.0, 1.0, 0.0)));
+    vec3 up = cross(right, forward);
+    vec3 direction = normalize(uv.x * right + uv.y * up + 1.5 * forward);
+
+    vec3 sky = vec3(0.6, 0.75, 0.9) - 0.4 * direction.y;
+    vec3 color = sky;
+    float t = march(origin, direction);
+    if (t > 0.0) {
+        vec3 p = origin + direction * t;
+        vec3 n = sceneNormal(p);
+        vec3 sun = normalize(vec3(0.6, 0.8, 0.4));
+        vec3 albedo = vec3(0.9, 0.3, 0.2);
+        if (p.y < 0.01) {
+            float checker = mod(floor(p.x) + floor(p.z), 2.0);
+            albedo = vec3(0.3 + 0.4 * checker);
+        }
+        float diffuse = max(dot(n, sun), 0.0) * softShadow(p + 0.01 * n, sun);
+        color = albedo * (0.15 + 0.85 * diffuse);
+        color = mix(color, sky, 1.0 - exp(-0.002 * t * t));
+    }
+    fragColor = vec4(pow(color, vec3(0.4545)), 1.0);
+}
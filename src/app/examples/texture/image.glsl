@@ -0,0 +1,25 @@
+// Title: Texture channel
+// Author: shadertoy_rs
+// Tags: texture, channel, distortion
+// License: CC0
+//
+// Samples the image bound to iChannel0 through a swirl that follows the
+// mouse, or circles the center when the mouse isn't pressed.
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 uv = fragCoord / iResolution.xy;
+    vec2 center = iMouse.z > 0.0
+        ? iMouse.xy / iResolution.xy
+        : 0.5 + 0.2 * vec2(cos(iTime), sin(iTime));
+    vec2 offset = (uv - center) * vec2(iResolution.x / iResolution.y, 1.0);
+    float radius = length(offset);
+    float twist = 4.0 * exp(-6.0 * radius) * sin(0.7 * iTime);
+    float c = cos(twist);
+    float s = sin(twist);
+    offset = mat2(c, s, -s, c) * offset;
+    uv = center + offset / vec2(iResolution.x / iResolution.y, 1.0);
+    // The texture repeats, so keep it square and tile it.
+    vec2 st = uv * vec2(iResolution.x / iResolution.y, 1.0) * 2.0;
+    fragColor = vec4(texture(iChannel0, st).rgb, 1.0);
+}
@@ -0,0 +1,613 @@
+use super::passes::Program;
+use super::render::{Frame, OffscreenRenderer, OFFSCREEN_FORMAT, OFFSCREEN_HDR_FORMAT};
+use super::WgpuUniform;
+use eframe::egui;
+use eframe::egui_wgpu::wgpu;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+mod animation;
+mod palette;
+pub use animation::{ApngWriter, GifWriter};
+pub use palette::{Dither, Palette, PaletteSettings, Quantizer};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    /// One `frame_00000.png` per frame inside the output directory.
+    PngSequence,
+    /// One float OpenEXR file per frame inside the output directory.
+    ExrSequence,
+    /// One Radiance RGBE `.hdr` file per frame inside the output directory.
+    HdrSequence,
+    /// A single uncompressed YUV4MPEG2 (4:4:4) stream.
+    Y4m,
+    /// A looping animated GIF.
+    Gif,
+    /// A looping animated PNG.
+    Apng,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 6] = [
+        ExportFormat::PngSequence,
+        ExportFormat::ExrSequence,
+        ExportFormat::HdrSequence,
+        ExportFormat::Y4m,
+        ExportFormat::Gif,
+        ExportFormat::Apng,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ExportFormat::PngSequence => "PNG sequence",
+            ExportFormat::ExrSequence => "OpenEXR sequence (float)",
+            ExportFormat::HdrSequence => "Radiance HDR sequence (float)",
+            ExportFormat::Y4m => "Y4M video",
+            ExportFormat::Gif => "GIF",
+            ExportFormat::Apng => "APNG",
+        }
+    }
+
+    /// File extension of the output, empty for formats that write a directory.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::PngSequence | ExportFormat::ExrSequence | ExportFormat::HdrSequence => "",
+            ExportFormat::Y4m => "y4m",
+            ExportFormat::Gif => "gif",
+            ExportFormat::Apng => "png",
+        }
+    }
+
+    /// Look a format up by its file-type name, as used on the command line.
+    pub fn from_extension(name: &str) -> Option<ExportFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(ExportFormat::PngSequence),
+            "exr" => Some(ExportFormat::ExrSequence),
+            "hdr" => Some(ExportFormat::HdrSequence),
+            "y4m" => Some(ExportFormat::Y4m),
+            "gif" => Some(ExportFormat::Gif),
+            "apng" => Some(ExportFormat::Apng),
+            _ => None,
+        }
+    }
+
+    /// Whether the format writes one file per frame into a directory.
+    pub fn is_sequence(self) -> bool {
+        matches!(
+            self,
+            ExportFormat::PngSequence | ExportFormat::ExrSequence | ExportFormat::HdrSequence
+        )
+    }
+
+    /// Whether the format keeps unclamped float values, which needs an
+    /// `Rgba32Float` target instead of the usual 8-bit one.
+    pub fn is_float(self) -> bool {
+        matches!(self, ExportFormat::ExrSequence | ExportFormat::HdrSequence)
+    }
+
+    pub fn target_format(self) -> wgpu::TextureFormat {
+        if self.is_float() {
+            OFFSCREEN_HDR_FORMAT
+        } else {
+            OFFSCREEN_FORMAT
+        }
+    }
+
+    /// Whether the format stores colors through a palette.
+    pub fn has_palette(self) -> bool {
+        matches!(self, ExportFormat::Gif | ExportFormat::Apng)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ExportSettings {
+    /// Render resolution, before downscaling.
+    pub width: u32,
+    pub height: u32,
+    /// Integer downscale factor applied to every frame. Values above 1
+    /// supersample, which also makes palettized output less noisy.
+    pub scale: u32,
+    pub fps: u32,
+    /// Time of the first rendered frame in seconds.
+    pub start_time: f32,
+    /// Length of the render (or of one loop) in seconds.
+    pub duration: f32,
+    pub format: ExportFormat,
+    pub palette: PaletteSettings,
+    /// Output directory for sequences, output file for streams.
+    pub path: PathBuf,
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 720,
+            scale: 1,
+            fps: 60,
+            start_time: 0.0,
+            duration: 5.0,
+            format: ExportFormat::PngSequence,
+            palette: PaletteSettings::default(),
+            path: PathBuf::from("render"),
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn frame_count(&self) -> u32 {
+        (self.duration * self.fps as f32).round().max(1.0) as u32
+    }
+
+    /// Index of the first frame on the fixed-rate clock.
+    pub fn first_frame(&self) -> u32 {
+        (self.start_time * self.fps as f32).round().max(0.0) as u32
+    }
+
+    /// Size of the written frames, after downscaling.
+    pub fn output_size(&self) -> (u32, u32) {
+        (
+            (self.width / self.scale).max(1),
+            (self.height / self.scale).max(1),
+        )
+    }
+
+    /// Create the writer for this format, writing `frame_count` frames to `path`.
+    fn create_writer(&self, frame_count: u32) -> crate::app::Result<Box<dyn FrameWriter>> {
+        match self.format {
+            format if format.is_sequence() => {
+                Ok(Box::new(ImageSequenceWriter::new(&self.path, format)?))
+            }
+            _ => {
+                let file = std::io::BufWriter::new(std::fs::File::create(&self.path)?);
+                self.create_stream_writer(file, frame_count)
+            }
+        }
+    }
+
+    /// Create the writer for single-file formats.
+    fn create_stream_writer<W: Write + 'static>(
+        &self,
+        output: W,
+        frame_count: u32,
+    ) -> crate::app::Result<Box<dyn FrameWriter>> {
+        let (width, height) = self.output_size();
+        Ok(match self.format {
+            ExportFormat::PngSequence | ExportFormat::ExrSequence | ExportFormat::HdrSequence => {
+                anyhow::bail!("Image sequences are written to a directory, not a stream")
+            }
+            ExportFormat::Y4m => Box::new(Y4mWriter::new(output, width, height, self.fps)?),
+            ExportFormat::Gif => Box::new(animation::GifWriter::new(
+                output,
+                width,
+                height,
+                self.fps,
+                self.palette,
+            )?),
+            ExportFormat::Apng => Box::new(animation::ApngWriter::new(
+                output,
+                self.fps,
+                frame_count,
+                self.palette,
+            )),
+        })
+    }
+}
+
+/// Receives rendered frames in order and writes them somewhere.
+pub trait FrameWriter {
+    fn write_frame(&mut self, index: u32, frame: &Frame) -> crate::app::Result<()>;
+    fn finish(&mut self) -> crate::app::Result<()>;
+}
+
+struct ImageSequenceWriter {
+    directory: PathBuf,
+    format: ExportFormat,
+}
+
+impl ImageSequenceWriter {
+    fn new(directory: &Path, format: ExportFormat) -> crate::app::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            format,
+        })
+    }
+}
+
+impl FrameWriter for ImageSequenceWriter {
+    fn write_frame(&mut self, index: u32, frame: &Frame) -> crate::app::Result<()> {
+        let extension = match self.format {
+            ExportFormat::ExrSequence => "exr",
+            ExportFormat::HdrSequence => "hdr",
+            _ => "png",
+        };
+        let path = self.directory.join(format!("frame_{index:05}.{extension}"));
+        match self.format {
+            ExportFormat::ExrSequence => {
+                let rgb = frame.rgb_f32();
+                let width = frame.width as usize;
+                exr::prelude::write_rgb_file(path, width, frame.height as usize, |x, y| {
+                    let i = (y * width + x) * 3;
+                    (rgb[i], rgb[i + 1], rgb[i + 2])
+                })?;
+            }
+            ExportFormat::HdrSequence => {
+                let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                let pixels = frame
+                    .rgb_f32()
+                    .chunks_exact(3)
+                    .map(|c| image::Rgb([c[0], c[1], c[2]]))
+                    .collect::<Vec<_>>();
+                image::codecs::hdr::HdrEncoder::new(file).encode(
+                    &pixels,
+                    frame.width as usize,
+                    frame.height as usize,
+                )?;
+            }
+            _ => image::save_buffer(
+                path,
+                &frame.rgb(),
+                frame.width,
+                frame.height,
+                image::ExtendedColorType::Rgb8,
+            )?,
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> crate::app::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes a YUV4MPEG2 stream of 4:4:4 frames.
+pub struct Y4mWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Y4mWriter<W> {
+    pub fn new(mut writer: W, width: u32, height: u32, fps: u32) -> crate::app::Result<Self> {
+        writeln!(writer, "YUV4MPEG2 W{width} H{height} F{fps}:1 Ip A1:1 C444")?;
+        Ok(Self { writer })
+    }
+}
+
+impl<W: Write> FrameWriter for Y4mWriter<W> {
+    fn write_frame(&mut self, _index: u32, frame: &Frame) -> crate::app::Result<()> {
+        // BT.601 limited range, planar Y then Cb then Cr.
+        let pixels = frame.width as usize * frame.height as usize;
+        let mut planes = vec![0u8; pixels * 3];
+        for (i, rgb) in frame.rgb().chunks_exact(3).enumerate() {
+            let (r, g, b) = (
+                rgb[0] as f32 / 255.0,
+                rgb[1] as f32 / 255.0,
+                rgb[2] as f32 / 255.0,
+            );
+            planes[i] = (16.0 + 65.481 * r + 128.553 * g + 24.966 * b).round() as u8;
+            planes[pixels + i] = (128.0 - 37.797 * r - 74.203 * g + 112.0 * b).round() as u8;
+            planes[2 * pixels + i] = (128.0 + 112.0 * r - 93.786 * g - 18.214 * b).round() as u8;
+        }
+        self.writer.write_all(b"FRAME\n")?;
+        self.writer.write_all(&planes)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> crate::app::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Renders a fixed number of frames on the deterministic clock, one frame per
+/// [`RenderJob::step`], and hands them to a [`FrameWriter`].
+pub struct RenderJob {
+    renderer: OffscreenRenderer,
+    writer: Box<dyn FrameWriter>,
+    fps: u32,
+    scale: u32,
+    first_frame: u32,
+    frame: u32,
+    frame_count: u32,
+}
+
+impl RenderJob {
+    /// Render `frame_count` frames, usually [`ExportSettings::frame_count`].
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        program: &Program,
+        settings: &ExportSettings,
+        frame_count: u32,
+    ) -> crate::app::Result<Self> {
+        Ok(Self {
+            renderer: OffscreenRenderer::new(
+                device,
+                queue,
+                program,
+                settings.format.target_format(),
+                settings.width,
+                settings.height,
+            ),
+            writer: settings.create_writer(frame_count)?,
+            fps: settings.fps,
+            scale: settings.scale,
+            first_frame: settings.first_frame(),
+            frame: 0,
+            frame_count,
+        })
+    }
+
+    /// Render and write the next frame. Returns `true` once every frame is written.
+    pub fn step(&mut self) -> crate::app::Result<bool> {
+        if self.frame < self.frame_count {
+            let uniform = WgpuUniform::at_frame(
+                self.first_frame + self.frame,
+                self.fps,
+                self.renderer.width(),
+                self.renderer.height(),
+            );
+            let frame = self.renderer.render(&uniform)?.downscale(self.scale);
+            self.writer.write_frame(self.frame, &frame)?;
+            self.frame += 1;
+        }
+        if self.frame == self.frame_count {
+            self.writer.finish()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.frame as f32 / self.frame_count as f32
+    }
+}
+
+/// Counts the bytes written to it, for size estimates.
+#[derive(Clone, Default)]
+struct ByteCounter(std::rc::Rc<std::cell::Cell<u64>>);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.set(self.0.get() + buf.len() as u64);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Estimate the size of the output file by encoding a few frames spread over
+/// the render and extrapolating to the full frame count.
+pub fn estimate_size(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    program: &Program,
+    settings: &ExportSettings,
+) -> crate::app::Result<u64> {
+    const SAMPLES: u32 = 4;
+    let frame_count = settings.frame_count();
+    let samples = frame_count.min(SAMPLES);
+    let counter = ByteCounter::default();
+    let mut writer = settings.create_stream_writer(counter.clone(), samples)?;
+    let mut renderer = OffscreenRenderer::new(
+        device,
+        queue,
+        program,
+        settings.format.target_format(),
+        settings.width,
+        settings.height,
+    );
+    for i in 0..samples {
+        let uniform = WgpuUniform::at_frame(
+            settings.first_frame() + i * frame_count / samples,
+            settings.fps,
+            settings.width,
+            settings.height,
+        );
+        let frame = renderer.render(&uniform)?.downscale(settings.scale);
+        writer.write_frame(i, &frame)?;
+    }
+    writer.finish()?;
+    Ok(counter.0.get() * frame_count as u64 / samples as u64)
+}
+
+pub enum ExportAction {
+    Render,
+    EstimateSize,
+}
+
+#[derive(Default)]
+pub struct ExportDialog {
+    pub open: bool,
+    pub settings: ExportSettings,
+    pub job: Option<RenderJob>,
+    /// Last size estimate and the settings it was made for.
+    pub estimate: Option<(ExportSettings, u64)>,
+}
+
+impl ExportDialog {
+    /// Show the export window and return what the user asked for, if anything.
+    pub fn ui(&mut self, ctx: &egui::Context) -> Option<ExportAction> {
+        let mut action = None;
+        let mut open = self.open;
+        egui::Window::new("Export")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let running = self.job.is_some();
+                ui.add_enabled_ui(!running, |ui| {
+                    egui::Grid::new("export_settings").show(ui, |ui| {
+                        self.settings_ui(ui);
+                    });
+                });
+                ui.separator();
+                match &self.job {
+                    Some(job) => {
+                        ui.add(egui::ProgressBar::new(job.progress()).text(format!(
+                            "{} / {}",
+                            job.frame(),
+                            job.frame_count()
+                        )));
+                        if ui.button("Cancel").clicked() {
+                            log::info!("Render cancelled at frame {}", job.frame());
+                            self.job = None;
+                        }
+                    }
+                    None => {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{} frames", self.settings.frame_count()));
+                            if !self.settings.format.is_sequence() {
+                                match &self.estimate {
+                                    Some((settings, bytes)) if *settings == self.settings => {
+                                        ui.label(format!("≈ {}", format_size(*bytes)));
+                                    }
+                                    _ => {
+                                        if ui.button("Estimate size").clicked() {
+                                            action = Some(ExportAction::EstimateSize);
+                                        }
+                                    }
+                                }
+                            }
+                        });
+                        if ui.button("Render").clicked() {
+                            action = Some(ExportAction::Render);
+                        }
+                    }
+                }
+            });
+        self.open = open;
+        action
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        ui.label("Format");
+        egui::ComboBox::from_id_salt("export_format")
+            .selected_text(settings.format.name())
+            .show_ui(ui, |ui| {
+                for format in ExportFormat::ALL {
+                    if ui
+                        .selectable_value(&mut settings.format, format, format.name())
+                        .changed()
+                    {
+                        settings.path.set_extension(format.extension());
+                        if format == ExportFormat::Gif
+                            && settings.palette.quantizer == Quantizer::None
+                        {
+                            settings.palette.quantizer = Quantizer::NeuQuant;
+                        }
+                    }
+                }
+            });
+        ui.end_row();
+        ui.label("Resolution");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut settings.width).range(1..=8192));
+            ui.label("×");
+            ui.add(egui::DragValue::new(&mut settings.height).range(1..=8192));
+        });
+        ui.end_row();
+        ui.label("Downscale");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::DragValue::new(&mut settings.scale)
+                    .range(1..=8)
+                    .prefix("1/"),
+            );
+            let (width, height) = settings.output_size();
+            ui.label(format!("{width} × {height}"));
+        });
+        ui.end_row();
+        ui.label("Frame rate");
+        ui.add(
+            egui::DragValue::new(&mut settings.fps)
+                .range(1..=240)
+                .suffix(" fps"),
+        );
+        ui.end_row();
+        ui.label("Start");
+        ui.add(
+            egui::DragValue::new(&mut settings.start_time)
+                .range(0.0..=3600.0)
+                .speed(0.1)
+                .suffix(" s"),
+        );
+        ui.end_row();
+        ui.label(if settings.format.has_palette() {
+            "Loop duration"
+        } else {
+            "Duration"
+        });
+        ui.add(
+            egui::DragValue::new(&mut settings.duration)
+                .range(0.0..=3600.0)
+                .speed(0.1)
+                .suffix(" s"),
+        );
+        ui.end_row();
+        if settings.format.has_palette() {
+            ui.label("Palette");
+            egui::ComboBox::from_id_salt("export_quantizer")
+                .selected_text(settings.palette.quantizer.name())
+                .show_ui(ui, |ui| {
+                    for quantizer in Quantizer::ALL {
+                        // GIF can't store true color.
+                        if quantizer == Quantizer::None && settings.format == ExportFormat::Gif {
+                            continue;
+                        }
+                        ui.selectable_value(
+                            &mut settings.palette.quantizer,
+                            quantizer,
+                            quantizer.name(),
+                        );
+                    }
+                });
+            ui.end_row();
+            if settings.palette.quantizer != Quantizer::None {
+                ui.label("Colors");
+                ui.add(egui::DragValue::new(&mut settings.palette.colors).range(2..=256));
+                ui.end_row();
+                ui.label("Dithering");
+                egui::ComboBox::from_id_salt("export_dither")
+                    .selected_text(settings.palette.dither.name())
+                    .show_ui(ui, |ui| {
+                        for dither in Dither::ALL {
+                            ui.selectable_value(
+                                &mut settings.palette.dither,
+                                dither,
+                                dither.name(),
+                            );
+                        }
+                    });
+                ui.end_row();
+            }
+        }
+        ui.label("Output");
+        let mut path = settings.path.display().to_string();
+        if ui.text_edit_singleline(&mut path).changed() {
+            settings.path = PathBuf::from(path);
+        }
+        ui.end_row();
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
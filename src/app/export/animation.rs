@@ -0,0 +1,147 @@
+use super::palette::{Palette, PaletteSettings, Quantizer};
+use super::FrameWriter;
+use crate::app::render::Frame;
+use std::borrow::Cow;
+use std::io::Write;
+
+/// Writes an endlessly looping GIF. GIF delays are in hundredths of a
+/// second, so per-frame delays alternate to keep the average at `fps`.
+pub struct GifWriter<W: Write> {
+    encoder: Option<gif::Encoder<W>>,
+    palette_settings: PaletteSettings,
+    /// Shared palette, when it doesn't depend on the frame content.
+    global_palette: Option<Palette>,
+    fps: u32,
+}
+
+impl<W: Write> GifWriter<W> {
+    pub fn new(
+        output: W,
+        width: u32,
+        height: u32,
+        fps: u32,
+        palette_settings: PaletteSettings,
+    ) -> crate::app::Result<Self> {
+        let (width, height) = (u16::try_from(width)?, u16::try_from(height)?);
+        let global_palette = (palette_settings.quantizer == Quantizer::Uniform)
+            .then(|| Palette::new(&palette_settings, &[]));
+        let global_rgb = global_palette
+            .as_ref()
+            .map(Palette::rgb)
+            .unwrap_or_default();
+        let mut encoder = gif::Encoder::new(output, width, height, &global_rgb)?;
+        encoder.set_repeat(gif::Repeat::Infinite)?;
+        Ok(Self {
+            encoder: Some(encoder),
+            palette_settings,
+            global_palette,
+            fps,
+        })
+    }
+
+    fn centiseconds(&self, index: u32) -> u32 {
+        ((index as f32) * 100.0 / self.fps as f32).round() as u32
+    }
+}
+
+impl<W: Write> FrameWriter for GifWriter<W> {
+    fn write_frame(&mut self, index: u32, frame: &Frame) -> crate::app::Result<()> {
+        let rgb = frame.rgb();
+        let local_palette;
+        let palette = match &self.global_palette {
+            Some(palette) => palette,
+            None => {
+                local_palette = Palette::new(&self.palette_settings, &rgb);
+                &local_palette
+            }
+        };
+        let indices = palette.quantize(&rgb, frame.width as usize, frame.height as usize);
+        let gif_frame = gif::Frame {
+            delay: (self.centiseconds(index + 1) - self.centiseconds(index)) as u16,
+            width: frame.width as u16,
+            height: frame.height as u16,
+            palette: self.global_palette.is_none().then(|| palette.rgb()),
+            buffer: Cow::Owned(indices),
+            ..Default::default()
+        };
+        if let Some(encoder) = &mut self.encoder {
+            encoder.write_frame(&gif_frame)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> crate::app::Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.into_inner()?.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes an endlessly looping APNG, either true color or with a single
+/// palette trained on the first frame (`PLTE` can't change between frames).
+pub struct ApngWriter<W: Write> {
+    output: Option<W>,
+    writer: Option<png::Writer<W>>,
+    palette_settings: PaletteSettings,
+    palette: Option<Palette>,
+    fps: u32,
+    frame_count: u32,
+}
+
+impl<W: Write> ApngWriter<W> {
+    pub fn new(output: W, fps: u32, frame_count: u32, palette_settings: PaletteSettings) -> Self {
+        Self {
+            output: Some(output),
+            writer: None,
+            palette_settings,
+            palette: None,
+            fps,
+            frame_count,
+        }
+    }
+
+    fn start(&mut self, frame: &Frame, rgb: &[u8]) -> crate::app::Result<()> {
+        let Some(output) = self.output.take() else {
+            return Ok(());
+        };
+        let mut encoder = png::Encoder::new(output, frame.width, frame.height);
+        encoder.set_depth(png::BitDepth::Eight);
+        if self.palette_settings.quantizer == Quantizer::None {
+            encoder.set_color(png::ColorType::Rgb);
+        } else {
+            let palette = Palette::new(&self.palette_settings, rgb);
+            encoder.set_color(png::ColorType::Indexed);
+            encoder.set_palette(palette.rgb());
+            self.palette = Some(palette);
+        }
+        encoder.set_animated(self.frame_count, 0)?;
+        encoder.set_frame_delay(1, self.fps.min(u16::MAX as u32) as u16)?;
+        self.writer = Some(encoder.write_header()?);
+        Ok(())
+    }
+}
+
+impl<W: Write> FrameWriter for ApngWriter<W> {
+    fn write_frame(&mut self, _index: u32, frame: &Frame) -> crate::app::Result<()> {
+        let rgb = frame.rgb();
+        if self.writer.is_none() {
+            self.start(frame, &rgb)?;
+        }
+        let data = match &self.palette {
+            Some(palette) => palette.quantize(&rgb, frame.width as usize, frame.height as usize),
+            None => rgb,
+        };
+        if let Some(writer) = &mut self.writer {
+            writer.write_image_data(&data)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> crate::app::Result<()> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,231 @@
+use color_quant::NeuQuant;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Quantizer {
+    /// Keep every color. Only valid for formats that support true color (APNG).
+    None,
+    /// Adaptive palette trained on the frame with the NeuQuant network.
+    NeuQuant,
+    /// Fixed RGB grid, identical for every frame. Its levels per channel
+    /// differ by at most one, to use as many of the colors as fit.
+    Uniform,
+}
+
+impl Quantizer {
+    pub const ALL: [Quantizer; 3] = [Quantizer::None, Quantizer::NeuQuant, Quantizer::Uniform];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantizer::None => "None (true color)",
+            Quantizer::NeuQuant => "Adaptive (NeuQuant)",
+            Quantizer::Uniform => "Uniform",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Dither {
+    None,
+    /// 4×4 Bayer matrix. Stable between frames, so it doesn't shimmer.
+    Ordered,
+    /// Error diffusion. Smoothest gradients but noisy in animation.
+    FloydSteinberg,
+}
+
+impl Dither {
+    pub const ALL: [Dither; 3] = [Dither::None, Dither::Ordered, Dither::FloydSteinberg];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dither::None => "None",
+            Dither::Ordered => "Ordered",
+            Dither::FloydSteinberg => "Floyd–Steinberg",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct PaletteSettings {
+    pub quantizer: Quantizer,
+    /// Number of palette entries, 2 to 256.
+    pub colors: u16,
+    pub dither: Dither,
+}
+
+impl Default for PaletteSettings {
+    fn default() -> Self {
+        Self {
+            quantizer: Quantizer::NeuQuant,
+            colors: 256,
+            dither: Dither::Ordered,
+        }
+    }
+}
+
+const BAYER_4X4: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+enum Lookup {
+    NeuQuant(NeuQuant),
+    /// Levels of the red, green and blue channels.
+    Uniform {
+        levels: [u32; 3],
+    },
+}
+
+/// Levels per channel of a uniform grid of at most `colors` colors, adding a
+/// level to green, then red, then blue for as long as they fit.
+fn uniform_levels(colors: u32) -> [u32; 3] {
+    let mut levels = [1; 3];
+    loop {
+        let mut added = false;
+        for channel in [1, 0, 2] {
+            levels[channel] += 1;
+            if levels.iter().product::<u32>() <= colors {
+                added = true;
+            } else {
+                levels[channel] -= 1;
+            }
+        }
+        if !added {
+            return levels;
+        }
+    }
+}
+
+/// Value of level `index` of a channel with `levels` levels, spread evenly
+/// over 0 to 255. A single level is mid-gray.
+fn level_value(index: u32, levels: u32) -> u8 {
+    match levels {
+        1 => 128,
+        _ => (index * 255 / (levels - 1)) as u8,
+    }
+}
+
+/// A palette of at most 256 colors and a way to map pixels onto it.
+pub struct Palette {
+    lookup: Lookup,
+    colors: Vec<[u8; 3]>,
+    dither: Dither,
+}
+
+impl Palette {
+    /// Build a palette for `rgb` (tightly packed RGB8). `Quantizer::None` is
+    /// treated as `NeuQuant`, since a palette was asked for anyway.
+    pub fn new(settings: &PaletteSettings, rgb: &[u8]) -> Self {
+        let colors = settings.colors.clamp(2, 256) as u32;
+        let lookup = match settings.quantizer {
+            Quantizer::Uniform => Lookup::Uniform {
+                levels: uniform_levels(colors),
+            },
+            Quantizer::None | Quantizer::NeuQuant => {
+                let rgba = rgb
+                    .chunks_exact(3)
+                    .flat_map(|c| [c[0], c[1], c[2], 255])
+                    .collect::<Vec<_>>();
+                Lookup::NeuQuant(NeuQuant::new(10, colors as usize, &rgba))
+            }
+        };
+        let colors = match &lookup {
+            Lookup::NeuQuant(nq) => nq
+                .color_map_rgb()
+                .chunks_exact(3)
+                .map(|c| [c[0], c[1], c[2]])
+                .collect(),
+            Lookup::Uniform { levels: [r, g, b] } => (0..r * g * b)
+                .map(|i| {
+                    [
+                        level_value(i / b / g, *r),
+                        level_value(i / b % g, *g),
+                        level_value(i % b, *b),
+                    ]
+                })
+                .collect(),
+        };
+        Self {
+            lookup,
+            colors,
+            dither: settings.dither,
+        }
+    }
+
+    /// The palette as packed RGB8, as GIF and PNG `PLTE` expect it.
+    pub fn rgb(&self) -> Vec<u8> {
+        self.colors.iter().flatten().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    fn index_of(&self, color: [f32; 3]) -> u8 {
+        let c = color.map(|c| c.round().clamp(0.0, 255.0) as u8);
+        match &self.lookup {
+            Lookup::NeuQuant(nq) => nq.index_of(&[c[0], c[1], c[2], 255]) as u8,
+            Lookup::Uniform { levels } => {
+                let q = [0, 1, 2].map(|i| (c[i] as u32 * (levels[i] - 1) + 127) / 255);
+                ((q[0] * levels[1] + q[1]) * levels[2] + q[2]) as u8
+            }
+        }
+    }
+
+    /// Map a packed RGB8 image onto palette indices, dithering as configured.
+    pub fn quantize(&self, rgb: &[u8], width: usize, height: usize) -> Vec<u8> {
+        let mut indices = vec![0u8; width * height];
+        // Roughly the distance between neighbouring palette colors.
+        let spread = 255.0 / (self.len() as f32).cbrt();
+        match self.dither {
+            Dither::None | Dither::Ordered => {
+                for y in 0..height {
+                    for x in 0..width {
+                        let i = y * width + x;
+                        let offset = if self.dither == Dither::Ordered {
+                            (BAYER_4X4[y % 4][x % 4] / 16.0 - 0.5) * spread
+                        } else {
+                            0.0
+                        };
+                        let color = [0, 1, 2].map(|c| rgb[i * 3 + c] as f32 + offset);
+                        indices[i] = self.index_of(color);
+                    }
+                }
+            }
+            Dither::FloydSteinberg => {
+                let mut error = vec![[0.0f32; 3]; width * (height + 1) + 1];
+                for y in 0..height {
+                    for x in 0..width {
+                        let i = y * width + x;
+                        let color = [0, 1, 2].map(|c| rgb[i * 3 + c] as f32 + error[i][c]);
+                        let index = self.index_of(color);
+                        indices[i] = index;
+                        let chosen = self.colors[index as usize];
+                        let diff = [0, 1, 2].map(|c| color[c] - chosen[c] as f32);
+                        let mut spread_error = |j: usize, weight: f32| {
+                            for (error, diff) in error[j].iter_mut().zip(diff) {
+                                *error += diff * weight;
+                            }
+                        };
+                        if x + 1 < width {
+                            spread_error(i + 1, 7.0 / 16.0);
+                        }
+                        if x > 0 {
+                            spread_error(i + width - 1, 3.0 / 16.0);
+                        }
+                        spread_error(i + width, 5.0 / 16.0);
+                        if x + 1 < width {
+                            spread_error(i + width + 1, 1.0 / 16.0);
+                        }
+                    }
+                }
+            }
+        }
+        indices
+    }
+}
@@ -0,0 +1,219 @@
+use super::project::{self, Project, PROJECT_EXTENSION};
+use std::path::{Path, PathBuf};
+
+/// Extensions offered by the open and save dialogs.
+pub const SHADER_EXTENSIONS: [&str; 2] = ["glsl", "frag"];
+/// Image formats that can be picked for texture channels.
+pub const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "hdr"];
+/// Files listed under File → Open Recent.
+pub const MAX_RECENT_FILES: usize = 10;
+
+/// The file behind the editor, either a lone shader or a project manifest,
+/// and the project as it was last loaded from or saved to it.
+pub struct ShaderFile {
+    /// Location on disk. Always `None` on the web, where files are uploaded
+    /// and downloaded instead.
+    pub path: Option<PathBuf>,
+    pub name: String,
+    pub saved: Project,
+}
+
+impl ShaderFile {
+    pub fn new(name: impl Into<String>, path: Option<PathBuf>, project: &Project) -> Self {
+        Self {
+            path,
+            name: name.into(),
+            saved: project.clone(),
+        }
+    }
+
+    pub fn is_dirty(&self, project: &Project) -> bool {
+        self.saved != *project
+    }
+
+    /// Whether the file is a project manifest rather than a lone shader.
+    pub fn is_project(&self) -> bool {
+        self.path.as_deref().is_some_and(is_project_path)
+    }
+
+    /// Directory that paths in the project are relative to.
+    pub fn directory(&self) -> &Path {
+        self.path
+            .as_deref()
+            .map_or(Path::new("."), project::directory)
+    }
+
+    /// Every file on disk the project was loaded from: the manifest, if any,
+    /// and the sources.
+    pub fn paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.path.iter().cloned().collect();
+        if self.path.is_some() {
+            for source in self.saved.sources() {
+                let path = self.directory().join(&source.path);
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+
+    /// Window title, with a dot in front while there are unsaved changes.
+    pub fn title(&self, project: &Project) -> String {
+        let dirty = if self.is_dirty(project) { "● " } else { "" };
+        format!("{dirty}{} - shadertoy_rs", self.name)
+    }
+}
+
+pub fn is_project_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == PROJECT_EXTENSION)
+}
+
+/// Read the project at `path`: a manifest, or a lone shader wrapped in a
+/// single-pass project.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read(path: &Path) -> crate::app::Result<Project> {
+    if is_project_path(path) {
+        Project::load(path)
+    } else {
+        let content = std::fs::read_to_string(path)?;
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        Ok(Project::single(name.as_ref(), content))
+    }
+}
+
+/// Most recently opened files, newest first.
+#[derive(Default)]
+pub struct RecentFiles {
+    paths: Vec<PathBuf>,
+}
+
+impl RecentFiles {
+    pub fn new(paths: Vec<PathBuf>) -> Self {
+        let mut paths = paths;
+        paths.truncate(MAX_RECENT_FILES);
+        Self { paths }
+    }
+
+    pub fn add(&mut self, path: PathBuf) {
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+    }
+
+    pub fn remove(&mut self, path: &std::path::Path) {
+        self.paths.retain(|p| p != path);
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn open_dialog() -> Option<PathBuf> {
+    let all: Vec<&str> = SHADER_EXTENSIONS
+        .into_iter()
+        .chain([PROJECT_EXTENSION])
+        .collect();
+    rfd::FileDialog::new()
+        .add_filter("Shader or project", &all)
+        .add_filter("Shader", &SHADER_EXTENSIONS)
+        .add_filter("Project", &[PROJECT_EXTENSION])
+        .pick_file()
+}
+
+/// Ask where to save `file`, as a manifest when `as_project` is set and as a
+/// lone shader otherwise.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_dialog(file: &ShaderFile, as_project: bool) -> Option<PathBuf> {
+    let mut name = PathBuf::from(&file.name);
+    let dialog = if as_project {
+        name.set_extension(PROJECT_EXTENSION);
+        rfd::FileDialog::new().add_filter("Project", &[PROJECT_EXTENSION])
+    } else {
+        rfd::FileDialog::new().add_filter("Shader", &SHADER_EXTENSIONS)
+    };
+    let mut dialog = dialog.set_file_name(name.to_string_lossy());
+    if let Some(directory) = file.path.as_ref().and_then(|p| p.parent()) {
+        dialog = dialog.set_directory(directory);
+    }
+    dialog.save_file()
+}
+
+/// Ask where to write a Shadertoy export of `file`, suggesting `name`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn shadertoy_dialog(file: &ShaderFile, name: &Path) -> Option<PathBuf> {
+    let mut dialog = rfd::FileDialog::new()
+        .add_filter("Shadertoy export", &[PROJECT_EXTENSION])
+        .set_file_name(name.to_string_lossy());
+    if let Some(directory) = file.path.as_ref().and_then(|p| p.parent()) {
+        dialog = dialog.set_directory(directory);
+    }
+    dialog.save_file()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn folder_dialog() -> Option<PathBuf> {
+    rfd::FileDialog::new().pick_folder()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn image_dialog(directory: &Path) -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("Image", &IMAGE_EXTENSIONS)
+        .set_directory(directory)
+        .pick_file()
+}
+
+/// Let the user pick a file in the browser and send its name and content
+/// to `sender` once it has been read.
+#[cfg(target_arch = "wasm32")]
+pub fn upload(sender: std::sync::mpsc::Sender<(String, String)>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let file = rfd::AsyncFileDialog::new()
+            .add_filter("Shader", &SHADER_EXTENSIONS)
+            .add_filter("Shadertoy export", &[PROJECT_EXTENSION])
+            .pick_file()
+            .await;
+        if let Some(file) = file {
+            let content = String::from_utf8_lossy(&file.read().await).into_owned();
+            let _ = sender.send((file.file_name(), content));
+        }
+    });
+}
+
+/// Offer `content` to the browser as a download named `name`.
+#[cfg(target_arch = "wasm32")]
+pub fn download(name: &str, content: &str) -> crate::app::Result<()> {
+    use anyhow::anyhow;
+    use eframe::wasm_bindgen::{JsCast as _, JsValue};
+    let js_error = |e: JsValue| anyhow!("{e:?}");
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| anyhow!("No document"))?;
+    let parts = js_sys::Array::of1(&JsValue::from_str(content));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type("text/plain");
+    let blob =
+        web_sys::Blob::new_with_str_sequence_and_options(&parts, &options).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let anchor = document
+        .create_element("a")
+        .map_err(js_error)?
+        .dyn_into::<web_sys::HtmlAnchorElement>()
+        .map_err(|_| anyhow!("Not an anchor element"))?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)?;
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn set_document_title(title: &str) {
+    if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+        document.set_title(title);
+    }
+}
@@ -0,0 +1,347 @@
+//! The editor's find and replace bar, which also takes line numbers to go to.
+use eframe::egui;
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+pub const FIND_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::F);
+pub const REPLACE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::H);
+pub const GO_TO_LINE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::G);
+
+/// What to look for.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Query {
+    pub text: String,
+    /// Whether `text` is a regular expression rather than plain text.
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+impl Query {
+    fn compile(&self) -> crate::app::Result<Regex> {
+        let pattern = if self.regex {
+            self.text.clone()
+        } else {
+            regex::escape(&self.text)
+        };
+        Ok(RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .multi_line(true)
+            .build()?)
+    }
+
+    /// Byte ranges of the matches in `code`, leaving out empty ones.
+    pub fn matches(&self, code: &str) -> crate::app::Result<Vec<Range<usize>>> {
+        if self.text.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(find(&self.compile()?, code))
+    }
+
+    /// What the match at `range` of `code` is replaced with. Regular
+    /// expressions can refer to their groups in `replacement`, like `$1`.
+    pub fn replacement(
+        &self,
+        code: &str,
+        range: Range<usize>,
+        replacement: &str,
+    ) -> crate::app::Result<String> {
+        if !self.regex {
+            return Ok(replacement.to_string());
+        }
+        Ok(expand(&self.compile()?, code, range, replacement))
+    }
+
+    /// `code` with every match replaced, and how many there were. Empty
+    /// matches are left alone, as they are when stepping through them.
+    pub fn replace_all(
+        &self,
+        code: &str,
+        replacement: &str,
+    ) -> crate::app::Result<(String, usize)> {
+        if self.text.is_empty() {
+            return Ok((code.to_string(), 0));
+        }
+        let regex = self.compile()?;
+        let matches = find(&regex, code);
+        let mut replaced = String::with_capacity(code.len());
+        let mut end = 0;
+        for range in &matches {
+            replaced.push_str(&code[end..range.start]);
+            if self.regex {
+                replaced.push_str(&expand(&regex, code, range.clone(), replacement));
+            } else {
+                replaced.push_str(replacement);
+            }
+            end = range.end;
+        }
+        replaced.push_str(&code[end..]);
+        Ok((replaced, matches.len()))
+    }
+}
+
+/// `replacement` with the groups of the match of `regex` at `range` of
+/// `code` filled in.
+fn expand(regex: &Regex, code: &str, range: Range<usize>, replacement: &str) -> String {
+    let captures = regex
+        .captures_at(code, range.start)
+        .filter(|captures| captures.get(0).map(|m| m.range()) == Some(range.clone()));
+    let mut expanded = String::new();
+    match captures {
+        Some(captures) => captures.expand(replacement, &mut expanded),
+        None => expanded.push_str(replacement),
+    }
+    expanded
+}
+
+/// Byte ranges of the matches of `regex` in `code`, leaving out empty ones.
+fn find(regex: &Regex, code: &str) -> Vec<Range<usize>> {
+    regex
+        .find_iter(code)
+        .map(|found| found.range())
+        .filter(|range| !range.is_empty())
+        .collect()
+}
+
+/// A query compiled and its matches, kept until the query or the code change
+/// rather than found again every frame.
+#[derive(Default)]
+struct Found {
+    /// The query `regex` was compiled from; `None` with an empty query.
+    compiled: Option<(Query, Result<Option<Regex>, String>)>,
+    /// The code `matches` are of.
+    code: Option<String>,
+    matches: Vec<Range<usize>>,
+}
+
+impl Found {
+    /// The matches of `query` in `code`, or why it doesn't compile.
+    fn get(&mut self, query: &Query, code: &str) -> Result<&[Range<usize>], &str> {
+        if self
+            .compiled
+            .as_ref()
+            .map_or(true, |(compiled, _)| compiled != query)
+        {
+            self.compiled = None;
+            self.code = None;
+        }
+        let (_, regex) = self.compiled.get_or_insert_with(|| {
+            let regex = if query.text.is_empty() {
+                Ok(None)
+            } else {
+                query.compile().map(Some).map_err(|e| e.to_string())
+            };
+            (query.clone(), regex)
+        });
+        let regex = regex.as_ref().map_err(String::as_str)?;
+        if self.code.as_deref() != Some(code) {
+            self.matches = regex
+                .as_ref()
+                .map(|regex| find(regex, code))
+                .unwrap_or_default();
+            self.code = Some(code.to_string());
+        }
+        Ok(&self.matches)
+    }
+}
+
+/// The match after `selection`, or the first one.
+pub fn next(matches: &[Range<usize>], selection: &Range<usize>) -> Option<Range<usize>> {
+    matches
+        .iter()
+        .find(|range| range.start >= selection.end && *range != selection)
+        .or(matches.first())
+        .cloned()
+}
+
+/// The match before `selection`, or the last one.
+pub fn previous(matches: &[Range<usize>], selection: &Range<usize>) -> Option<Range<usize>> {
+    matches
+        .iter()
+        .rev()
+        .find(|range| range.end <= selection.start && *range != selection)
+        .or(matches.last())
+        .cloned()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Find,
+    Replace,
+    GoToLine,
+}
+
+pub enum FindAction {
+    /// Select a byte range of the code.
+    Select(Range<usize>),
+    /// Go to a line, counted from 1.
+    GoToLine(usize),
+    /// Done; give the editor the focus back.
+    Close,
+}
+
+/// The bar above the editor.
+#[derive(Default)]
+pub struct FindBar {
+    mode: Option<Mode>,
+    query: Query,
+    replacement: String,
+    line: String,
+    /// Give the first field the focus when the bar next shows.
+    focus: bool,
+    found: Found,
+}
+
+impl FindBar {
+    pub fn open(&mut self, mode: Mode) {
+        self.mode = Some(mode);
+        self.focus = true;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.mode.is_some()
+    }
+
+    /// Matches to highlight in `code`, while finding.
+    pub fn matches(&mut self, code: &str) -> &[Range<usize>] {
+        match self.mode {
+            Some(Mode::Find | Mode::Replace) => {
+                self.found.get(&self.query, code).unwrap_or_default()
+            }
+            _ => &[],
+        }
+    }
+
+    /// Show the bar for `code`, where `selection` is the editor's selection
+    /// in bytes. Replacing edits `code` directly; the second value is whether
+    /// it did.
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        code: &mut String,
+        selection: Range<usize>,
+    ) -> (Option<FindAction>, bool) {
+        let Some(mode) = self.mode else {
+            return (None, false);
+        };
+        let mut action = None;
+        let mut changed = false;
+        let focus = std::mem::take(&mut self.focus);
+        let enter = |ui: &egui::Ui, response: &egui::Response| {
+            response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+        };
+        ui.horizontal(|ui| {
+            if mode == Mode::GoToLine {
+                let lines = code.split('\n').count();
+                ui.label("Go to line");
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.line)
+                        .hint_text(format!("1–{lines}"))
+                        .desired_width(80.0),
+                );
+                if focus {
+                    response.request_focus();
+                }
+                if enter(ui, &response) {
+                    if let Ok(line) = self.line.trim().parse::<usize>() {
+                        action = Some(FindAction::GoToLine(line.clamp(1, lines)));
+                        self.mode = None;
+                    }
+                }
+                return;
+            }
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.query.text)
+                    .hint_text("Find")
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(160.0),
+            );
+            if focus {
+                response.request_focus();
+            }
+            ui.toggle_value(&mut self.query.case_sensitive, "Aa")
+                .on_hover_text("Match case");
+            ui.toggle_value(&mut self.query.regex, ".*")
+                .on_hover_text("Regular expression");
+            let matches = match self.found.get(&self.query, code) {
+                Ok(matches) => matches.to_vec(),
+                Err(e) => {
+                    ui.colored_label(ui.visuals().error_fg_color, "Invalid")
+                        .on_hover_text(e.to_string());
+                    return;
+                }
+            };
+            match matches.iter().position(|range| *range == selection) {
+                Some(index) => ui.label(format!("{} of {}", index + 1, matches.len())),
+                None => ui.label(format!("{} matches", matches.len())),
+            };
+            let shift = ui.input(|i| i.modifiers.shift);
+            if ui.button("⏶").on_hover_text("Previous").clicked() || (enter(ui, &response) && shift)
+            {
+                action = previous(&matches, &selection).map(FindAction::Select);
+                response.request_focus();
+            } else if ui.button("⏷").on_hover_text("Next").clicked() || enter(ui, &response) {
+                action = next(&matches, &selection).map(FindAction::Select);
+                response.request_focus();
+            }
+        });
+        if mode == Mode::Replace {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.replacement)
+                        .hint_text("Replace")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(160.0),
+                );
+                let matches = self.found.get(&self.query, code).unwrap_or_default();
+                let matches = matches.to_vec();
+                if ui.button("Replace").clicked() {
+                    (action, changed) = self.replace(code, &matches, selection.clone());
+                }
+                if ui.button("Replace All").clicked() {
+                    match self.query.replace_all(code, &self.replacement) {
+                        Ok((replaced, count)) => {
+                            *code = replaced;
+                            changed = count > 0;
+                            log::info!("Replaced {count} matches");
+                        }
+                        Err(e) => log::error!("Error replacing: {e:#}"),
+                    }
+                }
+            });
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.mode = None;
+            action = Some(FindAction::Close);
+        }
+        (action, changed)
+    }
+
+    /// Replace the match that's selected, if it is one, and select the next.
+    fn replace(
+        &mut self,
+        code: &mut String,
+        matches: &[Range<usize>],
+        selection: Range<usize>,
+    ) -> (Option<FindAction>, bool) {
+        if !matches.contains(&selection) {
+            return (next(matches, &selection).map(FindAction::Select), false);
+        }
+        let replacement = match self
+            .query
+            .replacement(code, selection.clone(), &self.replacement)
+        {
+            Ok(replacement) => replacement,
+            Err(e) => {
+                log::error!("Error replacing: {e:#}");
+                return (None, false);
+            }
+        };
+        code.replace_range(selection.clone(), &replacement);
+        let after = selection.start + replacement.len();
+        let matches = self.found.get(&self.query, code).unwrap_or_default();
+        (next(matches, &(after..after)).map(FindAction::Select), true)
+    }
+}
@@ -0,0 +1,334 @@
+//! A GLSL formatter: four spaces of indentation per brace, opening braces at
+//! the end of the line before them, and single spaces around binary operators
+//! and after commas. Comments and preprocessor lines are kept as they are.
+//!
+//! Formatting is only applied once the code is checked to compile to the same
+//! SPIR-V as before, so it can't change what a shader does.
+use super::include::Includes;
+use super::load_pass_shader;
+use super::project::{PassKind, Project};
+use anyhow::{anyhow, bail, Context as _};
+use eframe::egui;
+
+pub const FORMAT_SHORTCUT: egui::KeyboardShortcut = egui::KeyboardShortcut::new(
+    egui::Modifiers::SHIFT.plus(egui::Modifiers::ALT),
+    egui::Key::F,
+);
+
+const INDENT: &str = "    ";
+
+/// Operators of more than one character, longest first.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "++", "--", "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "==", "!=", "<=",
+    ">=", "&&", "||", "^^", "<<", ">>",
+];
+
+/// Keywords followed by a space before a parenthesis, unlike function names.
+const CONTROL: &[&str] = &["if", "for", "while", "switch", "return", "else", "do"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Kind {
+    /// A name, keyword or number.
+    Word,
+    Punct,
+    Comment,
+}
+
+#[derive(Debug)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    /// Blank space before it on the line.
+    gap: usize,
+}
+
+impl Token<'_> {
+    fn is(&self, text: &str) -> bool {
+        self.kind == Kind::Punct && self.text == text
+    }
+
+    /// Whether it can end an operand, so a `-` after it subtracts.
+    fn ends_operand(&self) -> bool {
+        match self.kind {
+            Kind::Word => !matches!(self.text, "return" | "case"),
+            Kind::Punct => matches!(self.text, ")" | "]"),
+            Kind::Comment => false,
+        }
+    }
+}
+
+/// Split a line of code into tokens. Returns whether the line ends inside a
+/// block comment.
+fn tokenize(line: &str) -> (Vec<Token<'_>>, bool) {
+    let bytes = line.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut gap = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let kind = if c.is_ascii_whitespace() {
+            gap += 1;
+            i += 1;
+            continue;
+        } else if line[i..].starts_with("//") {
+            i = bytes.len();
+            Kind::Comment
+        } else if line[i..].starts_with("/*") {
+            match line[i + 2..].find("*/") {
+                Some(end) => i += end + 4,
+                None => {
+                    tokens.push(Token {
+                        kind: Kind::Comment,
+                        text: &line[start..],
+                        gap,
+                    });
+                    return (tokens, true);
+                }
+            }
+            Kind::Comment
+        } else if c.is_ascii_alphanumeric()
+            || c == b'_'
+            || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        {
+            let number = c.is_ascii_digit() || c == b'.';
+            i += 1;
+            while i < bytes.len() {
+                let exponent_sign = number
+                    && matches!(bytes[i], b'+' | b'-')
+                    && matches!(bytes[i - 1], b'e' | b'E')
+                    && !line[start..i].starts_with("0x");
+                let continues = bytes[i].is_ascii_alphanumeric()
+                    || bytes[i] == b'_'
+                    || (number && bytes[i] == b'.');
+                if continues || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Kind::Word
+        } else {
+            i += OPERATORS
+                .iter()
+                .find(|operator| line[i..].starts_with(*operator))
+                .map_or_else(
+                    || line[i..].chars().next().map_or(1, char::len_utf8),
+                    |o| o.len(),
+                );
+            Kind::Punct
+        };
+        tokens.push(Token {
+            kind,
+            text: &line[start..i],
+            gap,
+        });
+        gap = 0;
+    }
+    (tokens, false)
+}
+
+/// The tokens of a line joined with consistent spacing.
+fn join(tokens: &[Token<'_>]) -> String {
+    let case = tokens
+        .first()
+        .is_some_and(|token| matches!(token.text, "case" | "default"));
+    let mut text = String::new();
+    // Whether the token before is a prefix operator, binding to the next one.
+    let mut prefix = false;
+    for (index, token) in tokens.iter().enumerate() {
+        let previous = index.checked_sub(1).map(|index| &tokens[index]);
+        let operand_before = previous.is_some_and(Token::ends_operand);
+        let is_prefix = match token.text {
+            "!" | "~" => token.kind == Kind::Punct,
+            "-" | "+" | "++" | "--" => token.kind == Kind::Punct && !operand_before,
+            _ => false,
+        };
+        let postfix = matches!(token.text, "++" | "--") && operand_before;
+        let space = match previous {
+            None => false,
+            Some(_) if token.kind == Kind::Comment => token.gap > 0,
+            Some(previous) if previous.kind == Kind::Comment => previous.gap > 0 || token.gap > 0,
+            Some(previous) => {
+                let tight = prefix
+                    || postfix
+                    || ["(", "[", "."].iter().any(|p| previous.is(p))
+                    || [")", "]", ".", ",", ";"].iter().any(|p| token.is(p))
+                    || (case && token.is(":"))
+                    || ((token.is("(") || token.is("["))
+                        && (previous.is(")")
+                            || previous.is("]")
+                            || (previous.kind == Kind::Word && !CONTROL.contains(&previous.text))));
+                // Never glue operators into a different one, like `- -x`.
+                let glued = previous.kind == Kind::Punct
+                    && token.kind == Kind::Punct
+                    && tokenize(&format!("{}{}", previous.text, token.text))
+                        .0
+                        .len()
+                        == 1;
+                !tight || glued
+            }
+        };
+        if space {
+            // Comments keep their distance from the code before them.
+            let width = if token.kind == Kind::Comment {
+                token.gap
+            } else {
+                1
+            };
+            text.push_str(&" ".repeat(width.max(1)));
+        }
+        text.push_str(token.text);
+        prefix = is_prefix;
+    }
+    text
+}
+
+/// `code` formatted.
+pub fn format(code: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut depth = 0usize;
+    let mut parens = 0usize;
+    // Whether the statement on the line before goes on.
+    let mut continued = false;
+    // Whether a line with only `{` can join the line before.
+    let mut joinable = false;
+    let mut in_comment = false;
+    let mut in_directive = false;
+    for line in code.lines() {
+        let trimmed = line.trim();
+        if in_comment {
+            lines.push(line.trim_end().to_string());
+            if let Some(end) = line.find("*/") {
+                in_comment = tokenize(&line[end + 2..]).1;
+            }
+            joinable = false;
+            continue;
+        }
+        if in_directive || trimmed.starts_with('#') {
+            lines.push(if in_directive {
+                line.trim_end().to_string()
+            } else {
+                trimmed.to_string()
+            });
+            in_directive = trimmed.ends_with('\\');
+            joinable = false;
+            continue;
+        }
+        if trimmed.is_empty() {
+            if lines.last().is_some_and(|line| !line.is_empty()) {
+                lines.push(String::new());
+            }
+            continue;
+        }
+        let (tokens, open_comment) = tokenize(trimmed);
+        in_comment = open_comment;
+        let first = &tokens[0];
+        let code_tokens = || tokens.iter().filter(|token| token.kind != Kind::Comment);
+        let text = join(&tokens);
+        let previous = lines.last_mut().filter(|line| !line.is_empty());
+        match previous {
+            // A brace on its own line goes at the end of the line before.
+            Some(previous) if tokens.len() == 1 && first.is("{") && joinable => {
+                previous.push_str(" {");
+            }
+            Some(previous) if first.text == "else" && previous.trim() == "}" => {
+                previous.push(' ');
+                previous.push_str(&text);
+            }
+            _ => {
+                let closing = first.is("}") || first.is(")");
+                let inside_parens = parens > 0 && !closing;
+                let continuing = continued && parens == 0 && !first.is("{") && !first.is("}");
+                let indent = depth.saturating_sub(usize::from(first.is("}")))
+                    + usize::from(inside_parens || continuing);
+                lines.push(format!("{}{text}", INDENT.repeat(indent)));
+            }
+        }
+        for token in code_tokens() {
+            match token.text {
+                "{" if token.kind == Kind::Punct => depth += 1,
+                "}" if token.kind == Kind::Punct => depth = depth.saturating_sub(1),
+                "(" | "[" if token.kind == Kind::Punct => parens += 1,
+                ")" | "]" if token.kind == Kind::Punct => parens = parens.saturating_sub(1),
+                _ => {}
+            }
+        }
+        let last = code_tokens().last();
+        let case = matches!(first.text, "case" | "default");
+        continued = last.is_some_and(|last| {
+            !(last.is(";") || last.is("{") || last.is("}") || (case && last.is(":")))
+        });
+        joinable = tokens.last().is_some_and(|last| last.kind != Kind::Comment) && continued;
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let mut formatted = lines.join("\n");
+    formatted.push('\n');
+    formatted
+}
+
+/// The code of source `source`, in [`Project::sources`] order, formatted.
+/// `None` if it already is. Every pass the code is part of is compiled before
+/// and after, with `includes`, and formatting is refused unless the SPIR-V is
+/// the same. Code no compiled pass uses, like a Sound pass, can't be checked
+/// and isn't formatted.
+pub fn verified(
+    project: &Project,
+    source: usize,
+    includes: &Includes,
+) -> crate::app::Result<Option<String>> {
+    let code = &project
+        .sources()
+        .nth(source)
+        .ok_or_else(|| anyhow!("No source {source}"))?
+        .code;
+    let formatted = format(code);
+    if formatted == *code {
+        return Ok(None);
+    }
+    if cfg!(target_arch = "wasm32") {
+        bail!("Formatting needs the desktop version, to check the code compiles the same");
+    }
+    let mut changed = project.clone();
+    if let Some(changed) = changed.sources_mut().nth(source) {
+        changed.code = formatted.clone();
+    }
+    let common = usize::from(project.common.is_some());
+    let mut kinds = project
+        .passes
+        .iter()
+        .enumerate()
+        .filter(|(index, pass)| {
+            let part = source < common || index + common == source;
+            part && (pass.kind.is_buffer() || pass.kind == PassKind::Image)
+        })
+        .map(|(_, pass)| pass.kind)
+        .peekable();
+    if kinds.peek().is_none() {
+        bail!("No pass that compiles uses the code, to check formatting doesn't change it");
+    }
+    for kind in kinds {
+        let before = compile(project, kind, includes)
+            .context("The code has to compile before it can be formatted")?;
+        let after = compile(&changed, kind, includes)
+            .context("Formatting would break the code; left as it is")?;
+        if before != after {
+            bail!(
+                "Formatting would change the compiled {}; left as it is",
+                kind.name()
+            );
+        }
+    }
+    Ok(Some(formatted))
+}
+
+fn compile(project: &Project, kind: PassKind, includes: &Includes) -> crate::app::Result<Vec<u32>> {
+    let common = project.common.as_ref().map_or("", |common| &common.code);
+    let pass = project
+        .pass(kind)
+        .ok_or_else(|| anyhow!("No {} pass", kind.name()))?;
+    let shader = load_pass_shader(kind, common, &pass.source.code, includes)?;
+    Ok(shader.spirv.into_owned())
+}
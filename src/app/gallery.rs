@@ -0,0 +1,552 @@
+//! Browsing the bundled examples and a directory of shaders and projects,
+//! with animated thumbnails and search over their header comments.
+//!
+//! Header metadata is a run of `Key: value` comment lines at the top of a
+//! shader (of the image pass, for projects):
+//!
+//! ```glsl
+//! // Title: Raymarching - Primitives
+//! // Author: Inigo Quilez
+//! // Tags: raymarching, sdf, 3d
+//! // License: MIT
+//! ```
+#[cfg(not(target_arch = "wasm32"))]
+use super::file;
+use super::project::{self, Project};
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// Subdirectories deeper than this aren't scanned.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_DEPTH: usize = 3;
+/// Size of the rendered thumbnails.
+pub const THUMBNAIL_SIZE: [u32; 2] = [160, 90];
+/// Frames per second of the thumbnail animations.
+const THUMBNAIL_FPS: u32 = 10;
+
+/// What a shader's header comments say about it.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub tags: Vec<String>,
+    pub license: Option<String>,
+}
+
+impl Metadata {
+    /// Read the `Key: value` lines of the comments `code` starts with.
+    /// Unknown keys are ignored.
+    pub fn parse(code: &str) -> Self {
+        let mut metadata = Self::default();
+        let mut in_block = false;
+        for line in code.lines() {
+            let line = line.trim();
+            let text = if in_block {
+                if let Some(end) = line.find("*/") {
+                    in_block = false;
+                    &line[..end]
+                } else {
+                    line.trim_start_matches('*')
+                }
+            } else if let Some(comment) = line.strip_prefix("//") {
+                comment
+            } else if let Some(comment) = line.strip_prefix("/*") {
+                match comment.find("*/") {
+                    Some(end) => &comment[..end],
+                    None => {
+                        in_block = true;
+                        comment
+                    }
+                }
+            } else if line.is_empty() {
+                continue;
+            } else {
+                break;
+            };
+            let Some((key, value)) = text.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            match key.trim().to_lowercase().as_str() {
+                "title" => metadata.title = Some(value.to_string()),
+                "author" => metadata.author = Some(value.to_string()),
+                "tags" => {
+                    metadata.tags = value
+                        .split(',')
+                        .map(|tag| tag.trim().to_string())
+                        .filter(|tag| !tag.is_empty())
+                        .collect();
+                }
+                "license" => metadata.license = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        metadata
+    }
+}
+
+/// Where a gallery entry comes from.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Origin {
+    Bundled,
+    File(PathBuf),
+}
+
+/// A shader or project in the gallery.
+pub struct Entry {
+    /// File name, shown and searched.
+    pub name: String,
+    pub origin: Origin,
+    pub metadata: Metadata,
+    pub project: Project,
+}
+
+impl Entry {
+    fn new(name: String, origin: Origin, project: Project) -> Self {
+        Self {
+            name,
+            origin,
+            metadata: Metadata::parse(&project.image().source.code),
+            project,
+        }
+    }
+
+    /// Directory the entry's media and includes are relative to.
+    pub fn directory(&self) -> &Path {
+        match &self.origin {
+            Origin::Bundled => Path::new("."),
+            Origin::File(path) => project::directory(path),
+        }
+    }
+
+    /// Whether every word of `query` is in the file name or the metadata,
+    /// ignoring case.
+    pub fn matches(&self, query: &str) -> bool {
+        let Metadata {
+            title,
+            author,
+            tags,
+            license,
+        } = &self.metadata;
+        let haystack = [
+            Some(&self.name),
+            title.as_ref(),
+            author.as_ref(),
+            license.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .chain(tags)
+        .map(|text| text.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("\n");
+        query
+            .split_whitespace()
+            .all(|word| haystack.contains(&word.to_lowercase()))
+    }
+}
+
+/// The bundled examples followed by the shaders and projects found in a
+/// directory.
+#[derive(Default)]
+pub struct Gallery {
+    pub entries: Vec<Entry>,
+}
+
+impl Gallery {
+    /// The bundled examples, and those in `directory` and its subdirectories
+    /// sorted by path. Files that can't be read are skipped. Directories are
+    /// only scanned on desktop builds.
+    pub fn scan(directory: Option<&Path>) -> Self {
+        let bundled = super::examples::EXAMPLES.iter().map(|example| {
+            Entry::new(
+                example.file_name.to_string(),
+                Origin::Bundled,
+                example.project(),
+            )
+        });
+        #[cfg(not(target_arch = "wasm32"))]
+        let found = directory.map(scan_directory).unwrap_or_default();
+        #[cfg(target_arch = "wasm32")]
+        let found = {
+            let _ = directory;
+            Vec::new()
+        };
+        Self {
+            entries: bundled.chain(found).collect(),
+        }
+    }
+}
+
+/// The shaders and projects under `directory`, leaving out shaders that are
+/// sources of a project.
+#[cfg(not(target_arch = "wasm32"))]
+fn scan_directory(directory: &Path) -> Vec<Entry> {
+    let mut paths = Vec::new();
+    find_shaders(directory, 0, &mut paths);
+    paths.sort();
+    let mut entries = Vec::new();
+    for path in paths {
+        match read(&path) {
+            Ok(Some(project)) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                entries.push(Entry::new(name.into(), Origin::File(path), project));
+            }
+            Ok(None) => {}
+            Err(e) => log::warn!("Gallery: skipping {}: {:#}", path.display(), e),
+        }
+    }
+    let sources: Vec<PathBuf> = entries
+        .iter()
+        .filter(|entry| matches!(&entry.origin, Origin::File(path) if file::is_project_path(path)))
+        .flat_map(|entry| {
+            let directory = entry.directory();
+            entry
+                .project
+                .sources()
+                .map(|source| directory.join(&source.path))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    entries.retain(|entry| match &entry.origin {
+        Origin::File(path) => !sources.contains(path),
+        Origin::Bundled => true,
+    });
+    entries
+}
+
+/// Collect the shader and project files under `directory`.
+#[cfg(not(target_arch = "wasm32"))]
+fn find_shaders(directory: &Path, depth: usize, paths: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_DEPTH {
+                find_shaders(&path, depth + 1, paths);
+            }
+        } else if path.extension().is_some_and(|extension| {
+            file::SHADER_EXTENSIONS.contains(&extension.to_string_lossy().as_ref())
+                || file::is_project_path(&path)
+        }) {
+            paths.push(path);
+        }
+    }
+}
+
+/// The project at `path`, or `None` for JSON that isn't a project manifest.
+#[cfg(not(target_arch = "wasm32"))]
+fn read(path: &Path) -> crate::app::Result<Option<Project>> {
+    if file::is_project_path(path) && !project::is_manifest(&std::fs::read_to_string(path)?) {
+        return Ok(None);
+    }
+    file::read(path).map(Some)
+}
+
+pub enum GalleryAction {
+    /// Open the entry at this index of [`Gallery::entries`].
+    Open(usize),
+}
+
+/// A compiled gallery entry rendering its animation.
+#[cfg(not(target_arch = "wasm32"))]
+struct Preview {
+    renderer: super::render::OffscreenRenderer,
+    /// Where the frames are drawn, registered with egui as `texture`, which
+    /// paints it as it is on the GPU.
+    target: eframe::egui_wgpu::wgpu::Texture,
+    texture: egui::TextureId,
+    frame: u32,
+}
+
+/// The gallery window.
+#[derive(Default)]
+pub struct GalleryWindow {
+    pub open: bool,
+    /// Directory scanned after the bundled examples.
+    pub directory: Option<PathBuf>,
+    gallery: Gallery,
+    /// Whether `gallery` needs to be scanned again.
+    scanned: bool,
+    /// The gallery with the directory's entries, while it's scanned in the
+    /// background.
+    #[cfg(not(target_arch = "wasm32"))]
+    scanning: Option<std::sync::mpsc::Receiver<Gallery>>,
+    query: String,
+    /// Previews by entry index, or why the entry didn't compile.
+    #[cfg(not(target_arch = "wasm32"))]
+    previews: std::collections::HashMap<usize, Result<Preview, String>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    media: super::passes::MediaCache,
+    /// `egui` time the thumbnails were last rendered.
+    #[cfg(not(target_arch = "wasm32"))]
+    rendered_at: f64,
+}
+
+impl GalleryWindow {
+    pub fn gallery(&self) -> &Gallery {
+        &self.gallery
+    }
+
+    /// Show the gallery window and return what the user asked for, if
+    /// anything. Thumbnails are rendered with `render_state` on desktop
+    /// builds.
+    pub fn ui(
+        &mut self,
+        ctx: &egui::Context,
+        render_state: &eframe::egui_wgpu::RenderState,
+    ) -> Option<GalleryAction> {
+        if !self.open {
+            return None;
+        }
+        if !self.scanned {
+            // The bundled examples right away, the directory once it's read.
+            self.gallery = Gallery::scan(None);
+            self.scanned = true;
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let mut egui_renderer = render_state.renderer.write();
+                for (_, preview) in self.previews.drain() {
+                    if let Ok(preview) = preview {
+                        egui_renderer.free_texture(&preview.texture);
+                    }
+                }
+                self.scanning = self.directory.clone().map(|directory| {
+                    let (tx, rx) = std::sync::mpsc::channel();
+                    let ctx = ctx.clone();
+                    std::thread::spawn(move || {
+                        // Nobody waits for a scan that was started again.
+                        let _ = tx.send(Gallery::scan(Some(&directory)));
+                        ctx.request_repaint();
+                    });
+                    rx
+                });
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(scanning) = &self.scanning {
+            match scanning.try_recv() {
+                // The bundled examples come first, so their previews stay.
+                Ok(gallery) => {
+                    self.gallery = gallery;
+                    self.scanning = None;
+                }
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => self.scanning = None,
+                Err(std::sync::mpsc::TryRecvError::Empty) => {}
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let advance = {
+            let now = ctx.input(|i| i.time);
+            let advance = now - self.rendered_at >= 1.0 / THUMBNAIL_FPS as f64;
+            if advance {
+                self.rendered_at = now;
+            }
+            ctx.request_repaint_after(std::time::Duration::from_secs_f64(
+                1.0 / THUMBNAIL_FPS as f64,
+            ));
+            advance
+        };
+        #[cfg(target_arch = "wasm32")]
+        let _ = render_state;
+        // Compiling is slow, so at most one entry is compiled per frame.
+        #[cfg(not(target_arch = "wasm32"))]
+        let mut compiled = false;
+        let mut action = None;
+        let mut open = self.open;
+        egui::Window::new("Gallery")
+            .open(&mut open)
+            .default_size([560.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.query)
+                            .hint_text("Search names, titles, authors, tags, licenses"),
+                    );
+                    #[cfg(not(target_arch = "wasm32"))]
+                    {
+                        if ui.button("Folder…").clicked() {
+                            if let Some(directory) = file::folder_dialog() {
+                                self.directory = Some(directory);
+                                self.scanned = false;
+                            }
+                        }
+                        if ui.button("Rescan").clicked() {
+                            self.scanned = false;
+                        }
+                        if self.scanning.is_some() {
+                            ui.spinner();
+                        }
+                    }
+                });
+                if let Some(directory) = &self.directory {
+                    ui.small(directory.display().to_string());
+                }
+                ui.separator();
+                let [width, height] = THUMBNAIL_SIZE.map(|size| size as f32);
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        for (index, entry) in self.gallery.entries.iter().enumerate() {
+                            if !entry.matches(&self.query) {
+                                continue;
+                            }
+                            ui.vertical(|ui| {
+                                ui.set_width(width);
+                                let (rect, response) = ui.allocate_exact_size(
+                                    egui::vec2(width, height),
+                                    egui::Sense::click(),
+                                );
+                                #[cfg(not(target_arch = "wasm32"))]
+                                let error = if ui.is_rect_visible(rect) {
+                                    if !compiled && !self.previews.contains_key(&index) {
+                                        compiled = true;
+                                        let preview =
+                                            Preview::new(render_state, entry, &mut self.media);
+                                        self.previews.insert(index, preview);
+                                    }
+                                    match self.previews.get_mut(&index) {
+                                        Some(Ok(preview)) => {
+                                            preview.ui(ui, rect, advance);
+                                            None
+                                        }
+                                        Some(Err(e)) => {
+                                            ui.painter().text(
+                                                rect.center(),
+                                                egui::Align2::CENTER_CENTER,
+                                                "Doesn't compile",
+                                                egui::FontId::proportional(12.0),
+                                                ui.visuals().error_fg_color,
+                                            );
+                                            Some(e.clone())
+                                        }
+                                        None => None,
+                                    }
+                                } else {
+                                    None
+                                };
+                                #[cfg(target_arch = "wasm32")]
+                                let error: Option<String> = {
+                                    ui.painter().rect_filled(
+                                        rect,
+                                        4.0,
+                                        ui.visuals().extreme_bg_color,
+                                    );
+                                    None
+                                };
+                                let title = entry.metadata.title.as_deref().unwrap_or(&entry.name);
+                                let label = ui.add(
+                                    egui::Label::new(egui::RichText::new(title).strong())
+                                        .truncate()
+                                        .sense(egui::Sense::click()),
+                                );
+                                let mut details = vec![entry.name.clone()];
+                                details.extend(entry.metadata.author.clone());
+                                ui.add(
+                                    egui::Label::new(
+                                        egui::RichText::new(details.join(" · ")).small(),
+                                    )
+                                    .truncate(),
+                                );
+                                if response.clicked() || label.clicked() {
+                                    action = Some(GalleryAction::Open(index));
+                                }
+                                response
+                                    .on_hover_ui(|ui| entry_tooltip(ui, entry, error.as_deref()));
+                            });
+                        }
+                    });
+                });
+            });
+        self.open = open;
+        action
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Preview {
+    fn new(
+        render_state: &eframe::egui_wgpu::RenderState,
+        entry: &Entry,
+        media: &mut super::passes::MediaCache,
+    ) -> Result<Self, String> {
+        use super::render::{OffscreenRenderer, OFFSCREEN_FORMAT};
+        let program = super::passes::Program::compile(&entry.project, entry.directory(), media)
+            .map_err(|e| format!("{e:#}"))?;
+        let [width, height] = THUMBNAIL_SIZE;
+        let mut renderer = OffscreenRenderer::new(
+            &render_state.device,
+            &render_state.queue,
+            &program,
+            OFFSCREEN_FORMAT,
+            width,
+            height,
+        );
+        let target = renderer.create_srgb_target();
+        renderer.draw(
+            &super::WgpuUniform::at_frame(0, THUMBNAIL_FPS, width, height),
+            &target,
+        );
+        let texture = render_state.renderer.write().register_native_texture(
+            &render_state.device,
+            &target.create_view(&Default::default()),
+            eframe::egui_wgpu::wgpu::FilterMode::Linear,
+        );
+        Ok(Self {
+            renderer,
+            target,
+            texture,
+            frame: 1,
+        })
+    }
+
+    /// Paint the latest frame into `rect`, rendering the next one first if
+    /// `advance` is set. Frames stay on the GPU, so nothing waits for them.
+    fn ui(&mut self, ui: &egui::Ui, rect: egui::Rect, advance: bool) {
+        if advance {
+            let [width, height] = THUMBNAIL_SIZE;
+            let uniform = super::WgpuUniform::at_frame(self.frame, THUMBNAIL_FPS, width, height);
+            self.renderer.draw(&uniform, &self.target);
+            self.frame += 1;
+        }
+        ui.painter().image(
+            self.texture,
+            rect,
+            egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+            egui::Color32::WHITE,
+        );
+    }
+}
+
+/// What is known about `entry`, and why it doesn't compile if it doesn't.
+fn entry_tooltip(ui: &mut egui::Ui, entry: &Entry, error: Option<&str>) {
+    let Metadata {
+        title,
+        author,
+        tags,
+        license,
+    } = &entry.metadata;
+    if let Some(title) = title {
+        ui.strong(title);
+    }
+    match &entry.origin {
+        Origin::Bundled => ui.label(format!("{} (bundled)", entry.name)),
+        Origin::File(path) => ui.label(path.display().to_string()),
+    };
+    if let Some(author) = author {
+        ui.label(format!("Author: {author}"));
+    }
+    if !tags.is_empty() {
+        ui.label(format!("Tags: {}", tags.join(", ")));
+    }
+    if let Some(license) = license {
+        ui.label(format!("License: {license}"));
+    }
+    if let Some(error) = error {
+        ui.colored_label(ui.visuals().error_fg_color, error);
+    }
+}
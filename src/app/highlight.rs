@@ -0,0 +1,238 @@
+//! GLSL syntax highlighting for the editor, with the Shadertoy inputs and the
+//! shader's own uniforms set apart from other names.
+use super::complete::BUILTINS;
+use super::project::Project;
+use eframe::egui;
+use egui::text::{LayoutJob, TextFormat};
+use egui::Color32;
+use std::ops::Range;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Token {
+    Comment,
+    /// A `#` directive, up to a comment or the end of the line.
+    Preprocessor,
+    Number,
+    /// Control flow and declaration keywords.
+    Keyword,
+    /// Storage, interpolation and precision qualifiers.
+    Qualifier,
+    /// Built-in types.
+    Type,
+    /// Built-in functions and `gl_` variables.
+    Builtin,
+    /// The inputs Shadertoy provides, like `iTime` and `iChannel0`.
+    ShadertoyUniform,
+    /// Uniforms the shader declares, whose values the project holds.
+    UserUniform,
+    /// Other names and punctuation.
+    Plain,
+}
+
+// Word lists separated by whitespace.
+const KEYWORDS: &str = "break case continue default discard do else false for if return struct
+    switch true while";
+
+const QUALIFIERS: &str = "attribute buffer centroid coherent const flat highp in inout invariant
+    layout lowp mediump noperspective out patch precise precision readonly restrict sample shared
+    smooth uniform varying volatile writeonly";
+
+const TYPES: &str = "void bool int uint float double vec2 vec3 vec4 ivec2 ivec3 ivec4 uvec2 uvec3
+    uvec4 bvec2 bvec3 bvec4 dvec2 dvec3 dvec4 mat2 mat3 mat4 mat2x2 mat2x3 mat2x4 mat3x2 mat3x3
+    mat3x4 mat4x2 mat4x3 mat4x4 dmat2 dmat3 dmat4 sampler sampler2D sampler3D samplerCube
+    sampler2DArray sampler2DShadow samplerCubeShadow isampler2D usampler2D texture2D texture3D
+    textureCube image2D";
+
+/// Inputs the shader template declares, as on Shadertoy.
+pub const SHADERTOY_UNIFORMS: &[&str] = &[
+    "iResolution",
+    "iTime",
+    "iTimeDelta",
+    "iFrame",
+    "iChannelTime",
+    "iMouse",
+    "iDate",
+    "iSampleRate",
+    "iChannelResolution",
+    "iChannel0",
+    "iChannel1",
+    "iChannel2",
+    "iChannel3",
+];
+
+/// Names of the uniforms declared with `uniform <type> <name>;` in any of
+/// the project's sources, and of the values the project holds.
+pub fn user_uniforms(project: &Project) -> Vec<String> {
+    let mut names: Vec<String> = project.uniforms.keys().cloned().collect();
+    for line in project.sources().flat_map(|source| source.code.lines()) {
+        let Some(declaration) = line.trim_start().strip_prefix("uniform ") else {
+            continue;
+        };
+        let statement = declaration.split([';', '=']).next().unwrap_or_default();
+        let mut words = statement.split_whitespace().skip(1);
+        if let Some(name) = words.next() {
+            let name = name.split('[').next().unwrap_or(name);
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+/// Split `code` into tokens, by byte range. `uniforms` are the names
+/// highlighted as [`Token::UserUniform`].
+pub fn tokens(code: &str, uniforms: &[String]) -> Vec<(Range<usize>, Token)> {
+    let bytes = code.as_bytes();
+    let mut tokens: Vec<(Range<usize>, Token)> = Vec::new();
+    let mut push = |range: Range<usize>, token: Token| match tokens.last_mut() {
+        Some((last, last_token)) if *last_token == token && last.end == range.start => {
+            last.end = range.end;
+        }
+        _ => tokens.push((range, token)),
+    };
+    let mut line_start = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let token = if code[i..].starts_with("//") {
+            i = code[i..].find('\n').map_or(bytes.len(), |end| i + end);
+            Token::Comment
+        } else if code[i..].starts_with("/*") {
+            i = code[i + 2..]
+                .find("*/")
+                .map_or(bytes.len(), |end| i + end + 4);
+            Token::Comment
+        } else if c == b'#' && line_start {
+            while i < bytes.len()
+                && bytes[i] != b'\n'
+                && !code[i..].starts_with("//")
+                && !code[i..].starts_with("/*")
+            {
+                i += 1;
+            }
+            Token::Preprocessor
+        } else if c.is_ascii_digit()
+            || (c == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+        {
+            i += 1;
+            while i < bytes.len() {
+                let exponent_sign = matches!(bytes[i], b'+' | b'-')
+                    && matches!(bytes[i - 1], b'e' | b'E')
+                    && !code[start..i].starts_with("0x");
+                if bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || exponent_sign {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            Token::Number
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            classify(&code[start..i], uniforms)
+        } else {
+            // Whole characters, so ranges stay on char boundaries.
+            i += code[i..].chars().next().map_or(1, char::len_utf8);
+            Token::Plain
+        };
+        if token != Token::Plain || !c.is_ascii_whitespace() {
+            line_start = false;
+        }
+        if c == b'\n' {
+            line_start = true;
+        }
+        push(start..i, token);
+    }
+    tokens
+}
+
+/// The kind of the name `word`.
+pub fn classify(word: &str, uniforms: &[String]) -> Token {
+    let listed = |list: &str| list.split_whitespace().any(|w| w == word);
+    if listed(KEYWORDS) {
+        Token::Keyword
+    } else if listed(QUALIFIERS) {
+        Token::Qualifier
+    } else if listed(TYPES) {
+        Token::Type
+    } else if BUILTINS.iter().any(|(name, ..)| *name == word) || word.starts_with("gl_") {
+        Token::Builtin
+    } else if SHADERTOY_UNIFORMS.contains(&word) {
+        Token::ShadertoyUniform
+    } else if uniforms.iter().any(|name| name == word) {
+        Token::UserUniform
+    } else {
+        Token::Plain
+    }
+}
+
+fn format(token: Token, font_id: egui::FontId, dark_mode: bool) -> TextFormat {
+    let rgb = |dark: [u8; 3], light: [u8; 3]| {
+        let [r, g, b] = if dark_mode { dark } else { light };
+        Color32::from_rgb(r, g, b)
+    };
+    let color = match token {
+        Token::Comment => rgb([128, 128, 128], [120, 120, 120]),
+        Token::Preprocessor => rgb([152, 195, 121], [56, 120, 30]),
+        Token::Number => rgb([209, 154, 102], [152, 80, 0]),
+        Token::Keyword | Token::Qualifier => rgb([198, 120, 221], [150, 40, 170]),
+        Token::Type => rgb([86, 182, 194], [0, 120, 140]),
+        Token::Builtin => rgb([97, 175, 239], [20, 90, 190]),
+        Token::ShadertoyUniform => rgb([229, 192, 123], [170, 110, 0]),
+        Token::UserUniform => rgb([240, 113, 120], [200, 30, 50]),
+        Token::Plain => rgb([210, 210, 210], [40, 40, 40]),
+    };
+    TextFormat {
+        font_id,
+        color,
+        italics: token == Token::Comment,
+        underline: if token == Token::UserUniform {
+            egui::Stroke::new(1.0, color.gamma_multiply(0.5))
+        } else {
+            egui::Stroke::NONE
+        },
+        ..Default::default()
+    }
+}
+
+/// `code` laid out with GLSL colors, cached across frames like
+/// `egui_extras::syntax_highlighting::highlight`.
+pub fn highlight(
+    ctx: &egui::Context,
+    style: &egui::Style,
+    code: &str,
+    uniforms: &[String],
+) -> LayoutJob {
+    #[derive(Default)]
+    struct Highlighter;
+
+    impl egui::cache::ComputerMut<(&egui::FontId, bool, &str, &[String]), LayoutJob> for Highlighter {
+        fn compute(
+            &mut self,
+            (font_id, dark_mode, code, uniforms): (&egui::FontId, bool, &str, &[String]),
+        ) -> LayoutJob {
+            let mut job = LayoutJob::default();
+            for (range, token) in tokens(code, uniforms) {
+                job.append(&code[range], 0.0, format(token, font_id.clone(), dark_mode));
+            }
+            job
+        }
+    }
+
+    type HighlightCache = egui::cache::FrameCache<LayoutJob, Highlighter>;
+
+    let font_id = style
+        .override_font_id
+        .clone()
+        .unwrap_or_else(|| egui::TextStyle::Monospace.resolve(style));
+    let dark_mode = style.visuals.dark_mode;
+    ctx.memory_mut(|mem| {
+        mem.caches
+            .cache::<HighlightCache>()
+            .get((&font_id, dark_mode, code, uniforms))
+    })
+}
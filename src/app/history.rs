@@ -0,0 +1,453 @@
+//! Revisions of the project, one per successful compile, kept across
+//! restarts, with the window to browse, compare and restore them.
+use super::project::Project;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Storage key of [`History`].
+pub const KEY: &str = "shadertoy_rs_history";
+/// Oldest revisions are dropped beyond this many.
+pub const MAX_REVISIONS: usize = 50;
+/// Seconds within which a compile replaces the latest revision instead of
+/// adding one, so a burst of typing makes a single revision.
+pub const COALESCE_SECONDS: u64 = 5;
+/// Size of the rendered thumbnails.
+pub const THUMBNAIL_SIZE: [u32; 2] = [64, 36];
+
+/// A small RGB rendering of a revision.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed RGB8 pixels, stored as hex to keep the JSON small.
+    #[serde(with = "hex")]
+    pub rgb: Vec<u8>,
+}
+
+/// The project as it was when it compiled.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Revision {
+    /// Unique within the history, increasing.
+    pub id: u64,
+    /// Seconds since the Unix epoch.
+    pub time: u64,
+    /// Name of the file that was open.
+    pub file_name: String,
+    /// The project, without its code; see `code`.
+    project: Project,
+    /// Code of the project's sources, in [`Project::sources`] order.
+    code: Vec<String>,
+    pub thumbnail: Option<Thumbnail>,
+}
+
+impl Revision {
+    /// The project with its code put back.
+    pub fn project(&self) -> Project {
+        let mut project = self.project.clone();
+        for (source, code) in project.sources_mut().zip(&self.code) {
+            source.code = code.clone();
+        }
+        project
+    }
+}
+
+/// Revisions, oldest first.
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    revisions: Vec<Revision>,
+}
+
+impl History {
+    /// Read the history from `storage`. Unreadable history is logged and
+    /// replaced by an empty one.
+    pub fn load(storage: &dyn eframe::Storage) -> Self {
+        let Some(json) = storage.get_string(KEY) else {
+            return Self::default();
+        };
+        match Self::parse(&json) {
+            Ok(history) => history,
+            Err(e) => {
+                log::warn!("Ignoring the stored revision history: {e:#}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        match self.to_json() {
+            Ok(json) => storage.set_string(KEY, json),
+            Err(e) => log::error!("Error saving the revision history: {e}"),
+        }
+    }
+
+    pub fn parse(json: &str) -> crate::app::Result<Self> {
+        let history: Self = serde_json::from_str(json)?;
+        if history
+            .revisions
+            .iter()
+            .any(|revision| revision.code.len() != revision.project.sources().count())
+        {
+            anyhow::bail!("Revision code doesn't match its project's sources");
+        }
+        Ok(history)
+    }
+
+    pub fn to_json(&self) -> crate::app::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub fn revisions(&self) -> &[Revision] {
+        &self.revisions
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Revision> {
+        self.revisions.iter().find(|revision| revision.id == id)
+    }
+
+    /// Add `project` as compiled at `time`. Nothing is added when it is the
+    /// same as the latest revision, and a revision made less than
+    /// [`COALESCE_SECONDS`] after the latest one replaces it. Returns the id
+    /// of the revision holding `project`.
+    pub fn record(
+        &mut self,
+        project: &Project,
+        file_name: &str,
+        thumbnail: Option<Thumbnail>,
+        time: u64,
+    ) -> u64 {
+        let id = self.revisions.last().map_or(0, |latest| latest.id + 1);
+        if let Some(latest) = self.revisions.last_mut() {
+            if latest.project() == *project {
+                latest.thumbnail = thumbnail.or(latest.thumbnail.take());
+                return latest.id;
+            }
+            if time.saturating_sub(latest.time) < COALESCE_SECONDS {
+                self.revisions.pop();
+            }
+        }
+        self.revisions.push(Revision {
+            id,
+            time,
+            file_name: file_name.to_string(),
+            project: project.clone(),
+            code: project
+                .sources()
+                .map(|source| source.code.clone())
+                .collect(),
+            thumbnail,
+        });
+        let excess = self.revisions.len().saturating_sub(MAX_REVISIONS);
+        self.revisions.drain(..excess);
+        id
+    }
+
+    /// Id of the latest revision if, at `time`, a compile would add a
+    /// revision rather than replace it.
+    pub fn settled(&self, time: u64) -> Option<u64> {
+        self.revisions
+            .last()
+            .filter(|latest| time.saturating_sub(latest.time) >= COALESCE_SECONDS)
+            .map(|latest| latest.id)
+    }
+
+    pub fn set_thumbnail(&mut self, id: u64, thumbnail: Thumbnail) {
+        if let Some(revision) = self.revisions.iter_mut().find(|r| r.id == id) {
+            revision.thumbnail = Some(thumbnail);
+        }
+    }
+}
+
+/// Seconds since the Unix epoch.
+pub fn unix_time() -> u64 {
+    instant::SystemTime::now()
+        .duration_since(instant::SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// Render the first second of `program` into a thumbnail.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn render_thumbnail(
+    device: &eframe::egui_wgpu::wgpu::Device,
+    queue: &eframe::egui_wgpu::wgpu::Queue,
+    program: &super::passes::Program,
+) -> crate::app::Result<Thumbnail> {
+    use super::render::{OffscreenRenderer, OFFSCREEN_FORMAT};
+    let [width, height] = THUMBNAIL_SIZE;
+    let mut renderer =
+        OffscreenRenderer::new(device, queue, program, OFFSCREEN_FORMAT, width, height);
+    let frame = renderer.render(&super::WgpuUniform::at_frame(60, 60, width, height))?;
+    Ok(Thumbnail {
+        width,
+        height,
+        rgb: frame.rgb(),
+    })
+}
+
+/// A line of a [`diff`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Change<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The lines of `old` and `new`, marked as kept, removed or added, along a
+/// longest common subsequence.
+pub fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    // common[i][j]: length of the longest common subsequence of old[i..] and new[j..].
+    let mut common = vec![vec![0u32; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            changes.push(Change::Removed(old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new[j]));
+            j += 1;
+        }
+    }
+    changes.extend(old[i..].iter().map(|line| Change::Removed(line)));
+    changes.extend(new[j..].iter().map(|line| Change::Added(line)));
+    changes
+}
+
+/// A source named by its path, for diffing by name across revisions.
+fn named_sources(project: &Project) -> Vec<(String, &str)> {
+    project
+        .sources()
+        .map(|source| (source.path.display().to_string(), source.code.as_str()))
+        .collect()
+}
+
+pub enum HistoryAction {
+    /// Put the revision with this id into the editor.
+    Restore(u64),
+}
+
+/// The history window.
+#[derive(Default)]
+pub struct HistoryWindow {
+    pub open: bool,
+    /// Revision shown below the list.
+    selected: Option<u64>,
+    /// Revision the selected one is compared with.
+    compare: Option<u64>,
+    thumbnails: HashMap<u64, egui::TextureHandle>,
+    /// The diff shown, by the ids of the revisions compared, old first.
+    diff: Option<((u64, u64), Vec<FileDiff>)>,
+}
+
+impl HistoryWindow {
+    /// Show the history window and return what the user asked for, if anything.
+    pub fn ui(&mut self, ctx: &egui::Context, history: &History) -> Option<HistoryAction> {
+        let mut action = None;
+        let mut open = self.open;
+        let now = unix_time();
+        egui::Window::new("History")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                if history.revisions().is_empty() {
+                    ui.label("Every successful compile is kept here.");
+                    return;
+                }
+                egui::ScrollArea::vertical()
+                    .id_salt("history_revisions")
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        for revision in history.revisions().iter().rev() {
+                            ui.horizontal(|ui| {
+                                self.thumbnail_ui(ui, revision, 1.0);
+                                let label = format!(
+                                    "{} · {}",
+                                    format_age(now.saturating_sub(revision.time)),
+                                    revision.file_name
+                                );
+                                if ui
+                                    .selectable_label(self.selected == Some(revision.id), label)
+                                    .on_hover_text("Preview")
+                                    .clicked()
+                                {
+                                    self.selected = Some(revision.id);
+                                }
+                                if ui
+                                    .selectable_label(self.compare == Some(revision.id), "Diff")
+                                    .on_hover_text("Compare the previewed revision with this one")
+                                    .clicked()
+                                {
+                                    self.compare = if self.compare == Some(revision.id) {
+                                        None
+                                    } else {
+                                        Some(revision.id)
+                                    };
+                                }
+                                if ui
+                                    .button("Restore")
+                                    .on_hover_text(
+                                        "Put this revision in the editor as unsaved changes",
+                                    )
+                                    .clicked()
+                                {
+                                    action = Some(HistoryAction::Restore(revision.id));
+                                }
+                            });
+                        }
+                    });
+                let Some(selected) = self.selected.and_then(|id| history.get(id)) else {
+                    return;
+                };
+                ui.separator();
+                self.thumbnail_ui(ui, selected, 4.0);
+                let compared = self.compare.and_then(|id| history.get(id));
+                egui::ScrollArea::both()
+                    .id_salt("history_preview")
+                    .show(ui, |ui| match compared {
+                        Some(compared) if compared.id != selected.id => {
+                            let ids = (compared.id, selected.id);
+                            if self.diff.as_ref().map(|(cached, _)| *cached) != Some(ids) {
+                                let diff = diff_sources(&compared.project(), &selected.project());
+                                self.diff = Some((ids, diff));
+                            }
+                            if let Some((_, diff)) = &self.diff {
+                                diff_ui(ui, diff);
+                            }
+                        }
+                        _ => {
+                            for (name, code) in named_sources(&selected.project()) {
+                                ui.strong(name);
+                                ui.monospace(code);
+                            }
+                        }
+                    });
+            });
+        self.open = open;
+        // Let go of the textures of revisions that were dropped.
+        self.thumbnails.retain(|id, _| history.get(*id).is_some());
+        action
+    }
+
+    fn thumbnail_ui(&mut self, ui: &mut egui::Ui, revision: &Revision, scale: f32) {
+        let [width, height] = THUMBNAIL_SIZE.map(|size| size as f32 * scale);
+        let Some(thumbnail) = &revision.thumbnail else {
+            ui.allocate_space(egui::vec2(width, height));
+            return;
+        };
+        let texture = self.thumbnails.entry(revision.id).or_insert_with(|| {
+            let image = egui::ColorImage::from_rgb(
+                [thumbnail.width as usize, thumbnail.height as usize],
+                &thumbnail.rgb,
+            );
+            ui.ctx().load_texture(
+                format!("revision_{}", revision.id),
+                image,
+                egui::TextureOptions::LINEAR,
+            )
+        });
+        ui.add(egui::Image::new((texture.id(), egui::vec2(width, height))));
+    }
+}
+
+/// The diff of a source that changed: its name and its lines, each
+/// prefixed with `+`, `-` or a space.
+type FileDiff = (String, Vec<String>);
+
+/// Line diff of every source of `old` and `new` that differs, matched by path.
+fn diff_sources(old: &Project, new: &Project) -> Vec<FileDiff> {
+    let old = named_sources(old);
+    let new = named_sources(new);
+    let mut names: Vec<&String> = old.iter().chain(&new).map(|(name, _)| name).collect();
+    names.sort();
+    names.dedup();
+    let mut diffs = Vec::new();
+    for name in names {
+        let find = |sources: &[(String, &str)]| {
+            sources
+                .iter()
+                .find(|(n, _)| n == name)
+                .map_or(String::new(), |(_, code)| code.to_string())
+        };
+        let (old_code, new_code) = (find(&old), find(&new));
+        if old_code == new_code {
+            continue;
+        }
+        let lines = diff(&old_code, &new_code)
+            .into_iter()
+            .map(|change| match change {
+                Change::Same(line) => format!("  {line}"),
+                Change::Removed(line) => format!("- {line}"),
+                Change::Added(line) => format!("+ {line}"),
+            })
+            .collect();
+        diffs.push((name.clone(), lines));
+    }
+    diffs
+}
+
+fn diff_ui(ui: &mut egui::Ui, diffs: &[FileDiff]) {
+    for (name, lines) in diffs {
+        ui.strong(name);
+        for line in lines {
+            let color = match line.as_bytes().first() {
+                Some(b'-') => ui.visuals().error_fg_color,
+                Some(b'+') => egui::Color32::from_rgb(90, 190, 90),
+                _ => ui.visuals().text_color(),
+            };
+            ui.label(egui::RichText::new(line).monospace().color(color));
+        }
+    }
+}
+
+/// How long ago something happened `seconds` ago, roughly.
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", seconds / 60),
+        3600..86400 => format!("{} h ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+/// Bytes as a lowercase hex string.
+mod hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(bytes.len() * 2);
+        for byte in bytes {
+            hex.push_str(&format!("{byte:02x}"));
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+        hex.as_bytes()
+            .chunks(2)
+            .map(|digits| {
+                let digits = std::str::from_utf8(digits).map_err(D::Error::custom)?;
+                u8::from_str_radix(digits, 16).map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,229 @@
+//! `#include` support for pass code.
+//!
+//! `#include "path"` is looked up next to the file containing it, then in
+//! the library roots; `#include <lib/name>` only in the library roots, with
+//! or without the `.glsl` extension. Every file is included at most once per
+//! pass, so libraries don't need guards of their own. Line breaks are kept
+//! in step with a [`LineMap`], so diagnostics can name the file they are in.
+use anyhow::anyhow;
+use std::path::{Path, PathBuf};
+
+/// Environment variable with extra library roots, separated like `PATH`.
+pub const INCLUDE_PATH_VAR: &str = "SHADERTOY_RS_INCLUDE_PATH";
+
+/// Where includes are resolved.
+#[derive(Clone, Debug, Default)]
+pub struct Includes {
+    /// Directory of the project, for quoted includes in its own sources.
+    pub directory: PathBuf,
+    /// Library roots, searched in order.
+    pub roots: Vec<PathBuf>,
+}
+
+impl Includes {
+    /// Includes for a project in `directory` with the library roots of its
+    /// manifest, relative to `directory`, followed by those of
+    /// [`INCLUDE_PATH_VAR`].
+    pub fn new(directory: &Path, roots: &[PathBuf]) -> Self {
+        let mut roots: Vec<PathBuf> = roots.iter().map(|root| directory.join(root)).collect();
+        if let Some(paths) = std::env::var_os(INCLUDE_PATH_VAR) {
+            roots.extend(std::env::split_paths(&paths));
+        }
+        Self {
+            directory: directory.to_path_buf(),
+            roots,
+        }
+    }
+
+    /// Start expanding the sources of one pass.
+    pub fn expander(&self) -> Expander<'_> {
+        Expander {
+            includes: self,
+            files: Vec::new(),
+            written: 0,
+        }
+    }
+
+    fn resolve(&self, spec: &Spec<'_>, from: &Path) -> Option<PathBuf> {
+        let (name, local) = match spec {
+            Spec::Quoted(name) => (*name, Some(from)),
+            Spec::Angled(name) => (*name, None),
+        };
+        let candidates = local
+            .into_iter()
+            .chain(self.roots.iter().map(PathBuf::as_path));
+        for directory in candidates {
+            let path = directory.join(name);
+            if path.is_file() {
+                return Some(path);
+            }
+            let mut path = path.into_os_string();
+            path.push(".glsl");
+            let path = PathBuf::from(path);
+            if matches!(spec, Spec::Angled(_)) && path.is_file() {
+                return Some(path);
+            }
+        }
+        None
+    }
+}
+
+/// Source of a range of lines of expanded code.
+#[derive(Clone, Debug)]
+struct Segment {
+    /// First line in the expanded code, 1-based.
+    start: usize,
+    name: String,
+    /// Line of `name` that `start` came from, 1-based.
+    line: usize,
+}
+
+/// Maps lines of expanded code back to the files they came from.
+#[derive(Clone, Debug, Default)]
+pub struct LineMap {
+    segments: Vec<Segment>,
+}
+
+impl LineMap {
+    /// File name and line in it for `line` of the expanded code.
+    pub fn locate(&self, line: usize) -> Option<(&str, usize)> {
+        let segment = self
+            .segments
+            .iter()
+            .take_while(|segment| segment.start <= line)
+            .last()?;
+        Some((&segment.name, segment.line + line - segment.start))
+    }
+}
+
+/// Code with its includes pasted in.
+pub struct Expanded {
+    pub code: String,
+    pub lines: LineMap,
+}
+
+/// Expands the sources of one pass, remembering which files were included.
+pub struct Expander<'a> {
+    includes: &'a Includes,
+    /// Canonical paths of the files included so far.
+    files: Vec<PathBuf>,
+    /// Lines of the current expansion written so far.
+    written: usize,
+}
+
+impl Expander<'_> {
+    /// Expand `code`, shown as `name` in diagnostics.
+    pub fn expand(&mut self, name: &str, code: &str) -> crate::app::Result<Expanded> {
+        let mut expanded = Expanded {
+            code: String::new(),
+            lines: LineMap::default(),
+        };
+        self.written = 0;
+        let directory = self.includes.directory.clone();
+        self.expand_into(&mut expanded, name, code, &directory, 0)?;
+        Ok(expanded)
+    }
+
+    /// Every file included by the sources expanded so far.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    fn expand_into(
+        &mut self,
+        expanded: &mut Expanded,
+        name: &str,
+        code: &str,
+        directory: &Path,
+        depth: usize,
+    ) -> crate::app::Result<()> {
+        const MAX_DEPTH: usize = 32;
+        let mut line = 1;
+        let mut resume = true;
+        for text in code.lines() {
+            if resume {
+                expanded.lines.segments.push(Segment {
+                    start: self.written + 1,
+                    name: name.to_string(),
+                    line,
+                });
+                resume = false;
+            }
+            match parse_include(text) {
+                Some(Ok(spec)) => {
+                    let path = self
+                        .includes
+                        .resolve(&spec, directory)
+                        .ok_or_else(|| anyhow!("{name}:{line}: error: can't find {spec}"))?;
+                    let canonical = path.canonicalize()?;
+                    if self.files.contains(&canonical) {
+                        expanded.code.push('\n');
+                        self.written += 1;
+                    } else {
+                        if depth == MAX_DEPTH {
+                            return Err(anyhow!("{name}:{line}: error: includes nest too deep"));
+                        }
+                        self.files.push(canonical);
+                        let included = std::fs::read_to_string(&path)
+                            .map_err(|e| anyhow!("{name}:{line}: error: {spec}: {e}"))?;
+                        let parent = path.parent().unwrap_or(directory).to_path_buf();
+                        self.expand_into(expanded, spec.name(), &included, &parent, depth + 1)?;
+                        resume = true;
+                    }
+                }
+                Some(Err(())) => {
+                    return Err(anyhow!("{name}:{line}: error: malformed #include"));
+                }
+                None => {
+                    expanded.code.push_str(text);
+                    expanded.code.push('\n');
+                    self.written += 1;
+                }
+            }
+            line += 1;
+        }
+        Ok(())
+    }
+}
+
+enum Spec<'a> {
+    Quoted(&'a str),
+    Angled(&'a str),
+}
+
+impl Spec<'_> {
+    /// How the file is named in diagnostics.
+    fn name(&self) -> &str {
+        match self {
+            Spec::Quoted(name) | Spec::Angled(name) => name,
+        }
+    }
+}
+
+impl std::fmt::Display for Spec<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Spec::Quoted(name) => write!(f, "\"{name}\""),
+            Spec::Angled(name) => write!(f, "<{name}>"),
+        }
+    }
+}
+
+/// The file named by an `#include` line, `Err` if it doesn't name one, and
+/// `None` for any other line.
+fn parse_include(line: &str) -> Option<Result<Spec<'_>, ()>> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix("include")?.trim_start();
+    let quoted = rest.starts_with('"');
+    let close = match rest.chars().next() {
+        Some('"') => '"',
+        Some('<') => '>',
+        _ => return Some(Err(())),
+    };
+    let name = &rest[1..];
+    Some(match name.find(close) {
+        Some(end) if end > 0 && quoted => Ok(Spec::Quoted(&name[..end])),
+        Some(end) if end > 0 => Ok(Spec::Angled(&name[..end])),
+        _ => Err(()),
+    })
+}
@@ -0,0 +1,178 @@
+//! Moving around the editor: to a line, to where a function is defined, and
+//! to the lines the compiler complains about.
+use super::complete;
+use super::project::Project;
+use eframe::egui;
+use egui::text::{CCursor, CCursorRange};
+use std::ops::Range;
+
+pub const GO_TO_DEFINITION_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::F12);
+
+/// A compiler message about a line of the project's code.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Diagnostic {
+    /// Name of the code, as in the editor tabs, or of an included file.
+    pub file: String,
+    /// Line number, from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+/// The `<file>:<line>: <message>` lines of compiler output, named the way
+/// [`crate::load_pass_shader`] names them. Other lines are left out.
+pub fn diagnostics(output: &str) -> Vec<Diagnostic> {
+    let pattern = regex::Regex::new(r"^(.+?):(\d+):\s*(.*)$").unwrap();
+    output
+        .lines()
+        .filter_map(|line| {
+            let captures = pattern.captures(line.trim())?;
+            Some(Diagnostic {
+                file: captures[1].to_string(),
+                line: captures[2].parse().ok()?,
+                message: captures[3].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Position in [`Project::sources`] of the code named `name` in diagnostics.
+pub fn source_index(project: &Project, name: &str) -> Option<usize> {
+    let common = usize::from(project.common.is_some());
+    if name == "Common" && common == 1 {
+        return Some(0);
+    }
+    let pass = project
+        .passes
+        .iter()
+        .position(|pass| pass.kind.name() == name)?;
+    Some(common + pass)
+}
+
+/// Byte range of line `line` of `code`, counted from 1, without its line
+/// break.
+pub fn line_range(code: &str, line: usize) -> Option<Range<usize>> {
+    let mut start = 0;
+    for (number, text) in code.split('\n').enumerate() {
+        if number + 1 == line {
+            return Some(start..start + text.trim_end_matches('\r').len());
+        }
+        start += text.len() + 1;
+    }
+    None
+}
+
+/// Where the function or macro `name` is defined in `project`: the position
+/// of the source in [`Project::sources`] and the byte range of the name.
+/// Definitions win over prototypes.
+pub fn definition(project: &Project, name: &str) -> Option<(usize, Range<usize>)> {
+    let mut prototype = None;
+    for (index, source) in project.sources().enumerate() {
+        let mut start = 0;
+        for line in source.code.split_inclusive('\n') {
+            let code = line.split("//").next().unwrap_or_default();
+            let is_word_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+            let define = code.trim_start().strip_prefix("#define");
+            let found = match define {
+                Some(define) => {
+                    define.split(|c| !is_word_char(c)).find(|w| !w.is_empty()) == Some(name)
+                }
+                None => complete::function(code).is_some_and(|(function, _)| function == name),
+            };
+            // The name itself: the first word after `#define`, or the last
+            // before the parameters.
+            let head = code.split('(').next().unwrap_or(code);
+            let mut words = head.match_indices(name).map(|(at, _)| at).filter(|&at| {
+                let before = head[..at].chars().next_back();
+                let after = head[at + name.len()..].chars().next();
+                !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+            });
+            let at = if define.is_some() {
+                words.next()
+            } else {
+                words.last()
+            };
+            if let Some(at) = at.filter(|_| found) {
+                let range = start + at..start + at + name.len();
+                if define.is_some() || !code.trim_end().ends_with(';') {
+                    return Some((index, range));
+                }
+                prototype = prototype.or(Some((index, range)));
+            }
+            start += line.len();
+        }
+    }
+    prototype
+}
+
+/// A selection to make in the editor once it shows the source it's in.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Jump {
+    /// Position of the source in [`Project::sources`].
+    pub source: usize,
+    /// Byte range to select.
+    pub range: Range<usize>,
+    /// Whether the editor takes the keyboard focus, which the find bar keeps.
+    pub focus: bool,
+}
+
+impl Jump {
+    /// Select the range in the editor with `id` showing `code`. Call before
+    /// showing the editor.
+    pub fn select(&self, ctx: &egui::Context, id: egui::Id, code: &str) {
+        let range = char_range(code, &self.range);
+        let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+        state.cursor.set_char_range(Some(CCursorRange::two(
+            CCursor::new(range.start),
+            CCursor::new(range.end),
+        )));
+        state.store(ctx, id);
+        if self.focus {
+            ctx.memory_mut(|memory| memory.request_focus(id));
+        }
+    }
+
+    /// Scroll the editor `output` of `code` to the range. Call after showing
+    /// the editor.
+    pub fn scroll(&self, ui: &egui::Ui, output: &egui::text_edit::TextEditOutput, code: &str) {
+        let range = char_range(code, &self.range);
+        let start = output.galley.pos_from_ccursor(CCursor::new(range.start));
+        let end = output.galley.pos_from_ccursor(CCursor::new(range.end));
+        let rect = start.union(end).translate(output.galley_pos.to_vec2());
+        ui.scroll_to_rect(rect, Some(egui::Align::Center));
+    }
+}
+
+/// The selection of the editor with `id` showing `code`, by byte range.
+pub fn selection(ctx: &egui::Context, id: egui::Id, code: &str) -> Range<usize> {
+    let range = egui::TextEdit::load_state(ctx, id).and_then(|state| state.cursor.char_range());
+    let Some(range) = range else {
+        return 0..0;
+    };
+    let [start, end] = range.sorted();
+    let byte = |index: usize| {
+        code.char_indices()
+            .nth(index)
+            .map_or(code.len(), |(i, _)| i)
+    };
+    byte(start.index)..byte(end.index)
+}
+
+/// `range` of byte indices of `code` in char indices.
+pub fn char_range(code: &str, range: &Range<usize>) -> Range<usize> {
+    let start = code[..range.start].chars().count();
+    start..start + code[range.clone()].chars().count()
+}
+
+/// Byte `ranges` of `code`, sorted by their start, in char indices.
+pub fn char_ranges(code: &str, ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+    let (mut byte, mut index) = (0, 0);
+    ranges
+        .iter()
+        .map(|range| {
+            index += code[byte..range.start].chars().count();
+            byte = range.start;
+            index..index + code[range.clone()].chars().count()
+        })
+        .collect()
+}
@@ -0,0 +1,670 @@
+//! Rendering of multi-pass projects: buffer passes into ping-ponged float
+//! textures, then the image pass reading them through its channels.
+use super::include::Includes;
+use super::project::{ChannelInput, Filter, PassKind, Project, Sampler, UniformValue, Wrap};
+use super::uniforms;
+use super::{
+    create_bind_group_layout, create_pipeline, load_pass_shader, load_vertex_shader, CompileError,
+    WgpuUniform,
+};
+use anyhow::Context;
+use eframe::egui_wgpu::wgpu;
+use eframe::wgpu::util::DeviceExt;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Format of buffer pass outputs. Float like on Shadertoy, and filterable
+/// without extra device features.
+pub const BUFFER_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// An RGBA8 image bound to a channel.
+pub struct TextureImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl TextureImage {
+    /// Magenta and black checkerboard standing in for media that can't be loaded.
+    pub fn placeholder() -> Self {
+        const SIZE: u32 = 64;
+        let rgba = (0..SIZE * SIZE)
+            .flat_map(|i| {
+                let (x, y) = (i % SIZE / 8, i / SIZE / 8);
+                if (x + y) % 2 == 0 {
+                    [255, 0, 255, 255]
+                } else {
+                    [0, 0, 0, 255]
+                }
+            })
+            .collect();
+        Self {
+            width: SIZE,
+            height: SIZE,
+            rgba,
+        }
+    }
+
+    /// Decode an image file, flipped so that its first row is the bottom one
+    /// when `vflip` is set.
+    pub fn load(path: &Path, vflip: bool) -> crate::app::Result<Self> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            Ok(Self::from_image(image::open(path)?, vflip))
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (path, vflip);
+            anyhow::bail!("Textures need the desktop version, or the image dropped onto the page")
+        }
+    }
+
+    /// Decode the content of an image file; see [`TextureImage::load`].
+    pub fn decode(bytes: &[u8], vflip: bool) -> crate::app::Result<Self> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?, vflip))
+    }
+
+    fn from_image(image: image::DynamicImage, vflip: bool) -> Self {
+        let mut image = image.into_rgba8();
+        if vflip {
+            image::imageops::flip_vertical_in_place(&mut image);
+        }
+        Self {
+            width: image.width(),
+            height: image.height(),
+            rgba: image.into_raw(),
+        }
+    }
+}
+
+/// Decoded channel media, kept across recompiles so that editing code
+/// doesn't reload images or repeat warnings about them.
+#[derive(Default)]
+pub struct MediaCache {
+    textures: HashMap<(PathBuf, bool), Arc<TextureImage>>,
+    /// Content of image files dropped onto the window by file name, read
+    /// instead of files on disk with that name.
+    files: HashMap<String, Arc<[u8]>>,
+    /// Messages already logged.
+    logged: HashSet<String>,
+}
+
+impl MediaCache {
+    fn texture(&mut self, path: &Path, vflip: bool) -> Arc<TextureImage> {
+        let key = (path.to_path_buf(), vflip);
+        if let Some(texture) = self.textures.get(&key) {
+            return texture.clone();
+        }
+        let dropped = self
+            .files
+            .iter()
+            .find(|(name, _)| path.ends_with(name))
+            .map(|(_, bytes)| bytes.clone());
+        let texture = match dropped {
+            Some(bytes) => TextureImage::decode(&bytes, vflip),
+            None => TextureImage::load(path, vflip),
+        };
+        let texture = texture
+            .or_else(|e| super::examples::texture(path, vflip).ok_or(e))
+            .unwrap_or_else(|e| {
+                self.warn(format!("Using a placeholder for {}: {e}", path.display()));
+                TextureImage::placeholder()
+            });
+        let texture = Arc::new(texture);
+        self.textures.insert(key, texture.clone());
+        texture
+    }
+
+    /// Read `bytes` for images named `name` from now on.
+    pub fn add_file(&mut self, name: &str, bytes: Arc<[u8]>) {
+        self.textures.retain(|(path, _), _| !path.ends_with(name));
+        self.files.insert(name.to_string(), bytes);
+    }
+
+    /// Log `message` as a warning unless it was logged before.
+    pub fn warn(&mut self, message: String) {
+        if !self.logged.contains(&message) {
+            log::warn!("{message}");
+            self.logged.insert(message);
+        }
+    }
+
+    /// Log `message` as information unless it was logged before.
+    pub fn note(&mut self, message: String) {
+        if !self.logged.contains(&message) {
+            log::info!("{message}");
+            self.logged.insert(message);
+        }
+    }
+}
+
+enum ChannelSource {
+    Buffer(PassKind),
+    Texture(Arc<TextureImage>),
+}
+
+struct ProgramChannel {
+    source: ChannelSource,
+    sampler: Sampler,
+}
+
+struct ProgramPass {
+    kind: PassKind,
+    fragment: Cow<'static, [u32]>,
+    channels: [Option<ProgramChannel>; 4],
+    uniforms: uniforms::Layout,
+}
+
+/// A project compiled to SPIR-V with its media loaded, ready to render.
+pub struct Program {
+    vertex: Cow<'static, [u32]>,
+    /// Passes in execution order, ending with the image pass.
+    passes: Vec<ProgramPass>,
+    /// Canonical paths of the files the passes `#include`.
+    included: Vec<PathBuf>,
+    /// Values of the uniforms the code declares, as the project held them.
+    uniform_values: BTreeMap<String, UniformValue>,
+}
+
+impl Program {
+    /// A lone image pass without channels.
+    pub fn single(vertex: Cow<'static, [u32]>, fragment: Cow<'static, [u32]>) -> Self {
+        Self {
+            vertex,
+            passes: vec![ProgramPass {
+                kind: PassKind::Image,
+                fragment,
+                channels: Default::default(),
+                uniforms: uniforms::Layout::default(),
+            }],
+            included: Vec::new(),
+            uniform_values: BTreeMap::new(),
+        }
+    }
+
+    /// Compile every pass of `project`, resolving media paths against
+    /// `directory`. Media that can't be loaded is replaced by a placeholder.
+    pub fn compile(
+        project: &Project,
+        directory: &Path,
+        media: &mut MediaCache,
+    ) -> crate::app::Result<Self> {
+        let vertex = load_vertex_shader().context("Vertex shader")?;
+        let includes = Includes::new(directory, &project.include_paths);
+        let mut included = Vec::new();
+        let mut passes = Vec::new();
+        for pass in &project.passes {
+            if !(pass.kind.is_buffer() || pass.kind == PassKind::Image) {
+                media.warn(format!("{} passes are not supported yet", pass.kind.name()));
+                continue;
+            }
+            let common = project.common.as_ref().map_or("", |common| &common.code);
+            let fragment = load_pass_shader(pass.kind, common, &pass.source.code, &includes)
+                .map_err(|error| {
+                    // Along with the files of the passes compiled before.
+                    let mut error = CompileError::from(error);
+                    let mut files = included.clone();
+                    for path in error.includes {
+                        if !files.contains(&path) {
+                            files.push(path);
+                        }
+                    }
+                    error.includes = files;
+                    anyhow::Error::from(error)
+                })?;
+            for (name, rewrite) in &fragment.rewrites {
+                media.note(format!("{name} {rewrite}"));
+            }
+            for path in fragment.includes {
+                if !included.contains(&path) {
+                    included.push(path);
+                }
+            }
+            let uniforms = fragment.uniforms;
+            let fragment = fragment.spirv;
+            let channels = pass.channels.clone().map(|channel| {
+                let channel = channel?;
+                let source = match &channel.input {
+                    ChannelInput::Buffer { pass: input } if project.pass(*input).is_some() => {
+                        ChannelSource::Buffer(*input)
+                    }
+                    ChannelInput::Buffer { pass: input } => {
+                        media.warn(format!(
+                            "{} reads {}, which the project doesn't have",
+                            pass.kind.name(),
+                            input.name()
+                        ));
+                        ChannelSource::Texture(Arc::new(TextureImage::placeholder()))
+                    }
+                    ChannelInput::Texture { path } => ChannelSource::Texture(
+                        media.texture(&directory.join(path), channel.sampler.vflip),
+                    ),
+                    ChannelInput::Cubemap { .. }
+                    | ChannelInput::Keyboard
+                    | ChannelInput::Audio { .. } => {
+                        media.warn(format!(
+                            "{} uses a {} input, which is not supported yet",
+                            pass.kind.name(),
+                            input_name(&channel.input)
+                        ));
+                        ChannelSource::Texture(Arc::new(TextureImage::placeholder()))
+                    }
+                };
+                Some(ProgramChannel {
+                    source,
+                    sampler: channel.sampler,
+                })
+            });
+            passes.push(ProgramPass {
+                kind: pass.kind,
+                fragment,
+                channels,
+                uniforms,
+            });
+        }
+        // Buffers that aren't rendered read as placeholders.
+        let rendered: Vec<PassKind> = passes.iter().map(|pass| pass.kind).collect();
+        for channel in passes
+            .iter_mut()
+            .flat_map(|pass| pass.channels.iter_mut().flatten())
+        {
+            if let ChannelSource::Buffer(input) = channel.source {
+                if !rendered.contains(&input) {
+                    channel.source = ChannelSource::Texture(Arc::new(TextureImage::placeholder()));
+                }
+            }
+        }
+        Ok(Self {
+            vertex,
+            passes,
+            included,
+            uniform_values: project.uniforms.clone(),
+        })
+    }
+
+    /// Files the passes `#include`, to watch for changes.
+    pub fn included_files(&self) -> &[PathBuf] {
+        &self.included
+    }
+}
+
+fn input_name(input: &ChannelInput) -> &'static str {
+    match input {
+        ChannelInput::Buffer { .. } => "buffer",
+        ChannelInput::Texture { .. } => "texture",
+        ChannelInput::Cubemap { .. } => "cubemap",
+        ChannelInput::Keyboard => "keyboard",
+        ChannelInput::Audio { .. } => "audio",
+    }
+}
+
+/// What a channel binding points at once the program is on the GPU.
+enum ChannelView {
+    /// Index into `PassRenderer::textures`.
+    Texture(usize),
+    /// Index of the buffer pass in `PassRenderer::passes`.
+    Buffer(usize),
+    Unbound,
+}
+
+struct GpuPass {
+    kind: PassKind,
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    /// The `UserUniforms` block and where its members are.
+    user_uniform_buffer: wgpu::Buffer,
+    uniforms: uniforms::Layout,
+    channels: [ChannelView; 4],
+    samplers: [wgpu::Sampler; 4],
+    /// Two output textures for buffer passes, written on alternate frames.
+    targets: Vec<wgpu::TextureView>,
+    /// One bind group per frame parity.
+    bind_groups: Vec<wgpu::BindGroup>,
+}
+
+/// Renders a [`Program`]: buffer passes into textures owned by the renderer,
+/// and the image pass into whatever render pass it's given.
+pub struct PassRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    layout: wgpu::BindGroupLayout,
+    passes: Vec<GpuPass>,
+    textures: Vec<(wgpu::TextureView, [f32; 3])>,
+    unbound: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    /// Which of the two buffer targets is written this frame.
+    parity: usize,
+    uniform_values: BTreeMap<String, UniformValue>,
+}
+
+impl PassRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        program: &Program,
+        target_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let mut textures = Vec::new();
+        let passes = program
+            .passes
+            .iter()
+            .map(|pass| {
+                let format = if pass.kind.is_buffer() {
+                    BUFFER_FORMAT
+                } else {
+                    target_format
+                };
+                let channels = pass.channels.each_ref().map(|channel| match channel {
+                    Some(ProgramChannel {
+                        source: ChannelSource::Buffer(input),
+                        ..
+                    }) => ChannelView::Buffer(
+                        program
+                            .passes
+                            .iter()
+                            .position(|pass| pass.kind == *input)
+                            .expect("unrendered buffers are replaced by placeholders"),
+                    ),
+                    Some(ProgramChannel {
+                        source: ChannelSource::Texture(image),
+                        ..
+                    }) => {
+                        textures.push(upload_texture(device, queue, image));
+                        ChannelView::Texture(textures.len() - 1)
+                    }
+                    None => ChannelView::Unbound,
+                });
+                let samplers = pass.channels.each_ref().map(|channel| {
+                    create_sampler(
+                        device,
+                        channel.as_ref().map_or(Sampler::buffer(), |c| c.sampler),
+                    )
+                });
+                GpuPass {
+                    kind: pass.kind,
+                    pipeline: create_pipeline(
+                        device,
+                        program.vertex.clone(),
+                        pass.fragment.clone(),
+                        format,
+                    ),
+                    uniform_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("pass_uniform_buffer"),
+                        contents: &[0u8; std::mem::size_of::<WgpuUniform>()],
+                        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+                    }),
+                    user_uniform_buffer: device.create_buffer_init(
+                        &wgpu::util::BufferInitDescriptor {
+                            label: Some("pass_user_uniform_buffer"),
+                            contents: &pass.uniforms.pack(&program.uniform_values),
+                            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::UNIFORM,
+                        },
+                    ),
+                    uniforms: pass.uniforms.clone(),
+                    channels,
+                    samplers,
+                    targets: Vec::new(),
+                    bind_groups: Vec::new(),
+                }
+            })
+            .collect();
+        let unbound = upload_texture(
+            device,
+            queue,
+            &TextureImage {
+                width: 1,
+                height: 1,
+                rgba: vec![0; 4],
+            },
+        )
+        .0;
+        let mut renderer = Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            layout: create_bind_group_layout(device),
+            passes,
+            textures,
+            unbound,
+            width: 0,
+            height: 0,
+            parity: 0,
+            uniform_values: program.uniform_values.clone(),
+        };
+        renderer.resize(width, height);
+        renderer
+    }
+
+    /// Reallocate the buffer targets for a new resolution. Buffer contents
+    /// are lost, as on Shadertoy.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+        for pass in &mut self.passes {
+            pass.targets = if pass.kind.is_buffer() {
+                (0..2)
+                    .map(|_| {
+                        self.device
+                            .create_texture(&wgpu::TextureDescriptor {
+                                label: Some("buffer_target"),
+                                size: wgpu::Extent3d {
+                                    width,
+                                    height,
+                                    depth_or_array_layers: 1,
+                                },
+                                mip_level_count: 1,
+                                sample_count: 1,
+                                dimension: wgpu::TextureDimension::D2,
+                                format: BUFFER_FORMAT,
+                                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                                view_formats: &[],
+                            })
+                            .create_view(&wgpu::TextureViewDescriptor::default())
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+        }
+        for index in 0..self.passes.len() {
+            let bind_groups = (0..2)
+                .map(|parity| self.create_bind_group(index, parity))
+                .collect();
+            self.passes[index].bind_groups = bind_groups;
+        }
+    }
+
+    fn create_bind_group(&self, index: usize, parity: usize) -> wgpu::BindGroup {
+        let pass = &self.passes[index];
+        let views = pass.channels.each_ref().map(|channel| match *channel {
+            ChannelView::Texture(texture) => &self.textures[texture].0,
+            // Buffers that already ran this frame are read as written, the
+            // others (including the pass itself) as of the previous frame.
+            ChannelView::Buffer(input) if input < index => &self.passes[input].targets[parity],
+            ChannelView::Buffer(input) => &self.passes[input].targets[1 - parity],
+            ChannelView::Unbound => &self.unbound,
+        });
+        let mut entries = vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: pass.uniform_buffer.as_entire_binding(),
+        }];
+        for (channel, view) in views.into_iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 1 + channel as u32,
+                resource: wgpu::BindingResource::TextureView(view),
+            });
+        }
+        for (channel, sampler) in pass.samplers.iter().enumerate() {
+            entries.push(wgpu::BindGroupEntry {
+                binding: 5 + channel as u32,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            });
+        }
+        entries.push(wgpu::BindGroupEntry {
+            binding: 9,
+            resource: pass.user_uniform_buffer.as_entire_binding(),
+        });
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("pass_bind_group"),
+            layout: &self.layout,
+            entries: &entries,
+        })
+    }
+
+    fn channel_resolution(&self, pass: &GpuPass) -> [[f32; 3]; 4] {
+        pass.channels.each_ref().map(|channel| match *channel {
+            ChannelView::Texture(texture) => self.textures[texture].1,
+            ChannelView::Buffer(_) => [self.width as f32, self.height as f32, 1.0],
+            ChannelView::Unbound => [0.0; 3],
+        })
+    }
+
+    /// Values of the uniforms the code declares, from the next frame on.
+    pub fn set_uniform_values(&mut self, values: &BTreeMap<String, UniformValue>) {
+        if *values == self.uniform_values {
+            return;
+        }
+        self.uniform_values = values.clone();
+        for pass in &self.passes {
+            let bytes = pass.uniforms.pack(values);
+            self.queue
+                .write_buffer(&pass.user_uniform_buffer, 0, &bytes);
+        }
+    }
+
+    /// Upload the uniforms of every pass and render the buffer passes into
+    /// `encoder`. The image pass is drawn afterwards with [`Self::draw_image`].
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, uniform: &WgpuUniform) {
+        self.parity = 1 - self.parity;
+        if uniform.frame.0 == 0.0 {
+            // Restarting: passes reading the previous frame see black.
+            for view in self.passes.iter().flat_map(|pass| &pass.targets) {
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("buffer_clear"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+            }
+        }
+        for pass in &self.passes {
+            let mut pass_uniform = uniform.clone();
+            pass_uniform.channel_resolution = channel_resolution(self.channel_resolution(pass));
+            self.queue
+                .write_buffer(&pass.uniform_buffer, 0, pass_uniform.as_bytes());
+            if !pass.kind.is_buffer() {
+                continue;
+            }
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("buffer_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &pass.targets[self.parity],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&pass.pipeline);
+            render_pass.set_bind_group(0, &pass.bind_groups[self.parity], &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+    }
+
+    /// Draw the image pass of the frame last passed to [`Self::encode`].
+    pub fn draw_image(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        if let Some(pass) = self.passes.last() {
+            render_pass.set_pipeline(&pass.pipeline);
+            render_pass.set_bind_group(0, &pass.bind_groups[self.parity], &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+    }
+}
+
+fn channel_resolution(resolution: [[f32; 3]; 4]) -> std140::array<std140::vec3, 4> {
+    let [a, b, c, d] = resolution.map(|[x, y, z]| std140::vec3(x, y, z));
+    std140::array![a, b, c, d]
+}
+
+fn upload_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    image: &TextureImage,
+) -> (wgpu::TextureView, [f32; 3]) {
+    let size = wgpu::Extent3d {
+        width: image.width,
+        height: image.height,
+        depth_or_array_layers: 1,
+    };
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("channel_texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        &image.rgba,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(image.width * 4),
+            rows_per_image: Some(image.height),
+        },
+        size,
+    );
+    (
+        texture.create_view(&wgpu::TextureViewDescriptor::default()),
+        [image.width as f32, image.height as f32, 1.0],
+    )
+}
+
+fn create_sampler(device: &wgpu::Device, sampler: Sampler) -> wgpu::Sampler {
+    let address_mode = match sampler.wrap {
+        Wrap::Clamp => wgpu::AddressMode::ClampToEdge,
+        Wrap::Repeat => wgpu::AddressMode::Repeat,
+    };
+    let filter = match sampler.filter {
+        Filter::Nearest => wgpu::FilterMode::Nearest,
+        Filter::Linear | Filter::Mipmap => wgpu::FilterMode::Linear,
+    };
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("channel_sampler"),
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter: filter,
+        min_filter: filter,
+        mipmap_filter: filter,
+        ..Default::default()
+    })
+}
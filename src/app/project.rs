@@ -0,0 +1,474 @@
+//! Multi-pass shader projects and their JSON manifest.
+//!
+//! A project is a manifest file next to one GLSL file per pass. Every path in
+//! the manifest is relative to the manifest's directory, so a project folder
+//! can be moved or checked into git as a whole.
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Extension of project manifests.
+pub const PROJECT_EXTENSION: &str = "json";
+
+/// Manifest version written by this build. Bump it when the format changes
+/// and add the conversion from the previous version to `MIGRATIONS`.
+pub const VERSION: u32 = 1;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` manifest to version `n + 2`.
+const MIGRATIONS: [fn(serde_json::Value) -> crate::app::Result<serde_json::Value>;
+    VERSION as usize - 1] = [];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PassKind {
+    BufferA,
+    BufferB,
+    BufferC,
+    BufferD,
+    CubeA,
+    Sound,
+    Image,
+}
+
+impl PassKind {
+    /// Passes in the order they run each frame.
+    pub const ALL: [PassKind; 7] = [
+        PassKind::BufferA,
+        PassKind::BufferB,
+        PassKind::BufferC,
+        PassKind::BufferD,
+        PassKind::CubeA,
+        PassKind::Sound,
+        PassKind::Image,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PassKind::BufferA => "Buffer A",
+            PassKind::BufferB => "Buffer B",
+            PassKind::BufferC => "Buffer C",
+            PassKind::BufferD => "Buffer D",
+            PassKind::CubeA => "Cube A",
+            PassKind::Sound => "Sound",
+            PassKind::Image => "Image",
+        }
+    }
+
+    /// Default source file name for a new pass of this kind.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            PassKind::BufferA => "buffer_a.glsl",
+            PassKind::BufferB => "buffer_b.glsl",
+            PassKind::BufferC => "buffer_c.glsl",
+            PassKind::BufferD => "buffer_d.glsl",
+            PassKind::CubeA => "cube_a.glsl",
+            PassKind::Sound => "sound.glsl",
+            PassKind::Image => "image.glsl",
+        }
+    }
+
+    /// Whether the pass renders into a texture other passes can read.
+    pub fn is_buffer(self) -> bool {
+        matches!(
+            self,
+            PassKind::BufferA | PassKind::BufferB | PassKind::BufferC | PassKind::BufferD
+        )
+    }
+}
+
+/// A GLSL file of the project. Only the path is stored in the manifest; the
+/// code is read from and written to that file.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Source {
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub code: String,
+}
+
+impl Source {
+    pub fn new(path: impl Into<PathBuf>, code: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            code: code.into(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Pass {
+    pub kind: PassKind,
+    pub source: Source,
+    /// What `iChannel0` to `iChannel3` read from.
+    pub channels: [Option<Channel>; 4],
+}
+
+impl Pass {
+    pub fn new(kind: PassKind, source: Source) -> Self {
+        Self {
+            kind,
+            source,
+            channels: Default::default(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Channel {
+    pub input: ChannelInput,
+    pub sampler: Sampler,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChannelInput {
+    /// The output of a buffer pass. A pass reading itself, or a pass that
+    /// runs later, sees the previous frame.
+    Buffer { pass: PassKind },
+    /// An image file.
+    Texture { path: PathBuf },
+    /// A cubemap image file.
+    Cubemap { path: PathBuf },
+    /// Shadertoy's 256×3 keyboard state texture.
+    Keyboard,
+    /// The spectrum and waveform of an audio file.
+    Audio { path: PathBuf },
+}
+
+impl ChannelInput {
+    /// Short description for the channel list.
+    pub fn label(&self) -> String {
+        match self {
+            ChannelInput::Buffer { pass } => pass.name().to_string(),
+            ChannelInput::Texture { path }
+            | ChannelInput::Cubemap { path }
+            | ChannelInput::Audio { path } => path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            ChannelInput::Keyboard => "Keyboard".to_string(),
+        }
+    }
+
+    /// Path of the file the input reads, if any.
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            ChannelInput::Texture { path }
+            | ChannelInput::Cubemap { path }
+            | ChannelInput::Audio { path } => Some(path),
+            ChannelInput::Buffer { .. } | ChannelInput::Keyboard => None,
+        }
+    }
+
+    fn path_mut(&mut self) -> Option<&mut PathBuf> {
+        match self {
+            ChannelInput::Texture { path }
+            | ChannelInput::Cubemap { path }
+            | ChannelInput::Audio { path } => Some(path),
+            ChannelInput::Buffer { .. } | ChannelInput::Keyboard => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Filter {
+    Nearest,
+    Linear,
+    /// Trilinear filtering. Mipmaps are not generated yet, so this currently
+    /// samples like `Linear`.
+    Mipmap,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 3] = [Filter::Nearest, Filter::Linear, Filter::Mipmap];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Filter::Nearest => "Nearest",
+            Filter::Linear => "Linear",
+            Filter::Mipmap => "Mipmap",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Wrap {
+    Clamp,
+    Repeat,
+}
+
+impl Wrap {
+    pub const ALL: [Wrap; 2] = [Wrap::Clamp, Wrap::Repeat];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Wrap::Clamp => "Clamp",
+            Wrap::Repeat => "Repeat",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Sampler {
+    pub filter: Filter,
+    pub wrap: Wrap,
+    /// Flip images so that `v = 0` is their bottom row, like Shadertoy does.
+    /// Has no effect on buffers, which are always stored that way.
+    pub vflip: bool,
+}
+
+impl Sampler {
+    /// Shadertoy's defaults for buffer inputs.
+    pub fn buffer() -> Self {
+        Self {
+            filter: Filter::Linear,
+            wrap: Wrap::Clamp,
+            vflip: false,
+        }
+    }
+
+    /// Shadertoy's defaults for texture inputs.
+    pub fn texture() -> Self {
+        Self {
+            filter: Filter::Mipmap,
+            wrap: Wrap::Repeat,
+            vflip: true,
+        }
+    }
+}
+
+/// Value of a custom uniform, kept by name in the project.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum UniformValue {
+    Bool(bool),
+    Int(i32),
+    Float(f32),
+    /// A `vec2`, `vec3` or `vec4`.
+    Vector(Vec<f32>),
+}
+
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct Project {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    /// Resolution to export at. The view keeps its aspect ratio. `None`
+    /// follows the size of the view.
+    pub resolution: Option<[u32; 2]>,
+    /// Code prepended to every pass.
+    pub common: Option<Source>,
+    pub passes: Vec<Pass>,
+    pub uniforms: BTreeMap<String, UniformValue>,
+    /// Library roots for `#include <...>`, relative to the manifest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_paths: Vec<PathBuf>,
+}
+
+/// What gets written to disk: the project preceded by its format version.
+#[derive(Serialize)]
+struct Manifest<'a> {
+    version: u32,
+    #[serde(flatten)]
+    project: &'a Project,
+}
+
+impl Project {
+    /// A project made of a single image pass, as for a plain `.glsl` file.
+    pub fn single(path: impl Into<PathBuf>, code: impl Into<String>) -> Self {
+        Self {
+            passes: vec![Pass::new(PassKind::Image, Source::new(path, code))],
+            ..Default::default()
+        }
+    }
+
+    /// Whether the project holds nothing but the image code, so it can be
+    /// saved as a plain `.glsl` file without losing anything.
+    pub fn is_single_file(&self) -> bool {
+        let [pass] = self.passes.as_slice() else {
+            return false;
+        };
+        *self == Project::single(&pass.source.path, &pass.source.code)
+    }
+
+    pub fn pass(&self, kind: PassKind) -> Option<&Pass> {
+        self.passes.iter().find(|pass| pass.kind == kind)
+    }
+
+    pub fn pass_mut(&mut self, kind: PassKind) -> Option<&mut Pass> {
+        self.passes.iter_mut().find(|pass| pass.kind == kind)
+    }
+
+    /// The image pass, which every project has.
+    pub fn image(&self) -> &Pass {
+        self.pass(PassKind::Image)
+            .expect("projects always have an image pass")
+    }
+
+    /// Add an empty pass of `kind`, keeping passes in execution order.
+    pub fn add_pass(&mut self, kind: PassKind, code: impl Into<String>) {
+        if self.pass(kind).is_some() {
+            return;
+        }
+        self.passes
+            .push(Pass::new(kind, Source::new(kind.file_name(), code)));
+        self.passes.sort_by_key(|pass| pass.kind);
+    }
+
+    /// Remove the pass of `kind`, unless it's the image pass.
+    pub fn remove_pass(&mut self, kind: PassKind) {
+        if kind != PassKind::Image {
+            self.passes.retain(|pass| pass.kind != kind);
+        }
+    }
+
+    /// All GLSL files of the project: the common code first, then the passes.
+    pub fn sources(&self) -> impl Iterator<Item = &Source> {
+        self.common
+            .iter()
+            .chain(self.passes.iter().map(|pass| &pass.source))
+    }
+
+    pub fn sources_mut(&mut self) -> impl Iterator<Item = &mut Source> {
+        self.common
+            .iter_mut()
+            .chain(self.passes.iter_mut().map(|pass| &mut pass.source))
+    }
+
+    /// Parse a manifest, upgrading it from older versions. Sources are left
+    /// empty; see [`Project::load`].
+    pub fn from_manifest(json: &str) -> crate::app::Result<Self> {
+        let mut manifest: serde_json::Value = serde_json::from_str(json)?;
+        let version = manifest
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| anyhow!("Missing project version"))? as u32;
+        if version == 0 {
+            bail!("Invalid project version 0");
+        }
+        if version > VERSION {
+            bail!("Project version {version} was written by a newer shadertoy_rs (this one reads up to {VERSION})");
+        }
+        for migration in &MIGRATIONS[version as usize - 1..] {
+            manifest = migration(manifest)?;
+        }
+        if let Some(object) = manifest.as_object_mut() {
+            object.remove("version");
+        }
+        let project: Project = serde_json::from_value(manifest)?;
+        if project.pass(PassKind::Image).is_none() {
+            bail!("Project has no image pass");
+        }
+        Ok(project)
+    }
+
+    /// The manifest in the current format, without the sources.
+    pub fn to_manifest(&self) -> crate::app::Result<String> {
+        let manifest = Manifest {
+            version: VERSION,
+            project: self,
+        };
+        Ok(serde_json::to_string_pretty(&manifest)? + "\n")
+    }
+
+    /// Read the manifest at `path` and the sources it lists.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: &Path) -> crate::app::Result<Self> {
+        use anyhow::Context;
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut project = Self::from_manifest(&json)
+            .with_context(|| format!("Invalid project {}", path.display()))?;
+        let directory = directory(path);
+        for source in project.sources_mut() {
+            let source_path = directory.join(&source.path);
+            source.code = std::fs::read_to_string(&source_path)
+                .with_context(|| format!("Failed to read {}", source_path.display()))?;
+        }
+        Ok(project)
+    }
+
+    /// Write the sources and then the manifest to `path`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: &Path) -> crate::app::Result<()> {
+        use anyhow::Context;
+        let directory = directory(path);
+        for source in self.sources() {
+            let source_path = directory.join(&source.path);
+            std::fs::write(&source_path, &source.code)
+                .with_context(|| format!("Failed to write {}", source_path.display()))?;
+        }
+        std::fs::write(path, self.to_manifest()?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Rewrite asset paths relative to `from` so they stay valid relative to
+    /// `to`, for saving the project into another directory. Sources move
+    /// along with the manifest and keep their paths.
+    pub fn relocate(&mut self, from: &Path, to: &Path) {
+        for pass in &mut self.passes {
+            for channel in pass.channels.iter_mut().flatten() {
+                if let Some(path) = channel.input.path_mut() {
+                    *path = rebase(path, from, to);
+                }
+            }
+        }
+        for path in &mut self.include_paths {
+            *path = rebase(path, from, to);
+        }
+    }
+}
+
+/// Directory that relative paths of the file at `path` are resolved against.
+pub fn directory(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// Whether `json` looks like one of our manifests, as opposed to some other
+/// JSON file.
+pub fn is_manifest(json: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(json)
+        .is_ok_and(|value| value.get("version").is_some() && value.get("passes").is_some())
+}
+
+/// `path` relative to `directory` where possible, for storing in the project.
+pub fn relative_path(path: &Path, directory: &Path) -> PathBuf {
+    match (path.canonicalize(), directory.canonicalize()) {
+        (Ok(path), Ok(directory)) => relative_to(&path, &directory).unwrap_or(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+fn rebase(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let (Ok(from), Ok(to)) = (from.canonicalize(), to.canonicalize()) else {
+        return path.to_path_buf();
+    };
+    let absolute = from.join(path);
+    relative_to(&absolute, &to).unwrap_or(absolute)
+}
+
+/// `path` relative to `base`, both absolute. `None` if they share no root,
+/// like paths on different Windows drives.
+fn relative_to(path: &Path, base: &Path) -> Option<PathBuf> {
+    let path: Vec<Component<'_>> = path.components().collect();
+    let base: Vec<Component<'_>> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return None;
+    }
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    relative.extend(&path[common..]);
+    Some(relative)
+}
@@ -0,0 +1,303 @@
+//! Autosaved snapshots of the editor, to get unsaved work back after a crash.
+//!
+//! Every session keeps its own running mark and snapshot, so instances open
+//! side by side don't overwrite each other's. A session clears its mark when
+//! it shuts down cleanly. A mark left by a session that is no longer running
+//! at startup means it crashed or was killed, and its last snapshot is
+//! offered back. Snapshots are kept in a recovery directory on native builds
+//! and in local storage on the web.
+use super::state::State;
+
+/// Seconds between autosaves of an editor with changes.
+pub const AUTOSAVE_INTERVAL: f64 = 30.0;
+
+/// Where one session keeps its running mark and snapshot.
+pub struct Recovery {
+    #[cfg(not(target_arch = "wasm32"))]
+    directory: std::path::PathBuf,
+    /// Process of this session.
+    #[cfg(not(target_arch = "wasm32"))]
+    pid: u32,
+    #[cfg(target_arch = "wasm32")]
+    storage: web_sys::Storage,
+    /// Names this session among the others keeping snapshots.
+    session: String,
+}
+
+/// Milliseconds since the Unix epoch, to tell sessions apart.
+fn unix_millis() -> u128 {
+    instant::SystemTime::now()
+        .duration_since(instant::SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Recovery {
+    const RUNNING: &str = "running";
+    const SNAPSHOT: &str = "snapshot.json";
+
+    /// The `recovery` directory next to the app's stored state, if the
+    /// platform has a place for it.
+    pub fn new() -> Option<Self> {
+        // The app id passed to `eframe::run_native`.
+        let directory = eframe::storage_dir("shadertoy_rs")?.join("recovery");
+        Some(Self::in_directory(directory, std::process::id()))
+    }
+
+    /// A session of process `pid` keeping its snapshots under `directory`.
+    pub fn in_directory(directory: impl Into<std::path::PathBuf>, pid: u32) -> Self {
+        Self {
+            directory: directory.into(),
+            pid,
+            session: format!("{pid}-{}", unix_millis()),
+        }
+    }
+
+    fn session_directory(&self) -> std::path::PathBuf {
+        self.directory.join(&self.session)
+    }
+
+    /// Mark this session as running. Returns the last snapshot of a previous
+    /// session that didn't shut down cleanly, which this session takes over.
+    pub fn start(&self) -> crate::app::Result<Option<State>> {
+        let own = self.session_directory();
+        std::fs::create_dir_all(&own)?;
+        std::fs::write(own.join(Self::RUNNING), self.pid.to_string())?;
+        // The most recent snapshot of the sessions that crashed.
+        let mut crashed = None;
+        for entry in std::fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path == own || !path.is_dir() {
+                continue;
+            }
+            let pid = std::fs::read_to_string(path.join(Self::RUNNING))
+                .ok()
+                .and_then(|pid| pid.trim().parse().ok());
+            if pid.is_some_and(is_running) {
+                continue;
+            }
+            let snapshot = path.join(Self::SNAPSHOT);
+            let modified = snapshot.metadata().and_then(|m| m.modified()).ok();
+            match (modified, &crashed) {
+                (None, _) => std::fs::remove_dir_all(&path)?,
+                (Some(modified), Some((_, latest))) if modified <= *latest => {}
+                (Some(modified), _) => crashed = Some((path, modified)),
+            }
+        }
+        let Some((path, _)) = crashed else {
+            return Ok(None);
+        };
+        let recovered = State::parse(&std::fs::read_to_string(path.join(Self::SNAPSHOT))?)?;
+        // Taken over, so it's offered once and kept until dealt with.
+        std::fs::rename(path.join(Self::SNAPSHOT), own.join(Self::SNAPSHOT))?;
+        std::fs::remove_dir_all(path)?;
+        Ok(Some(recovered))
+    }
+
+    /// Replace the snapshot with `state`.
+    pub fn snapshot(&self, state: &State) -> crate::app::Result<()> {
+        // Written aside and renamed, so a crash while writing leaves the
+        // previous snapshot intact.
+        let own = self.session_directory();
+        let partial = own.join("snapshot.json.partial");
+        std::fs::write(&partial, state.to_json()?)?;
+        std::fs::rename(partial, own.join(Self::SNAPSHOT))?;
+        Ok(())
+    }
+
+    /// Forget the snapshot, once there is nothing left to recover.
+    pub fn discard(&self) -> crate::app::Result<()> {
+        remove_file(&self.session_directory().join(Self::SNAPSHOT))
+    }
+
+    /// Mark this session as shut down cleanly and forget its snapshot.
+    pub fn finish(&self) -> crate::app::Result<()> {
+        match std::fs::remove_dir_all(self.session_directory()) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Remove `path`, if it exists.
+#[cfg(not(target_arch = "wasm32"))]
+fn remove_file(path: &std::path::Path) -> crate::app::Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether process `pid` is running.
+#[cfg(unix)]
+fn is_running(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 checks the process exists without sending anything. It may
+    // belong to another user, which it isn't allowed to signal.
+    let signalled = unsafe { libc::kill(pid, 0) };
+    signalled == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether process `pid` is running.
+#[cfg(windows)]
+fn is_running(pid: u32) -> bool {
+    let output = std::process::Command::new("tasklist")
+        .args(["/NH", "/FO", "CSV", "/FI", &format!("PID eq {pid}")])
+        .output();
+    // Lists nothing but a message when no process matches.
+    output
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{pid}\"")))
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Recovery {
+    const RUNNING: &str = "shadertoy_rs_running_";
+    const SNAPSHOT: &str = "shadertoy_rs_recovery_";
+    /// Milliseconds between heartbeats of a running session.
+    const HEARTBEAT_MILLIS: i32 = 30 * 1000;
+    /// Milliseconds without a heartbeat after which a session counts as
+    /// gone. Browsers run the timers of background tabs as rarely as once a
+    /// minute.
+    const STALE_MILLIS: u128 = 3 * 60 * 1000;
+
+    /// The page's local storage, if the browser allows it. Leaving the page
+    /// counts as a clean shutdown. A timer keeps noting that the session is
+    /// running, also while the tab is in the background and doesn't render.
+    pub fn new() -> Option<Self> {
+        use eframe::wasm_bindgen::{closure::Closure, JsCast as _};
+        let window = web_sys::window()?;
+        let storage = window.local_storage().ok()??;
+        let session = unix_millis().to_string();
+        let recovery = {
+            let (storage, session) = (storage.clone(), session.clone());
+            move || Self {
+                storage: storage.clone(),
+                session: session.clone(),
+            }
+        };
+        let on_leave = {
+            let recovery = recovery.clone();
+            Closure::<dyn Fn()>::new(move || {
+                if let Err(e) = recovery().finish() {
+                    log::error!("Error clearing the autosave: {e:#}");
+                }
+            })
+        };
+        window
+            .add_event_listener_with_callback("pagehide", on_leave.as_ref().unchecked_ref())
+            .ok()?;
+        on_leave.forget();
+        let on_beat = Closure::<dyn Fn()>::new(move || {
+            if let Err(e) = recovery().heartbeat() {
+                log::error!("Error marking the session as running: {e:#}");
+            }
+        });
+        window
+            .set_interval_with_callback_and_timeout_and_arguments_0(
+                on_beat.as_ref().unchecked_ref(),
+                Self::HEARTBEAT_MILLIS,
+            )
+            .ok()?;
+        on_beat.forget();
+        Some(Self { storage, session })
+    }
+
+    /// Mark this session as running. Returns the last snapshot of a previous
+    /// session that didn't shut down cleanly, which this session takes over.
+    pub fn start(&self) -> crate::app::Result<Option<State>> {
+        self.heartbeat()?;
+        let now = unix_millis();
+        // The most recent snapshot of the sessions that crashed.
+        let mut crashed: Option<(String, u128)> = None;
+        for key in self.keys()? {
+            let Some(session) = key.strip_prefix(Self::RUNNING) else {
+                continue;
+            };
+            if session == self.session {
+                continue;
+            }
+            let beat: u128 = self
+                .get(&key)?
+                .and_then(|beat| beat.parse().ok())
+                .unwrap_or_default();
+            if now.saturating_sub(beat) < Self::STALE_MILLIS {
+                continue;
+            }
+            if self.get(&format!("{}{session}", Self::SNAPSHOT))?.is_none() {
+                self.remove(&key)?;
+            } else if crashed.as_ref().map_or(true, |(_, latest)| beat > *latest) {
+                crashed = Some((session.to_string(), beat));
+            }
+        }
+        let Some((session, _)) = crashed else {
+            return Ok(None);
+        };
+        let snapshot = format!("{}{session}", Self::SNAPSHOT);
+        let Some(json) = self.get(&snapshot)? else {
+            return Ok(None);
+        };
+        let recovered = State::parse(&json)?;
+        // Taken over, so it's offered once and kept until dealt with.
+        self.set(&self.key(Self::SNAPSHOT), &json)?;
+        self.remove(&snapshot)?;
+        self.remove(&format!("{}{session}", Self::RUNNING))?;
+        Ok(Some(recovered))
+    }
+
+    /// Note that this session is still running.
+    fn heartbeat(&self) -> crate::app::Result<()> {
+        self.set(&self.key(Self::RUNNING), &unix_millis().to_string())
+    }
+
+    /// Replace the snapshot with `state`.
+    pub fn snapshot(&self, state: &State) -> crate::app::Result<()> {
+        self.set(&self.key(Self::SNAPSHOT), &state.to_json()?)
+    }
+
+    /// Forget the snapshot, once there is nothing left to recover.
+    pub fn discard(&self) -> crate::app::Result<()> {
+        self.remove(&self.key(Self::SNAPSHOT))
+    }
+
+    /// Mark this session as shut down cleanly and forget its snapshot.
+    pub fn finish(&self) -> crate::app::Result<()> {
+        self.discard()?;
+        self.remove(&self.key(Self::RUNNING))
+    }
+
+    /// The storage key of this session starting with `prefix`.
+    fn key(&self, prefix: &str) -> String {
+        format!("{prefix}{}", self.session)
+    }
+
+    fn keys(&self) -> crate::app::Result<Vec<String>> {
+        let error = |e| anyhow::anyhow!("Error reading local storage: {e:?}");
+        let length = self.storage.length().map_err(error)?;
+        let mut keys = Vec::new();
+        for index in 0..length {
+            keys.extend(self.storage.key(index).map_err(error)?);
+        }
+        Ok(keys)
+    }
+
+    fn get(&self, key: &str) -> crate::app::Result<Option<String>> {
+        self.storage
+            .get_item(key)
+            .map_err(|e| anyhow::anyhow!("Error reading local storage: {e:?}"))
+    }
+
+    fn set(&self, key: &str, value: &str) -> crate::app::Result<()> {
+        self.storage
+            .set_item(key, value)
+            .map_err(|e| anyhow::anyhow!("Error writing local storage: {e:?}"))
+    }
+
+    fn remove(&self, key: &str) -> crate::app::Result<()> {
+        self.storage
+            .remove_item(key)
+            .map_err(|e| anyhow::anyhow!("Error writing local storage: {e:?}"))
+    }
+}
@@ -0,0 +1,325 @@
+use super::passes::{PassRenderer, Program};
+use super::project::UniformValue;
+use super::WgpuUniform;
+use eframe::egui_wgpu::wgpu;
+use std::collections::BTreeMap;
+
+/// Format used for offscreen targets. Non-sRGB like the egui swapchain, so
+/// exported frames look the same as the live preview.
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Format used for HDR exports, keeping values above 1.0 and below 0.0.
+pub const OFFSCREEN_HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+/// Tightly packed RGBA pixels read back from an offscreen target.
+pub enum Pixels {
+    Rgba8(Vec<u8>),
+    Rgba32Float(Vec<f32>),
+}
+
+/// A rendered frame read back from the GPU.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Pixels,
+}
+
+impl Frame {
+    /// The frame as RGB8 with alpha dropped, as the shader's alpha is not
+    /// shown on screen. Float values are clamped to `[0, 1]`.
+    pub fn rgb(&self) -> Vec<u8> {
+        match &self.pixels {
+            Pixels::Rgba8(data) => data
+                .chunks_exact(4)
+                .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+                .collect(),
+            Pixels::Rgba32Float(data) => data
+                .chunks_exact(4)
+                .flat_map(|rgba| [0, 1, 2].map(|c| (rgba[c].clamp(0.0, 1.0) * 255.0).round() as u8))
+                .collect(),
+        }
+    }
+
+    /// The frame as linear RGB floats with alpha dropped.
+    pub fn rgb_f32(&self) -> Vec<f32> {
+        match &self.pixels {
+            Pixels::Rgba8(data) => data
+                .chunks_exact(4)
+                .flat_map(|rgba| [0, 1, 2].map(|c| rgba[c] as f32 / 255.0))
+                .collect(),
+            Pixels::Rgba32Float(data) => data
+                .chunks_exact(4)
+                .flat_map(|rgba| [rgba[0], rgba[1], rgba[2]])
+                .collect(),
+        }
+    }
+
+    /// Shrink the frame by an integer `factor`, averaging each block of pixels.
+    pub fn downscale(self, factor: u32) -> Frame {
+        if factor <= 1 {
+            return self;
+        }
+        let (width, height) = ((self.width / factor).max(1), (self.height / factor).max(1));
+        let pixels = match &self.pixels {
+            Pixels::Rgba8(data) => Pixels::Rgba8(box_filter(
+                data,
+                (self.width, self.height),
+                (width, height),
+                factor,
+                |v| v.round() as u8,
+            )),
+            Pixels::Rgba32Float(data) => Pixels::Rgba32Float(box_filter(
+                data,
+                (self.width, self.height),
+                (width, height),
+                factor,
+                |v| v,
+            )),
+        };
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+}
+
+fn box_filter<T: Copy + Into<f32>>(
+    data: &[T],
+    (src_width, src_height): (u32, u32),
+    (width, height): (u32, u32),
+    factor: u32,
+    from_f32: impl Fn(f32) -> T,
+) -> Vec<T> {
+    let mut result = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = [0.0f32; 4];
+            let mut count = 0.0;
+            for sy in y * factor..((y + 1) * factor).min(src_height) {
+                for sx in x * factor..((x + 1) * factor).min(src_width) {
+                    let i = ((sy * src_width + sx) * 4) as usize;
+                    for (c, sum) in sum.iter_mut().enumerate() {
+                        *sum += data[i + c].into();
+                    }
+                    count += 1.0;
+                }
+            }
+            result.extend(sum.map(|sum| from_f32(sum / count)));
+        }
+    }
+    result
+}
+
+/// Renders the shader into a texture of a fixed size instead of the window.
+pub struct OffscreenRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    passes: PassRenderer,
+    texture: wgpu::Texture,
+    readback_buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    bytes_per_pixel: u32,
+    padded_bytes_per_row: u32,
+    width: u32,
+    height: u32,
+}
+
+impl OffscreenRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        program: &Program,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let passes = PassRenderer::new(device, queue, program, format, width, height);
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let bytes_per_pixel = format.block_copy_size(None).unwrap_or(4);
+        // Rows copied out of a texture must be aligned to 256 bytes.
+        let padded_bytes_per_row =
+            (width * bytes_per_pixel).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen_readback"),
+            size: (padded_bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            passes,
+            texture,
+            readback_buffer,
+            format,
+            bytes_per_pixel,
+            padded_bytes_per_row,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Values of the uniforms the shader declares, from the next frame on.
+    pub fn set_uniform_values(&mut self, values: &BTreeMap<String, UniformValue>) {
+        self.passes.set_uniform_values(values);
+    }
+
+    /// A texture [`Self::draw`] can copy frames into. Its format has the
+    /// sRGB suffix, so sampling it reads the colors the way egui expects of
+    /// the textures it's given and [`Frame::rgb`] treats them.
+    pub fn create_srgb_target(&self) -> wgpu::Texture {
+        self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("offscreen_srgb_target"),
+            size: self.texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format.add_srgb_suffix(),
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    /// Render one frame of every pass with the given uniforms into `target`,
+    /// made by [`Self::create_srgb_target`], without waiting for it.
+    pub fn draw(&mut self, uniform: &WgpuUniform, target: &wgpu::Texture) {
+        let mut encoder = self.encode(uniform);
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            target.as_image_copy(),
+            self.texture.size(),
+        );
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// The commands rendering one frame into the texture.
+    fn encode(&mut self, uniform: &WgpuUniform) -> wgpu::CommandEncoder {
+        let view = self
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("offscreen_encoder"),
+            });
+        self.passes.encode(&mut encoder, uniform);
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("offscreen_render_pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.passes.draw_image(&mut render_pass);
+        }
+        encoder
+    }
+
+    /// Render one frame of every pass with the given uniforms and wait for the result.
+    pub fn render(&mut self, uniform: &WgpuUniform) -> crate::app::Result<Frame> {
+        let mut encoder = self.encode(uniform);
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = tx.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        rx.recv()??;
+
+        let row_bytes = (self.width * self.bytes_per_pixel) as usize;
+        let mut data = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks(self.padded_bytes_per_row as usize) {
+                data.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        self.readback_buffer.unmap();
+        let pixels = match self.format {
+            OFFSCREEN_HDR_FORMAT => Pixels::Rgba32Float(bytemuck::pod_collect_to_vec(&data)),
+            _ => Pixels::Rgba8(data),
+        };
+        Ok(Frame {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+}
+
+/// Create a device without any window or surface, for rendering from scripts
+/// and CI. With `software` set, only a fallback adapter such as lavapipe or
+/// llvmpipe is accepted.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn headless_device(software: bool) -> crate::app::Result<(wgpu::Device, wgpu::Queue)> {
+    use anyhow::anyhow;
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        force_fallback_adapter: software,
+        compatible_surface: None,
+    }))
+    .ok_or_else(|| anyhow!("No suitable graphics adapter found"))?;
+    log::info!("Using adapter {:?}", adapter.get_info());
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("headless_device"),
+            ..Default::default()
+        },
+        None,
+    ))?;
+    Ok((device, queue))
+}
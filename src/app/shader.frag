@@ -1,5 +1,5 @@
 #version 450
-
+{defines}
 layout (binding = 0, std140) uniform Uniforms {{
     vec2 iResolution;
     float iTime;
@@ -9,16 +9,40 @@ layout (binding = 0, std140) uniform Uniforms {{
     vec4 iMouse;
     vec4 iDate;
     float iSampleRate;
-    //vec3 iChannelResolution[4];
+    vec3 iChannelResolution[4];
+    // Value of the number literal being alt-dragged in the editor.
+    float _tweak;
 }};
+// Uniforms declared in the shader's own code, with values from the project.
+layout (binding = 9, std140) uniform UserUniforms {{ {uniforms} }};
+
+// wgpu has no combined image samplers, so every channel is a texture and a
+// sampler put together where it's used.
+layout (binding = 1) uniform texture2D _iChannel0_texture;
+layout (binding = 2) uniform texture2D _iChannel1_texture;
+layout (binding = 3) uniform texture2D _iChannel2_texture;
+layout (binding = 4) uniform texture2D _iChannel3_texture;
+layout (binding = 5) uniform sampler _iChannel0_sampler;
+layout (binding = 6) uniform sampler _iChannel1_sampler;
+layout (binding = 7) uniform sampler _iChannel2_sampler;
+layout (binding = 8) uniform sampler _iChannel3_sampler;
+#define iChannel0 sampler2D(_iChannel0_texture, _iChannel0_sampler)
+#define iChannel1 sampler2D(_iChannel1_texture, _iChannel1_sampler)
+#define iChannel2 sampler2D(_iChannel2_texture, _iChannel2_sampler)
+#define iChannel3 sampler2D(_iChannel3_texture, _iChannel3_sampler)
 
 layout (location = 0) out vec4 _f_color;
 
+{common}
 {content}
 
 void main() {{
     vec2 fragCoord = gl_FragCoord.xy;
+    // The image has y pointing up like on Shadertoy. Buffers keep the
+    // framebuffer's orientation, so that their rows line up with texture
+    // coordinates when other passes read them.
+#ifndef BUFFER_PASS
     fragCoord.y = iResolution.y - fragCoord.y;
+#endif
     mainImage(_f_color, fragCoord);
 }}
-
@@ -1,4 +1,10 @@
+use super::compat;
+use super::include::Includes;
+use super::include::{Expanded, Expander, LineMap};
+use super::project::PassKind;
+use super::uniforms;
 use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn convert_shader(source: &str, stage: shaderc::ShaderKind) -> crate::app::Result<Vec<u32>> {
@@ -18,15 +24,74 @@ pub fn convert_shader(source: &str, stage: shaderc::ShaderKind) -> crate::app::R
     )?;
     Ok(binary_result.as_binary().into())
 }
+/// Compile a filled-in fragment template with naga, which unlike shaderc
+/// also runs on the web. Errors point at lines the way shaderc's do.
+pub fn convert_shader_with_naga(source: &str) -> crate::app::Result<Vec<u32>> {
+    use anyhow::anyhow;
+    use naga::back::spv;
+    use naga::valid::{Capabilities, ValidationFlags, Validator};
+    let located = |span: naga::Span, message: String| {
+        let line = span.location(source).line_number;
+        format!("shader.glsl:{line}: error: {message}")
+    };
+    let options = naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+    let module = naga::front::glsl::Frontend::default()
+        .parse(&options, source)
+        .map_err(|e| {
+            let errors: Vec<String> = e
+                .errors
+                .iter()
+                .map(|error| located(error.meta, error.kind.to_string()))
+                .collect();
+            anyhow!("{}", errors.join("\n"))
+        })?;
+    let info = Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|e| {
+            // The outermost error only names the function; the cause is inside.
+            let mut message = e.as_inner().to_string();
+            let mut cause = std::error::Error::source(e.as_inner());
+            while let Some(error) = cause {
+                message = format!("{message}: {error}");
+                cause = error.source();
+            }
+            match e.spans().next() {
+                Some((span, _)) => anyhow!("{}", located(*span, message)),
+                None => anyhow!("{message}"),
+            }
+        })?;
+    let pipeline = spv::PipelineOptions {
+        shader_stage: naga::ShaderStage::Fragment,
+        entry_point: "main".to_string(),
+    };
+    Ok(spv::write_vec(
+        &module,
+        &info,
+        &spv::Options::default(),
+        Some(&pipeline),
+    )?)
+}
+
+/// Read a template from the source tree so edits are picked up while running
+/// from the repo, falling back to the copy built into the binary elsewhere.
 #[cfg(not(target_arch = "wasm32"))]
 macro_rules! load_shader {
-    ($path:literal) => {
-        if cfg!(target_arch = "wasm32") {
-            include_str!($path).to_string()
+    ($path:literal) => {{
+        let path = format!("src/app/{}", $path);
+        let path = std::path::Path::new(&path);
+        if path.exists() {
+            std::fs::read_to_string(path)?
         } else {
-            let path = format!("src/app/{}", $path);
-            std::fs::read_to_string(&std::path::Path::new(&path))?
+            include_str!($path).to_string()
         }
+    }};
+}
+
+/// The template built into the binary; the web version has no source tree.
+#[cfg(target_arch = "wasm32")]
+macro_rules! load_shader {
+    ($path:literal) => {
+        include_str!($path).to_string()
     };
 }
 
@@ -41,24 +106,251 @@ pub fn load_vertex_shader() -> crate::app::Result<Cow<'static, [u32]>> {
         Ok(Cow::from(bytemuck::cast_slice(bytes)))
     }
 }
-pub fn load_fragment_shader(content: &str) -> crate::app::Result<Cow<'static, [u32]>> {
+/// A compiled fragment shader and what was rewritten in its code to get it
+/// through the compiler.
+pub struct FragmentShader {
+    pub spirv: Cow<'static, [u32]>,
+    /// Rewrites by the name of the code they were made in: the pass, the
+    /// Common tab or an included file.
+    pub rewrites: Vec<(String, compat::Rewrite)>,
+    /// Canonical paths of the files pulled in with `#include`.
+    pub includes: Vec<PathBuf>,
+    /// Where the uniforms the code declares are in the `UserUniforms` block.
+    pub uniforms: uniforms::Layout,
+}
+
+/// A shader that failed to compile, with the files it included up to the
+/// error. They are still watched, so fixing one of them compiles it again.
+#[derive(Debug)]
+pub struct CompileError {
+    error: anyhow::Error,
+    /// Canonical paths of the files pulled in with `#include`.
+    pub includes: Vec<PathBuf>,
+}
+
+impl std::fmt::Display for CompileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.error)
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+impl From<anyhow::Error> for CompileError {
+    /// The [`CompileError`] in `error`, or `error` with no included files.
+    fn from(error: anyhow::Error) -> Self {
+        error.downcast().unwrap_or_else(|error| Self {
+            error,
+            includes: Vec::new(),
+        })
+    }
+}
+
+/// The files included up to `error`, if it's a [`CompileError`].
+pub fn included_files(error: &anyhow::Error) -> &[PathBuf] {
+    error
+        .downcast_ref::<CompileError>()
+        .map_or(&[], |error| &error.includes)
+}
+
+/// Compile Shadertoy-style code into the fragment template. GLSL ES code is
+/// rewritten to GLSL 4.50 first; see [`compat`].
+pub fn load_fragment_shader(content: &str) -> crate::app::Result<FragmentShader> {
+    load_pass_shader(
+        PassKind::Image,
+        "",
+        content,
+        &Includes::new(Path::new("."), &[]),
+    )
+}
+
+/// Compile the code of a pass of kind `kind` into the fragment template,
+/// preceded by the project's `common` code, with `#include`s resolved by
+/// `includes`. Compiler diagnostics name the Common tab, the pass or the
+/// included file, with line numbers counted in that code. Fails with a
+/// [`CompileError`]. The web version compiles with naga, apart from the
+/// bundled examples, which are compiled ahead of time.
+pub fn load_pass_shader(
+    kind: PassKind,
+    common: &str,
+    content: &str,
+    includes: &Includes,
+) -> crate::app::Result<FragmentShader> {
     #[cfg(not(target_arch = "wasm32"))]
     {
-        let template = load_shader!("shader.frag");
-        let map = [("content".to_string(), content.to_string())]
-            .into_iter()
-            .collect::<std::collections::HashMap<String, String>>();
-        convert_shader(
-            &strfmt::strfmt(template.as_str(), &map)?,
-            shaderc::ShaderKind::Fragment,
-        )
-        .map(Cow::from)
+        compile_with(kind, common, content, includes, |source| {
+            convert_shader(source, shaderc::ShaderKind::Fragment)
+        })
     }
     #[cfg(target_arch = "wasm32")]
     {
-        let _ = content;
-        let bytes = include_bytes!("shader.frag.spv");
-        Ok(Cow::from(bytemuck::cast_slice(bytes)))
+        if let Some(spirv) = super::examples::precompiled(kind, common, content) {
+            return Ok(FragmentShader {
+                spirv: spirv.into(),
+                rewrites: Vec::new(),
+                includes: Vec::new(),
+                uniforms: uniforms::Layout::default(),
+            });
+        }
+        load_pass_shader_with_naga(kind, common, content, includes)
+    }
+}
+
+/// [`load_pass_shader`] as the web version compiles code that isn't an
+/// example, with [`convert_shader_with_naga`].
+pub fn load_pass_shader_with_naga(
+    kind: PassKind,
+    common: &str,
+    content: &str,
+    includes: &Includes,
+) -> crate::app::Result<FragmentShader> {
+    compile_with(kind, common, content, includes, convert_shader_with_naga)
+}
+
+/// [`load_pass_shader`], turning the filled-in template into SPIR-V with
+/// `convert`.
+fn compile_with(
+    kind: PassKind,
+    common: &str,
+    content: &str,
+    includes: &Includes,
+    convert: impl FnOnce(&str) -> crate::app::Result<Vec<u32>>,
+) -> crate::app::Result<FragmentShader> {
+    let mut expander = includes.expander();
+    compile_expanded(kind, common, content, &mut expander, convert).map_err(|error| {
+        CompileError {
+            error,
+            includes: expander.files().to_vec(),
+        }
+        .into()
+    })
+}
+
+/// [`compile_with`], resolving includes with `expander`.
+fn compile_expanded(
+    kind: PassKind,
+    common: &str,
+    content: &str,
+    expander: &mut Expander<'_>,
+    convert: impl FnOnce(&str) -> crate::app::Result<Vec<u32>>,
+) -> crate::app::Result<FragmentShader> {
+    let template = load_shader!("shader.frag");
+    let defines = if kind.is_buffer() {
+        "#define BUFFER_PASS"
+    } else {
+        ""
+    };
+    let common = expander.expand("Common", common)?;
+    let content = expander.expand(kind.name(), content)?;
+    let mut declared = uniforms::declarations(&common.code);
+    declared.extend(uniforms::declarations(&content.code));
+    let layout = uniforms::Layout::new(&declared)?;
+    let rewritten_common = compat::rewrite(&uniforms::strip(&common.code));
+    let rewritten_content = compat::rewrite(&uniforms::strip(&content.code));
+    let lines = SourceLines::new(&template, &common, &content);
+    let map = [
+        ("defines".to_string(), defines.to_string()),
+        ("uniforms".to_string(), layout.block()),
+        ("common".to_string(), rewritten_common.code),
+        ("content".to_string(), rewritten_content.code),
+    ]
+    .into_iter()
+    .collect::<std::collections::HashMap<String, String>>();
+    let spirv = convert(&strfmt::strfmt(template.as_str(), &map)?)
+        .map_err(|e| anyhow::anyhow!("{}", lines.map_diagnostics(&format!("{e:#}"))))?;
+    let mut rewrites = locate_rewrites(rewritten_common.rewrites, &common.lines);
+    rewrites.extend(locate_rewrites(rewritten_content.rewrites, &content.lines));
+    Ok(FragmentShader {
+        spirv: spirv.into(),
+        rewrites,
+        includes: expander.files().to_vec(),
+        uniforms: layout,
+    })
+}
+
+/// Split `rewrites` of expanded code by the file each line came from.
+fn locate_rewrites(
+    rewrites: Vec<compat::Rewrite>,
+    lines: &LineMap,
+) -> Vec<(String, compat::Rewrite)> {
+    let mut located: Vec<(String, compat::Rewrite)> = Vec::new();
+    for rewrite in rewrites {
+        let start = located.len();
+        for line in rewrite.lines {
+            let Some((name, line)) = lines.locate(line) else {
+                continue;
+            };
+            match located[start..].iter_mut().find(|(n, _)| n == name) {
+                Some((_, rewrite)) => rewrite.lines.push(line),
+                None => located.push((
+                    name.to_string(),
+                    compat::Rewrite {
+                        lines: vec![line],
+                        description: rewrite.description.clone(),
+                    },
+                )),
+            }
+        }
+    }
+    located
+}
+
+/// Where the common code and the pass code start in the filled-in template,
+/// to translate the compiler's line numbers back.
+struct SourceLines<'a> {
+    common_start: usize,
+    content_start: usize,
+    common: &'a LineMap,
+    content: &'a LineMap,
+}
+
+impl<'a> SourceLines<'a> {
+    /// The replacement for `{defines}` must be a single line.
+    fn new(template: &str, common: &'a Expanded, content: &'a Expanded) -> Self {
+        let line_of = |key: &str| {
+            let index = template.find(key).unwrap_or_default();
+            template[..index].matches('\n').count() + 1
+        };
+        Self {
+            common_start: line_of("{common}"),
+            content_start: line_of("{content}") + common.code.matches('\n').count(),
+            common: &common.lines,
+            content: &content.lines,
+        }
+    }
+
+    /// Name of the code on template line `line`, and the line within it.
+    fn locate(&self, line: usize) -> (&str, usize) {
+        let located = if line >= self.content_start {
+            self.content.locate(line - self.content_start + 1)
+        } else if line >= self.common_start {
+            self.common.locate(line - self.common_start + 1)
+        } else {
+            None
+        };
+        located.unwrap_or(("template", line))
+    }
+
+    /// Replace the `shader.glsl:<line>:` locations in compiler output.
+    fn map_diagnostics(&self, message: &str) -> String {
+        const FILE: &str = "shader.glsl:";
+        let mut mapped = String::new();
+        let mut rest = message;
+        while let Some(index) = rest.find(FILE) {
+            mapped.push_str(&rest[..index]);
+            rest = &rest[index + FILE.len()..];
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            match rest[..digits].parse() {
+                Ok(line) => {
+                    let (name, line) = self.locate(line);
+                    mapped.push_str(&format!("{name}:{line}:"));
+                    rest = rest[digits..].strip_prefix(':').unwrap_or(&rest[digits..]);
+                }
+                Err(_) => mapped.push_str(FILE),
+            }
+        }
+        mapped.push_str(rest);
+        mapped
     }
 }
 
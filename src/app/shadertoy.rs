@@ -0,0 +1,576 @@
+//! Conversion from and to Shadertoy's JSON format, as returned by its API
+//! and by the usual export scripts.
+use super::project::{
+    Channel, ChannelInput, Filter, Pass, PassKind, Project, Sampler, Source, UniformValue, Wrap,
+};
+use anyhow::{anyhow, bail};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// A shader converted to a project, with what didn't carry over.
+pub struct Import {
+    pub project: Project,
+    /// Shadertoy's id of the shader, if the export has one.
+    pub id: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+/// Whether `json` looks like a Shadertoy export.
+pub fn is_shadertoy_json(json: &str) -> bool {
+    serde_json::from_str::<Value>(json).is_ok_and(|value| !shaders(&value).is_empty())
+}
+
+/// Convert every shader in a Shadertoy export. Media is looked up relative
+/// to `directory`, by its Shadertoy path (`media/a/<hash>.png`) or by its
+/// file name alone. Media that isn't there is kept at the Shadertoy path,
+/// which renders as a placeholder until the file is put there.
+pub fn import(json: &str, directory: &Path) -> crate::app::Result<Vec<Import>> {
+    let value: Value = serde_json::from_str(json)?;
+    let shaders = shaders(&value);
+    if shaders.is_empty() {
+        bail!("Not a Shadertoy export: no renderpass array found");
+    }
+    shaders
+        .into_iter()
+        .map(|shader| import_shader(shader, directory))
+        .collect()
+}
+
+/// The shader objects of an export: `{"Shader": {...}}` from the API, a bare
+/// shader object, or an array of either.
+fn shaders(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().flat_map(shaders).collect(),
+        Value::Object(object) => match object.get("Shader") {
+            Some(shader) => shaders(shader),
+            None if object.get("renderpass").is_some_and(Value::is_array) => vec![value],
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn import_shader(shader: &Value, directory: &Path) -> crate::app::Result<Import> {
+    let info = &shader["info"];
+    let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+    let mut project = Project {
+        name: text(&info["name"]),
+        author: text(&info["username"]),
+        description: text(&info["description"]),
+        ..Default::default()
+    };
+    let mut warnings = Vec::new();
+    let renderpasses = shader["renderpass"]
+        .as_array()
+        .ok_or_else(|| anyhow!("Missing renderpass array"))?;
+    for renderpass in renderpasses {
+        let code = text(&renderpass["code"]);
+        let Some(kind) = pass_kind(renderpass) else {
+            if renderpass["type"] == "common" {
+                project.common = Some(Source::new("common.glsl", code));
+            } else {
+                warnings.push(format!(
+                    "Skipped pass {:?} of unknown type {}",
+                    text(&renderpass["name"]),
+                    renderpass["type"]
+                ));
+            }
+            continue;
+        };
+        if project.pass(kind).is_some() {
+            warnings.push(format!("Skipped a second {} pass", kind.name()));
+            continue;
+        }
+        let mut pass = Pass::new(kind, Source::new(kind.file_name(), code));
+        for input in renderpass["inputs"].as_array().into_iter().flatten() {
+            let Some(index) = input["channel"].as_u64().filter(|&c| c < 4) else {
+                warnings.push(format!(
+                    "{}: skipped an input without a channel",
+                    kind.name()
+                ));
+                continue;
+            };
+            let context = format!("{} iChannel{index}", kind.name());
+            if let Some(channel) = import_input(input, directory, &context, &mut warnings) {
+                pass.channels[index as usize] = Some(channel);
+            }
+        }
+        project.passes.push(pass);
+    }
+    if project.pass(PassKind::Image).is_none() {
+        bail!("Shader {:?} has no image pass", project.name);
+    }
+    project.passes.sort_by_key(|pass| pass.kind);
+    for kind in [PassKind::CubeA, PassKind::Sound] {
+        if project.pass(kind).is_some() {
+            warnings.push(format!(
+                "{} passes are imported but can't be rendered yet",
+                kind.name()
+            ));
+        }
+    }
+    Ok(Import {
+        project,
+        id: info["id"].as_str().map(str::to_string),
+        warnings,
+    })
+}
+
+fn pass_kind(renderpass: &Value) -> Option<PassKind> {
+    match renderpass["type"].as_str()? {
+        "image" => Some(PassKind::Image),
+        "sound" => Some(PassKind::Sound),
+        "cubemap" => Some(PassKind::CubeA),
+        "buffer" => renderpass["outputs"]
+            .as_array()
+            .and_then(|outputs| outputs.first())
+            .and_then(|output| buffer_by_id(&output["id"]))
+            .or_else(|| buffer_by_name(renderpass["name"].as_str()?)),
+        _ => None,
+    }
+}
+
+/// Fixed ids of the passes whose output other passes can read, and the
+/// preview images inputs reading them point at.
+const PASS_IDS: [(PassKind, &str, &str); 5] = [
+    (PassKind::BufferA, "4dXGR8", "/media/previz/buffer00.png"),
+    (PassKind::BufferB, "XsXGR8", "/media/previz/buffer01.png"),
+    (PassKind::BufferC, "4sXGR8", "/media/previz/buffer02.png"),
+    (PassKind::BufferD, "XdfGR8", "/media/previz/buffer03.png"),
+    (PassKind::CubeA, "4dX3Rr", "/media/previz/cubemap00.png"),
+];
+const IMAGE_ID: &str = "4sf3Rr";
+const KEYBOARD_ID: &str = "4dXGRr";
+const KEYBOARD_SRC: &str = "/presets/tex00.jpg";
+
+/// Buffer passes are identified by fixed ids: strings in current exports,
+/// numbers in older ones.
+fn buffer_by_id(id: &Value) -> Option<PassKind> {
+    match id {
+        Value::String(id) => PASS_IDS
+            .iter()
+            .find(|(_, pass_id, _)| pass_id == id)
+            .map(|&(kind, ..)| kind),
+        Value::Number(id) => match id.as_u64()? {
+            257 => Some(PassKind::BufferA),
+            258 => Some(PassKind::BufferB),
+            259 => Some(PassKind::BufferC),
+            260 => Some(PassKind::BufferD),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn buffer_by_name(name: &str) -> Option<PassKind> {
+    PassKind::ALL
+        .into_iter()
+        .find(|kind| kind.name().eq_ignore_ascii_case(name))
+}
+
+/// Buffers read as inputs point at their preview images.
+fn buffer_by_src(src: &str) -> Option<PassKind> {
+    let name = src.rsplit('/').next()?;
+    PASS_IDS
+        .iter()
+        .find(|(.., previz)| previz.rsplit('/').next() == Some(name))
+        .map(|&(kind, ..)| kind)
+}
+
+fn import_input(
+    input: &Value,
+    directory: &Path,
+    context: &str,
+    warnings: &mut Vec<String>,
+) -> Option<Channel> {
+    // Current exports use `type` and `filepath`, older ones `ctype` and `src`.
+    let ctype = input["type"]
+        .as_str()
+        .or_else(|| input["ctype"].as_str())
+        .unwrap_or_default();
+    let src = input["filepath"]
+        .as_str()
+        .or_else(|| input["src"].as_str())
+        .unwrap_or_default();
+    let mut sampler = import_sampler(&input["sampler"]);
+    let input = match ctype {
+        "buffer" => {
+            let Some(pass) = buffer_by_id(&input["id"]).or_else(|| buffer_by_src(src)) else {
+                warnings.push(format!("{context}: unknown buffer {src}"));
+                return None;
+            };
+            sampler.vflip = false;
+            ChannelInput::Buffer { pass }
+        }
+        "keyboard" => ChannelInput::Keyboard,
+        "cubemap" if buffer_by_src(src) == Some(PassKind::CubeA) => ChannelInput::Buffer {
+            pass: PassKind::CubeA,
+        },
+        "texture" => ChannelInput::Texture {
+            path: find_media(src, directory, context, warnings),
+        },
+        "cubemap" => ChannelInput::Cubemap {
+            path: find_media(src, directory, context, warnings),
+        },
+        "music" | "musicstream" => ChannelInput::Audio {
+            path: find_media(src, directory, context, warnings),
+        },
+        _ => {
+            warnings.push(format!(
+                "{context}: {ctype} inputs have no offline equivalent, using a placeholder texture"
+            ));
+            ChannelInput::Texture {
+                path: media_path(src),
+            }
+        }
+    };
+    Some(Channel { input, sampler })
+}
+
+fn import_sampler(sampler: &Value) -> Sampler {
+    // Booleans are exported as strings.
+    let flag = |value: &Value| value == true || value == "true";
+    let mut result = Sampler::texture();
+    result.filter = match sampler["filter"].as_str() {
+        Some("nearest") => Filter::Nearest,
+        Some("linear") => Filter::Linear,
+        _ => Filter::Mipmap,
+    };
+    result.wrap = match sampler["wrap"].as_str() {
+        Some("clamp") => Wrap::Clamp,
+        _ => Wrap::Repeat,
+    };
+    if !sampler["vflip"].is_null() {
+        result.vflip = flag(&sampler["vflip"]);
+    }
+    result
+}
+
+/// Where media with the Shadertoy path `src` is expected next to the project.
+fn media_path(src: &str) -> PathBuf {
+    PathBuf::from(src.trim_start_matches('/'))
+}
+
+/// Path of the media `src` relative to `directory`, warning if it isn't there.
+fn find_media(src: &str, directory: &Path, context: &str, warnings: &mut Vec<String>) -> PathBuf {
+    let path = media_path(src);
+    if directory.join(&path).exists() {
+        return path;
+    }
+    if let Some(name) = path.file_name() {
+        if directory.join(name).exists() {
+            return PathBuf::from(name);
+        }
+    }
+    warnings.push(format!(
+        "{context}: {src} is not available offline, using a placeholder texture"
+    ));
+    path
+}
+
+/// A project converted to a Shadertoy export, with what didn't carry over.
+pub struct Export {
+    pub json: String,
+    pub warnings: Vec<String>,
+}
+
+/// Identifiers of GLSL 4.50 that GLSL ES 3.00 doesn't have.
+const DESKTOP_ONLY: [&str; 12] = [
+    "double",
+    "dvec2",
+    "dvec3",
+    "dvec4",
+    "dmat2",
+    "dmat3",
+    "dmat4",
+    "textureQueryLevels",
+    "textureSamples",
+    "imageLoad",
+    "imageStore",
+    "BUFFER_PASS",
+];
+
+/// Convert `project` to the JSON Shadertoy imports, with GLSL ES 3.00 code.
+pub fn export(project: &Project) -> crate::app::Result<Export> {
+    let mut warnings = Vec::new();
+    if project.resolution.is_some() {
+        warnings
+            .push("Shadertoy renders at the size of the view, the resolution is dropped".into());
+    }
+    let mut renderpass = Vec::new();
+    if let Some(common) = &project.common {
+        renderpass.push(json!({
+            "inputs": [],
+            "outputs": [],
+            "code": export_code(&common.code, project, "Common", &mut warnings),
+            "name": "Common",
+            "description": "",
+            "type": "common",
+        }));
+    }
+    for pass in &project.passes {
+        let context = pass.kind.name();
+        let inputs: Vec<Value> = pass
+            .channels
+            .iter()
+            .enumerate()
+            .filter_map(|(index, channel)| {
+                let channel = channel.as_ref()?;
+                let context = format!("{context} iChannel{index}");
+                Some(export_input(channel, index, &context, &mut warnings))
+            })
+            .collect();
+        let (kind, output) = match pass.kind {
+            PassKind::Image => ("image", Some(IMAGE_ID)),
+            PassKind::Sound => ("sound", None),
+            PassKind::CubeA => ("cubemap", output_id(pass.kind)),
+            _ => ("buffer", output_id(pass.kind)),
+        };
+        let outputs: Vec<Value> = output
+            .map(|id| json!({ "id": id, "channel": 0 }))
+            .into_iter()
+            .collect();
+        renderpass.push(json!({
+            "inputs": inputs,
+            "outputs": outputs,
+            "code": export_code(&pass.source.code, project, context, &mut warnings),
+            "name": pass.kind.name(),
+            "description": "",
+            "type": kind,
+        }));
+    }
+    let shader = json!({
+        "Shader": {
+            "ver": "0.1",
+            "info": {
+                "name": project.name,
+                "username": project.author,
+                "description": project.description,
+            },
+            "renderpass": renderpass,
+        }
+    });
+    Ok(Export {
+        json: serde_json::to_string_pretty(&shader)? + "\n",
+        warnings,
+    })
+}
+
+fn output_id(kind: PassKind) -> Option<&'static str> {
+    PASS_IDS
+        .iter()
+        .find(|&&(pass, ..)| pass == kind)
+        .map(|&(_, id, _)| id)
+}
+
+fn export_input(
+    channel: &Channel,
+    index: usize,
+    context: &str,
+    warnings: &mut Vec<String>,
+) -> Value {
+    let (kind, id, src) = match &channel.input {
+        ChannelInput::Buffer { pass } => {
+            let (_, id, previz) = PASS_IDS
+                .iter()
+                .find(|&&(kind, ..)| kind == *pass)
+                .copied()
+                .unwrap_or(PASS_IDS[0]);
+            let kind = if *pass == PassKind::CubeA {
+                "cubemap"
+            } else {
+                "buffer"
+            };
+            (kind, Value::from(id), previz.to_string())
+        }
+        ChannelInput::Keyboard => ("keyboard", Value::from(KEYBOARD_ID), KEYBOARD_SRC.into()),
+        ChannelInput::Texture { path } => (
+            "texture",
+            Value::Null,
+            export_media(path, context, warnings),
+        ),
+        ChannelInput::Cubemap { path } => (
+            "cubemap",
+            Value::Null,
+            export_media(path, context, warnings),
+        ),
+        ChannelInput::Audio { path } => {
+            ("music", Value::Null, export_media(path, context, warnings))
+        }
+    };
+    let flag = |value: bool| if value { "true" } else { "false" };
+    let sampler = &channel.sampler;
+    json!({
+        "id": id,
+        "filepath": src,
+        "type": kind,
+        "channel": index,
+        "sampler": {
+            "filter": sampler.filter.name().to_lowercase(),
+            "wrap": sampler.wrap.name().to_lowercase(),
+            "vflip": flag(sampler.vflip),
+            "srgb": "false",
+            "internal": "byte",
+        },
+        "published": 1,
+    })
+}
+
+/// Shadertoy only serves its own media. Files imported from it keep their
+/// `media/a/...` path; anything else has to be replaced after publishing.
+fn export_media(path: &Path, context: &str, warnings: &mut Vec<String>) -> String {
+    let src = path.to_string_lossy().replace('\\', "/");
+    if src.starts_with("media/a/") {
+        return format!("/{src}");
+    }
+    warnings.push(format!(
+        "{context}: {} is not Shadertoy media, pick a replacement from its library after publishing",
+        path.display()
+    ));
+    format!(
+        "/media/a/{}",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    )
+}
+
+/// The code of a pass as Shadertoy expects it: without the declarations our
+/// template or Shadertoy itself provides, and with custom uniforms turned into
+/// constants holding their current values.
+fn export_code(code: &str, project: &Project, context: &str, warnings: &mut Vec<String>) -> String {
+    let mut lines = Vec::new();
+    let mut in_uniform_block = false;
+    for line in code.lines() {
+        let trimmed = line.trim();
+        if in_uniform_block {
+            in_uniform_block = !trimmed.starts_with('}');
+            continue;
+        }
+        if trimmed.contains("uniform ") && trimmed.ends_with('{') {
+            in_uniform_block = true;
+            continue;
+        }
+        if is_scaffolding(trimmed) {
+            continue;
+        }
+        if let Some(declaration) = trimmed.strip_prefix("uniform ") {
+            match uniform_constant(declaration, project) {
+                Some(constant) => {
+                    warnings.push(format!(
+                        "{context}: uniform {} has no Shadertoy equivalent, exported as a constant",
+                        constant.name
+                    ));
+                    lines.push(constant.line);
+                }
+                None => {
+                    warnings.push(format!(
+                        "{context}: uniform declaration {trimmed:?} has no Shadertoy equivalent"
+                    ));
+                    lines.push(line.to_string());
+                }
+            }
+            continue;
+        }
+        lines.push(line.to_string());
+    }
+    if code
+        .lines()
+        .any(|line| line.trim_start().starts_with("#include"))
+    {
+        warnings.push(format!(
+            "{context}: Shadertoy has no #include, paste the included code in instead"
+        ));
+    }
+    for identifier in DESKTOP_ONLY {
+        if contains_identifier(code, identifier) {
+            warnings.push(format!(
+                "{context}: {identifier} is not available in GLSL ES 3.00"
+            ));
+        }
+    }
+    let mut code = lines.join("\n");
+    code.push('\n');
+    code
+}
+
+/// Lines that only make sense inside our template, or that Shadertoy adds
+/// itself and rejects when repeated.
+fn is_scaffolding(line: &str) -> bool {
+    line.starts_with("#version")
+        || line.starts_with("#extension")
+        || (line.starts_with("precision ") && line.ends_with(';'))
+        || (line.starts_with("layout") && line.contains("uniform"))
+        || (line.starts_with("layout") && line.contains(" out "))
+        || (line.starts_with("#define iChannel") && line.contains("sampler2D("))
+}
+
+struct Constant {
+    name: String,
+    line: String,
+}
+
+/// `const <type> <name> = <value>;` for the declaration `<type> <name>;`,
+/// with the project's value or zero. Trailing comments, where annotations
+/// live, are kept.
+fn uniform_constant(declaration: &str, project: &Project) -> Option<Constant> {
+    let (statement, comment) = match declaration.split_once(';') {
+        Some((statement, rest)) => (statement, rest.trim()),
+        None => return None,
+    };
+    let statement = statement.split('=').next()?.trim();
+    let mut words = statement.split_whitespace();
+    let (ty, name) = (words.next()?, words.next()?);
+    if words.next().is_some() || name.contains(['[', ',']) {
+        return None;
+    }
+    let value = glsl_literal(ty, project.uniforms.get(name))?;
+    let mut line = format!("const {ty} {name} = {value};");
+    if !comment.is_empty() {
+        line = format!("{line} {comment}");
+    }
+    Some(Constant {
+        name: name.to_string(),
+        line,
+    })
+}
+
+/// `value` as a GLSL literal of type `ty`. GLSL has no literals for
+/// infinities and NaN, so those have none.
+fn glsl_literal(ty: &str, value: Option<&UniformValue>) -> Option<String> {
+    let float = |f: f32| {
+        if !f.is_finite() {
+            return None;
+        }
+        let text = f.to_string();
+        Some(if text.contains('.') {
+            text
+        } else {
+            format!("{text}.0")
+        })
+    };
+    let components = match ty {
+        "vec2" => 2,
+        "vec3" => 3,
+        "vec4" => 4,
+        _ => 1,
+    };
+    Some(match (ty, value) {
+        ("bool", Some(UniformValue::Bool(b))) => b.to_string(),
+        ("bool", None) => "false".into(),
+        ("int", Some(UniformValue::Int(i))) => i.to_string(),
+        ("int", None) => "0".into(),
+        ("float", Some(UniformValue::Float(f))) => float(*f)?,
+        ("float", Some(UniformValue::Int(i))) => float(*i as f32)?,
+        ("float", None) => "0.0".into(),
+        ("vec2" | "vec3" | "vec4", Some(UniformValue::Vector(v))) if v.len() == components => {
+            let values: Vec<String> = v.iter().map(|&f| float(f)).collect::<Option<_>>()?;
+            format!("{ty}({})", values.join(", "))
+        }
+        ("vec2" | "vec3" | "vec4", None) => format!("{ty}(0.0)"),
+        _ => return None,
+    })
+}
+
+fn contains_identifier(code: &str, identifier: &str) -> bool {
+    code.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|word| word == identifier)
+}
@@ -0,0 +1,137 @@
+//! Share links: the open shader, and the settings of a project, compressed
+//! into the URL fragment of the web version, so sharing needs no server.
+//!
+//! A fragment is `shader=` followed by the deflated JSON of a [`Shared`] in
+//! URL-safe base64.
+use super::project::Project;
+use anyhow::{anyhow, bail};
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+
+/// Start of the fragments this module reads and writes.
+pub const PREFIX: &str = "shader=";
+/// Bumped when links can't be read by older code or vice versa.
+const VERSION: u32 = 1;
+/// Links longer than this may be cut short by browsers and chat apps.
+pub const MAX_LINK_LENGTH: usize = 8000;
+/// Decompressed size above which a fragment is rejected rather than inflated.
+const MAX_SIZE: usize = 1 << 20;
+
+const BASE64: base64::engine::GeneralPurpose = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+/// What a link holds.
+#[derive(Serialize, Deserialize)]
+struct Shared {
+    version: u32,
+    file_name: String,
+    /// The project's manifest, left out for a plain shader.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    manifest: Option<serde_json::Value>,
+    /// Code of the sources, in [`Project::sources`] order.
+    code: Vec<String>,
+}
+
+/// The fragment, without `#`, that opens `project` as `file_name`.
+pub fn encode(file_name: &str, project: &Project) -> crate::app::Result<String> {
+    let manifest = if project.is_single_file() {
+        None
+    } else {
+        Some(serde_json::from_str(&project.to_manifest()?)?)
+    };
+    let shared = Shared {
+        version: VERSION,
+        file_name: file_name.to_string(),
+        manifest,
+        code: project
+            .sources()
+            .map(|source| source.code.clone())
+            .collect(),
+    };
+    let json = serde_json::to_vec(&shared)?;
+    let compressed = miniz_oxide::deflate::compress_to_vec(&json, 9);
+    Ok(format!("{PREFIX}{}", BASE64.encode(compressed)))
+}
+
+/// The file name and project a fragment, with or without `#`, opens.
+/// `Ok(None)` if it isn't a share link, an error if it is one that can't be
+/// read.
+pub fn decode(fragment: &str) -> crate::app::Result<Option<(String, Project)>> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let Some(data) = fragment.strip_prefix(PREFIX) else {
+        return Ok(None);
+    };
+    let compressed = BASE64.decode(data.trim())?;
+    let json = miniz_oxide::inflate::decompress_to_vec_with_limit(&compressed, MAX_SIZE)
+        .map_err(|e| anyhow!("Invalid share link data: {e}"))?;
+    let shared: Shared = serde_json::from_slice(&json)?;
+    if shared.version != VERSION {
+        bail!(
+            "Share link version {} isn't supported (this one reads {VERSION})",
+            shared.version
+        );
+    }
+    let mut project = match shared.manifest {
+        Some(manifest) => Project::from_manifest(&manifest.to_string())?,
+        None => Project::single(&shared.file_name, ""),
+    };
+    let count = project.sources().count();
+    if shared.code.len() != count {
+        bail!(
+            "Share link has code for {} sources, the project has {count}",
+            shared.code.len()
+        );
+    }
+    for (source, code) in project.sources_mut().zip(shared.code) {
+        source.code = code;
+    }
+    Ok(Some((shared.file_name, project)))
+}
+
+/// The page's URL with `fragment` in place of its own.
+#[cfg(target_arch = "wasm32")]
+pub fn link(fragment: &str) -> Option<String> {
+    let location = web_sys::window()?.location();
+    let href = location.href().ok()?;
+    let base = href.split('#').next().unwrap_or(&href);
+    Some(format!("{base}#{fragment}"))
+}
+
+/// The project shared in the page's URL, if any, which is then taken out of
+/// the address bar so reloading brings back the edited state instead.
+#[cfg(target_arch = "wasm32")]
+pub fn take_from_location() -> Option<(String, Project)> {
+    let window = web_sys::window()?;
+    let hash = window.location().hash().ok()?;
+    match decode(&hash) {
+        Ok(Some(shared)) => {
+            let url = link("").map(|link| link.trim_end_matches('#').to_string());
+            if let Ok(history) = window.history() {
+                if let Err(e) = history.replace_state_with_url(
+                    &eframe::wasm_bindgen::JsValue::NULL,
+                    "",
+                    url.as_deref(),
+                ) {
+                    log::warn!("Error clearing the share link from the address bar: {e:?}");
+                }
+            }
+            Some(shared)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            log::error!("Error opening the share link: {e:#}");
+            None
+        }
+    }
+}
+
+/// Files the project reads that a link can't carry, by their paths.
+pub fn missing_files(project: &Project) -> Vec<String> {
+    project
+        .passes
+        .iter()
+        .flat_map(|pass| pass.channels.iter().flatten())
+        .filter_map(|channel| channel.input.path())
+        .filter(|path| super::examples::texture(path, false).is_none())
+        .map(|path| path.display().to_string())
+        .collect()
+}
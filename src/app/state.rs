@@ -0,0 +1,118 @@
+//! What the app keeps in `eframe::Storage` between runs.
+use super::project::Project;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Storage key of [`State`].
+pub const KEY: &str = "shadertoy_rs";
+/// Bumped when the stored state can't be read by older code or vice versa.
+/// State of another version is dropped rather than misread.
+const VERSION: u32 = 1;
+
+/// The editor as it was when the app was closed.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    version: u32,
+    pub show_logger: bool,
+    pub shader_editor: bool,
+    pub show_history: bool,
+    /// Whether saving formats the code first.
+    pub format_on_save: bool,
+    /// Name and location of the open file.
+    pub file_name: String,
+    pub file_path: Option<PathBuf>,
+    /// Whether the editor had changes that weren't saved to the file.
+    pub dirty: bool,
+    /// The project, without its code; see `code`.
+    pub project: Project,
+    /// Code of the project's sources, in [`Project::sources`] order, since
+    /// the manifest format leaves it to the source files.
+    pub code: Vec<String>,
+    pub recent_files: Vec<PathBuf>,
+    /// Directory the gallery shows after the bundled examples.
+    pub gallery_directory: Option<PathBuf>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            show_logger: true,
+            shader_editor: true,
+            show_history: false,
+            format_on_save: false,
+            file_name: String::new(),
+            file_path: None,
+            dirty: false,
+            project: Project::default(),
+            code: Vec::new(),
+            recent_files: Vec::new(),
+            gallery_directory: None,
+        }
+    }
+}
+
+impl State {
+    pub fn new(project: &Project) -> Self {
+        Self {
+            project: project.clone(),
+            code: project
+                .sources()
+                .map(|source| source.code.clone())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Read the state from `storage`. `Ok(None)` if there is none yet, an
+    /// error if it can't be used.
+    pub fn load(storage: &dyn eframe::Storage) -> crate::app::Result<Option<Self>> {
+        storage
+            .get_string(KEY)
+            .map(|json| Self::parse(&json))
+            .transpose()
+    }
+
+    /// Stored as JSON, like project manifests, rather than with
+    /// `eframe::set_value`, whose RON encoding can't read back the project's
+    /// tagged enums.
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        match self.to_json() {
+            Ok(json) => storage.set_string(KEY, json),
+            Err(e) => log::error!("Error saving the editor state: {e}"),
+        }
+    }
+
+    /// Read state written by [`State::to_json`], checking that it can be used.
+    pub fn parse(json: &str) -> crate::app::Result<Self> {
+        let state: Self = serde_json::from_str(json)?;
+        if state.version != VERSION {
+            anyhow::bail!("Stored state has version {}", state.version);
+        }
+        if state
+            .project
+            .pass(super::project::PassKind::Image)
+            .is_none()
+        {
+            anyhow::bail!("Stored project has no image pass");
+        }
+        if state.code.len() != state.project.sources().count() {
+            anyhow::bail!("Stored code doesn't match the project's sources");
+        }
+        Ok(state)
+    }
+
+    pub fn to_json(&self) -> crate::app::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// The stored project with its code put back.
+    pub fn project(&self) -> Project {
+        let mut project = self.project.clone();
+        for (source, code) in project.sources_mut().zip(&self.code) {
+            source.code = code.clone();
+        }
+        project
+    }
+}
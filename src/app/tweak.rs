@@ -0,0 +1,276 @@
+//! Alt-dragging a number literal in the editor scrubs its value live. While
+//! the drag lasts the literal reads a uniform of the template instead, so the
+//! shader isn't recompiled for every value, and the final value is written
+//! back into the code on release.
+use super::highlight::{self, Token};
+use super::project::Project;
+use eframe::egui;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Member of the template's uniform block that stands in for the literal.
+pub const UNIFORM: &str = "_tweak";
+/// Points dragged per step of the literal's last digit.
+const POINTS_PER_STEP: f32 = 8.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// A float with this many digits after the point.
+    Float {
+        decimals: usize,
+    },
+    Int,
+    Uint,
+}
+
+/// A decimal number literal in the code, with the minus sign in front of it.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Literal {
+    pub range: Range<usize>,
+    pub kind: Kind,
+    pub value: f64,
+    /// Type suffix kept when the value is rewritten, like the `f` of `1.0f`.
+    suffix: String,
+}
+
+impl Literal {
+    /// The literal of `code` that the byte `byte` is in or right after.
+    /// Hexadecimal, octal and exponent forms can't be tweaked.
+    pub fn at(code: &str, byte: usize) -> Option<Self> {
+        let (mut range, _) = highlight::tokens(code, &[])
+            .into_iter()
+            .find(|(range, token)| {
+                *token == Token::Number && range.start <= byte && byte <= range.end
+            })?;
+        let text = &code[range.clone()];
+        let digits = text.trim_end_matches(['f', 'F', 'u', 'U']);
+        let suffix = text[digits.len()..].to_string();
+        let kind = match (digits.split_once('.'), suffix.as_str()) {
+            (Some((_, decimals)), "" | "f" | "F") => Kind::Float {
+                decimals: decimals.len(),
+            },
+            (None, "") if digits == "0" || !digits.starts_with('0') => Kind::Int,
+            (None, "u" | "U") if digits == "0" || !digits.starts_with('0') => Kind::Uint,
+            _ => return None,
+        };
+        if !digits.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+            return None;
+        }
+        let mut value: f64 = digits.parse().ok()?;
+        // A minus sign applying to the literal alone, rather than subtracting it.
+        if let Some(before) = code[..range.start].strip_suffix('-') {
+            let operand = before
+                .trim_end()
+                .ends_with(|c: char| c.is_ascii_alphanumeric() || "_.)]".contains(c));
+            if !operand {
+                range.start -= 1;
+                value = -value;
+            }
+        }
+        Some(Self {
+            range,
+            kind,
+            value,
+            suffix,
+        })
+    }
+
+    /// Change of the value per step: one unit of the literal's last digit.
+    pub fn step(&self) -> f64 {
+        match self.kind {
+            Kind::Float { decimals } => 10f64.powi(-(decimals as i32)),
+            Kind::Int | Kind::Uint => 1.0,
+        }
+    }
+
+    /// GLSL for `value` in place of the literal, written like it.
+    pub fn format(&self, value: f64, before: &str) -> String {
+        let mut text = match self.kind {
+            Kind::Float { decimals } => {
+                let text = format!("{value:.decimals$}");
+                // No negative zero.
+                let text = match text.strip_prefix('-') {
+                    Some(unsigned) if unsigned.bytes().all(|b| b == b'0' || b == b'.') => {
+                        unsigned.to_string()
+                    }
+                    _ => text,
+                };
+                if decimals == 0 {
+                    text + "."
+                } else {
+                    text
+                }
+            }
+            Kind::Int => format!("{}", value.round() as i64),
+            Kind::Uint => format!("{}", value.round().max(0.0) as u64),
+        };
+        text.push_str(&self.suffix);
+        if text.starts_with('-') && before.trim_end().ends_with(['-', '+']) {
+            text = format!("({text})");
+        }
+        text
+    }
+
+    /// The expression reading the uniform instead.
+    pub fn expression(&self) -> String {
+        match self.kind {
+            Kind::Float { .. } => UNIFORM.to_string(),
+            Kind::Int => format!("int({UNIFORM})"),
+            Kind::Uint => format!("uint({UNIFORM})"),
+        }
+    }
+
+    /// Write `value` over the literal in `code`, and follow it.
+    pub fn write(&mut self, code: &mut String, value: f64) {
+        let text = self.format(value, &code[..self.range.start]);
+        code.replace_range(self.range.clone(), &text);
+        self.range.end = self.range.start + text.len();
+    }
+}
+
+/// A literal being dragged.
+struct Drag {
+    /// Index of its source in [`Project::sources`] order.
+    source: usize,
+    literal: Literal,
+    /// The literal as it's written, to notice the code changing under it.
+    text: String,
+    /// Value the drag started from.
+    start: f64,
+    value: f64,
+    /// Where the pointer went down.
+    start_x: f32,
+    /// Whether the shader reads the value from the uniform. Literals in
+    /// constant expressions can't, and are rewritten as they change instead.
+    promoted: bool,
+}
+
+/// Alt-dragging in one editor.
+#[derive(Default)]
+pub struct Tweaker {
+    drag: Option<Drag>,
+    /// The editor's text as last laid out, where it was and the part of it
+    /// that was visible, to find the literal under the pointer.
+    layout: Option<(Arc<egui::Galley>, egui::Pos2, egui::Rect)>,
+}
+
+impl Tweaker {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    /// Start dragging the literal under the pointer when the primary button
+    /// goes down with Alt held. `code` is the editor's text, the source at
+    /// `source` in [`Project::sources`] order. Call before showing the editor;
+    /// returns whether a drag started.
+    pub fn begin(&mut self, ui: &egui::Ui, code: &str, source: usize) -> bool {
+        let pressed = ui.input(|i| {
+            let pressed = i.modifiers.alt && i.pointer.primary_pressed();
+            i.pointer.interact_pos().filter(|_| pressed)
+        });
+        let (Some(pointer), Some((galley, position, clip))) = (pressed, &self.layout) else {
+            return false;
+        };
+        if !clip.contains(pointer) {
+            return false;
+        }
+        let index = galley.cursor_from_pos(pointer - *position).ccursor.index;
+        let byte = code
+            .char_indices()
+            .nth(index)
+            .map_or(code.len(), |(i, _)| i);
+        let Some(literal) = Literal::at(code, byte) else {
+            return false;
+        };
+        self.drag = Some(Drag {
+            source,
+            text: code[literal.range.clone()].to_string(),
+            start: literal.value,
+            value: literal.value,
+            literal,
+            start_x: pointer.x,
+            promoted: true,
+        });
+        true
+    }
+
+    /// Remember where the editor laid out its text.
+    pub fn laid_out(&mut self, output: &egui::text_edit::TextEditOutput) {
+        self.layout = Some((
+            output.galley.clone(),
+            output.galley_pos,
+            output.text_clip_rect,
+        ));
+    }
+
+    /// Follow the pointer, showing the value next to it. The value is written
+    /// into `project` when the drag ends, and as it changes while the literal
+    /// isn't promoted. Returns whether `project` needs to be recompiled.
+    pub fn drag(&mut self, ctx: &egui::Context, project: &mut Project) -> bool {
+        let Some(drag) = &mut self.drag else {
+            return false;
+        };
+        let (down, pointer) = ctx.input(|i| (i.pointer.primary_down(), i.pointer.latest_pos()));
+        let steps = pointer.map_or(0.0, |p| ((p.x - drag.start_x) / POINTS_PER_STEP).round());
+        let mut value = drag.start + steps as f64 * drag.literal.step();
+        if drag.literal.kind == Kind::Uint {
+            value = value.max(0.0);
+        }
+        let changed = value != drag.value;
+        drag.value = value;
+        let source = project.sources_mut().nth(drag.source);
+        let Some(source) =
+            source.filter(|s| s.code.get(drag.literal.range.clone()) == Some(&drag.text))
+        else {
+            // Another project was opened, or a revision restored.
+            self.drag = None;
+            return true;
+        };
+        if !down {
+            if drag.value != drag.literal.value {
+                drag.literal.write(&mut source.code, drag.value);
+            }
+            self.drag = None;
+            return true;
+        }
+        let text = drag.literal.format(value, "");
+        egui::show_tooltip_at_pointer(
+            ctx,
+            egui::LayerId::background(),
+            egui::Id::new("tweak_value"),
+            |ui| ui.monospace(text),
+        );
+        if !drag.promoted && changed {
+            drag.literal.write(&mut source.code, value);
+            drag.literal.value = value;
+            drag.text = source.code[drag.literal.range.clone()].to_string();
+            return true;
+        }
+        false
+    }
+
+    /// `project` with the dragged literal reading the uniform, while that's
+    /// how it's dragged.
+    pub fn promoted(&self, project: &Project) -> Option<Project> {
+        let drag = self.drag.as_ref().filter(|drag| drag.promoted)?;
+        let mut project = project.clone();
+        let source = project.sources_mut().nth(drag.source)?;
+        source
+            .code
+            .replace_range(drag.literal.range.clone(), &drag.literal.expression());
+        Some(project)
+    }
+
+    /// Rewrite the literal as it changes instead, since the shader can't read
+    /// it from the uniform.
+    pub fn demote(&mut self) {
+        if let Some(drag) = &mut self.drag {
+            drag.promoted = false;
+        }
+    }
+
+    /// The value for the uniform.
+    pub fn value(&self) -> Option<f32> {
+        self.drag.as_ref().map(|drag| drag.value as f32)
+    }
+}
@@ -0,0 +1,269 @@
+//! Uniforms a shader declares itself, like `uniform float speed;`. Vulkan
+//! GLSL only takes them inside a block, so the declarations are taken out of
+//! the code and gathered into the template's `UserUniforms` block, which is
+//! filled from the values kept in the project.
+use super::project::{Project, UniformValue};
+use anyhow::bail;
+use eframe::egui;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UniformType {
+    Bool,
+    Int,
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+}
+
+impl UniformType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "bool" => Self::Bool,
+            "int" => Self::Int,
+            "float" => Self::Float,
+            "vec2" => Self::Vec2,
+            "vec3" => Self::Vec3,
+            "vec4" => Self::Vec4,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bool => "bool",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Vec2 => "vec2",
+            Self::Vec3 => "vec3",
+            Self::Vec4 => "vec4",
+        }
+    }
+
+    /// Number of components.
+    fn components(self) -> usize {
+        match self {
+            Self::Bool | Self::Int | Self::Float => 1,
+            Self::Vec2 => 2,
+            Self::Vec3 => 3,
+            Self::Vec4 => 4,
+        }
+    }
+
+    /// Alignment in the block, in bytes, by the std140 rules.
+    fn alignment(self) -> usize {
+        match self.components() {
+            1 => 4,
+            2 => 8,
+            _ => 16,
+        }
+    }
+
+    /// `value` as this type, converting or padding with zeros if it was
+    /// stored as another one.
+    pub fn convert(self, value: Option<&UniformValue>) -> UniformValue {
+        let mut components = match value {
+            Some(UniformValue::Bool(value)) => vec![f32::from(u8::from(*value))],
+            Some(UniformValue::Int(value)) => vec![*value as f32],
+            Some(UniformValue::Float(value)) => vec![*value],
+            Some(UniformValue::Vector(values)) => values.clone(),
+            None => Vec::new(),
+        };
+        components.resize(self.components(), 0.0);
+        match self {
+            Self::Bool => UniformValue::Bool(components[0] != 0.0),
+            Self::Int => UniformValue::Int(components[0].round() as i32),
+            Self::Float => UniformValue::Float(components[0]),
+            Self::Vec2 | Self::Vec3 | Self::Vec4 => UniformValue::Vector(components),
+        }
+    }
+}
+
+/// A `uniform <type> <name>;` line of the code.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Declaration {
+    pub name: String,
+    pub ty: UniformType,
+}
+
+impl Declaration {
+    /// The declaration on `line`, if the line holds nothing else but a
+    /// comment.
+    fn parse(line: &str) -> Option<Self> {
+        let code = line.split("//").next().unwrap_or_default().trim();
+        let statement = code.strip_prefix("uniform")?.strip_suffix(';')?;
+        let mut words = statement.split_whitespace();
+        let ty = UniformType::parse(words.next()?)?;
+        let name = words.next()?;
+        let is_name = !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        (is_name && words.next().is_none()).then(|| Self {
+            name: name.to_string(),
+            ty,
+        })
+    }
+
+    /// Name of the block member holding a `bool`, which can't be in a
+    /// uniform block and is kept as a `uint`.
+    fn member(&self) -> String {
+        match self.ty {
+            UniformType::Bool => format!("_{}", self.name),
+            _ => self.name.clone(),
+        }
+    }
+}
+
+/// The uniforms declared in `code`, in order.
+pub fn declarations(code: &str) -> Vec<Declaration> {
+    code.lines().filter_map(Declaration::parse).collect()
+}
+
+/// The uniforms declared in any of the project's sources, each once.
+pub fn project_declarations(project: &Project) -> Vec<Declaration> {
+    let mut declared: Vec<Declaration> = Vec::new();
+    for declaration in project
+        .sources()
+        .flat_map(|source| declarations(&source.code))
+    {
+        if !declared.iter().any(|d| d.name == declaration.name) {
+            declared.push(declaration);
+        }
+    }
+    declared
+}
+
+/// `code` with its uniform declarations taken out, keeping the line count so
+/// compiler messages still point at the right lines. A `bool` becomes a
+/// macro reading its `uint` member.
+pub fn strip(code: &str) -> String {
+    code.split_inclusive('\n')
+        .map(|line| match Declaration::parse(line) {
+            Some(declaration) => {
+                let end = &line[line.trim_end_matches(['\r', '\n']).len()..];
+                match declaration.ty {
+                    UniformType::Bool => format!(
+                        "#define {} ({} != 0u){end}",
+                        declaration.name,
+                        declaration.member()
+                    ),
+                    _ => end.to_string(),
+                }
+            }
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+/// Where each declared uniform goes in the template's `UserUniforms` block.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Layout {
+    /// Declarations with their byte offset.
+    members: Vec<(Declaration, usize)>,
+    size: usize,
+}
+
+impl Layout {
+    /// Lay out `declarations`, which may name the same uniform more than
+    /// once, as long as it's with the same type.
+    pub fn new(declarations: &[Declaration]) -> crate::app::Result<Self> {
+        let mut layout = Self::default();
+        for declaration in declarations {
+            if let Some((declared, _)) = layout
+                .members
+                .iter()
+                .find(|(declared, _)| declared.name == declaration.name)
+            {
+                if declared.ty != declaration.ty {
+                    bail!(
+                        "Uniform {} is declared both as {} and {}",
+                        declaration.name,
+                        declared.ty.name(),
+                        declaration.ty.name()
+                    );
+                }
+                continue;
+            }
+            let offset = layout.size.next_multiple_of(declaration.ty.alignment());
+            layout.size = offset + 4 * declaration.ty.components();
+            layout.members.push((declaration.clone(), offset));
+        }
+        Ok(layout)
+    }
+
+    /// The members of the block, on a single line. Blocks can't be empty.
+    pub fn block(&self) -> String {
+        if self.members.is_empty() {
+            return "float _none;".to_string();
+        }
+        let members: Vec<String> = self
+            .members
+            .iter()
+            .map(|(declaration, _)| match declaration.ty {
+                UniformType::Bool => format!("uint {};", declaration.member()),
+                ty => format!("{} {};", ty.name(), declaration.member()),
+            })
+            .collect();
+        members.join(" ")
+    }
+
+    /// Size of the block's buffer in bytes, rounded up as std140 requires.
+    pub fn size(&self) -> usize {
+        self.size.next_multiple_of(16).max(16)
+    }
+
+    /// The contents of the block's buffer, with `values` by name. Uniforms
+    /// without a value are zero.
+    pub fn pack(&self, values: &BTreeMap<String, UniformValue>) -> Vec<u8> {
+        let mut bytes = vec![0; self.size()];
+        for (declaration, offset) in &self.members {
+            let value = declaration.ty.convert(values.get(&declaration.name));
+            let words: Vec<[u8; 4]> = match value {
+                UniformValue::Bool(value) => vec![u32::from(value).to_ne_bytes()],
+                UniformValue::Int(value) => vec![value.to_ne_bytes()],
+                UniformValue::Float(value) => vec![value.to_ne_bytes()],
+                UniformValue::Vector(values) => values.iter().map(|v| v.to_ne_bytes()).collect(),
+            };
+            for (index, word) in words.iter().enumerate() {
+                let at = offset + 4 * index;
+                bytes[at..at + 4].copy_from_slice(word);
+            }
+        }
+        bytes
+    }
+}
+
+/// Edit the values of the `declared` uniforms. A value is only added to
+/// `values` once it's changed. Returns whether any was.
+pub fn ui(
+    ui: &mut egui::Ui,
+    declared: &[Declaration],
+    values: &mut BTreeMap<String, UniformValue>,
+) -> bool {
+    let mut changed = false;
+    egui::Grid::new("uniform_grid").show(ui, |ui| {
+        for declaration in declared {
+            ui.label(&declaration.name)
+                .on_hover_text(declaration.ty.name());
+            let mut value = declaration.ty.convert(values.get(&declaration.name));
+            let edited = ui
+                .horizontal(|ui| match &mut value {
+                    UniformValue::Bool(value) => ui.checkbox(value, "").changed(),
+                    UniformValue::Int(value) => ui.add(egui::DragValue::new(value)).changed(),
+                    UniformValue::Float(value) => {
+                        ui.add(egui::DragValue::new(value).speed(0.01)).changed()
+                    }
+                    UniformValue::Vector(values) => values.iter_mut().fold(false, |edited, v| {
+                        ui.add(egui::DragValue::new(v).speed(0.01)).changed() || edited
+                    }),
+                })
+                .inner;
+            if edited {
+                values.insert(declaration.name.clone(), value);
+                changed = true;
+            }
+            ui.end_row();
+        }
+    });
+    changed
+}
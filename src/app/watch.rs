@@ -0,0 +1,105 @@
+use notify::Watcher;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Watches a changing set of files for modifications.
+///
+/// The parent directories are watched rather than the files themselves, so
+/// editors that save by writing a new file and renaming it over the old one
+/// are still noticed.
+pub struct FileWatcher {
+    watcher: notify::RecommendedWatcher,
+    rx: std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+    files: HashSet<PathBuf>,
+    /// Watched directories and how many watched files live in each.
+    directories: HashMap<PathBuf, usize>,
+}
+
+impl FileWatcher {
+    pub fn new() -> crate::app::Result<Self> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watcher = notify::RecommendedWatcher::new(tx, notify::Config::default())?;
+        Ok(Self {
+            watcher,
+            rx,
+            files: HashSet::new(),
+            directories: HashMap::new(),
+        })
+    }
+
+    pub fn watch(&mut self, path: &Path) -> crate::app::Result<()> {
+        let path = normalize(path)?;
+        if self.files.contains(&path) {
+            return Ok(());
+        }
+        if let Some(directory) = path.parent() {
+            if !self.directories.contains_key(directory) {
+                self.watcher
+                    .watch(directory, notify::RecursiveMode::NonRecursive)?;
+            }
+            *self.directories.entry(directory.to_path_buf()).or_default() += 1;
+        }
+        self.files.insert(path);
+        Ok(())
+    }
+
+    pub fn unwatch(&mut self, path: &Path) {
+        let Ok(path) = normalize(path) else {
+            return;
+        };
+        if !self.files.remove(&path) {
+            return;
+        }
+        if let Some(directory) = path.parent() {
+            if let Some(count) = self.directories.get_mut(directory) {
+                *count -= 1;
+                if *count == 0 {
+                    self.directories.remove(directory);
+                    let _ = self.watcher.unwatch(directory);
+                }
+            }
+        }
+    }
+
+    /// The directories watched for the files, each once.
+    pub fn directories(&self) -> Vec<&Path> {
+        let mut directories: Vec<&Path> = self.directories.keys().map(PathBuf::as_path).collect();
+        directories.sort();
+        directories
+    }
+
+    /// Drain pending events and return the watched files that changed.
+    pub fn changed_files(&mut self) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        while let Ok(event) = self.rx.try_recv() {
+            let Ok(event) = event else {
+                continue;
+            };
+            if !matches!(
+                event.kind,
+                notify::EventKind::Create(_) | notify::EventKind::Modify(_)
+            ) {
+                continue;
+            }
+            for path in event.paths {
+                if self.files.contains(&path) && !changed.contains(&path) {
+                    changed.push(path);
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// Canonicalize the directory part of `path`, which must exist, and keep the
+/// file name as is, since the file may be replaced while it's watched.
+pub fn normalize(path: &Path) -> crate::app::Result<PathBuf> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file", path.display()))?;
+    Ok(directory.canonicalize()?.join(file_name))
+}
@@ -0,0 +1,272 @@
+//! Command line entry points that run without opening a window.
+use crate::export::{ExportFormat, ExportSettings, RenderJob};
+use crate::passes::{MediaCache, Program};
+use crate::project::{self, Project};
+use crate::render::headless_device;
+use crate::shadertoy;
+use anyhow::{anyhow, bail, Context};
+use std::path::{Path, PathBuf};
+
+const RENDER_USAGE: &str = "\
+Usage: shadertoy_rs render <shader.glsl | project.json> [options]
+
+Render a shader or project to image files without opening a window.
+
+Options:
+  -o, --output <path>    Output directory for image sequences, file otherwise
+                         [default: render]
+  -f, --format <format>  png, exr, hdr, y4m, gif or apng [default: png]
+  -W, --width <pixels>   [default: project resolution or 640]
+  -H, --height <pixels>  [default: project resolution or 360]
+  -n, --frames <count>   Number of frames to render [default: 1]
+      --fps <rate>       Frame rate of the clock [default: 60]
+  -t, --time <seconds>   Time of the first frame [default: 0]
+      --scale <factor>   Downscale factor applied after rendering [default: 1]
+      --software         Only use a software adapter (lavapipe, llvmpipe, ...)
+  -h, --help             Print this help";
+
+const IMPORT_USAGE: &str = "\
+Usage: shadertoy_rs import <export.json>... [options]
+
+Convert Shadertoy JSON exports to projects, one directory per shader.
+Media is looked up next to each export; missing media is reported and
+rendered as a placeholder until it's copied into the project.
+
+Options:
+  -o, --output <path>    Directory to create the projects in [default: .]
+  -h, --help             Print this help";
+
+/// Run `shadertoy_rs <args>` if `args` names a command line subcommand.
+/// Returns `None` when the GUI should be started instead.
+pub fn run(args: &[String]) -> Option<anyhow::Result<()>> {
+    match args.first().map(String::as_str) {
+        Some("render") => Some(render(&args[1..])),
+        Some("import") => Some(import(&args[1..])),
+        _ => None,
+    }
+}
+
+/// What `shadertoy_rs render` was asked for.
+#[derive(Debug)]
+pub struct RenderArgs {
+    pub shader: PathBuf,
+    pub settings: ExportSettings,
+    /// Whether `--width` or `--height` was passed, overriding the project.
+    pub size_given: bool,
+    pub frames: u32,
+    pub software: bool,
+}
+
+/// Parse the arguments after `render`. Returns `None` when help was asked
+/// for.
+pub fn parse_render_args(args: &[String]) -> anyhow::Result<Option<RenderArgs>> {
+    let mut shader = None;
+    let mut settings = ExportSettings {
+        width: 640,
+        height: 360,
+        ..Default::default()
+    };
+    let mut frames = 1;
+    let mut software = false;
+    let mut size_given = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| anyhow!("Missing value for {arg}"))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "-o" | "--output" => settings.path = PathBuf::from(value()?),
+            "-f" | "--format" => {
+                let name = value()?;
+                settings.format = ExportFormat::from_extension(name)
+                    .ok_or_else(|| anyhow!("Unknown format {name}"))?;
+            }
+            "-W" | "--width" => {
+                settings.width = value()?.parse().context("Invalid width")?;
+                size_given = true;
+            }
+            "-H" | "--height" => {
+                settings.height = value()?.parse().context("Invalid height")?;
+                size_given = true;
+            }
+            "-n" | "--frames" => frames = value()?.parse().context("Invalid frame count")?,
+            "--fps" => settings.fps = value()?.parse().context("Invalid frame rate")?,
+            "-t" | "--time" => settings.start_time = value()?.parse().context("Invalid time")?,
+            "--scale" => settings.scale = value()?.parse().context("Invalid scale")?,
+            "--software" => software = true,
+            _ if arg.starts_with('-') => bail!("Unknown option {arg}"),
+            _ if shader.is_none() => shader = Some(PathBuf::from(arg)),
+            _ => bail!("Unexpected argument {arg}"),
+        }
+    }
+    if settings.width == 0 || settings.height == 0 || settings.fps == 0 || frames == 0 {
+        bail!("Width, height, frame rate and frame count must be positive");
+    }
+    settings.scale = settings.scale.max(1);
+    let file_extension = settings.path.extension().and_then(|e| e.to_str());
+    if settings.format.is_sequence()
+        && file_extension.is_some_and(|e| ExportFormat::from_extension(e).is_some())
+    {
+        bail!(
+            "{} names a file, but the {} format writes a directory of frames",
+            settings.path.display(),
+            settings.format.name()
+        );
+    }
+    if settings.format.extension() != "" && settings.path.extension().is_none() {
+        settings.path.set_extension(settings.format.extension());
+    }
+    let shader = shader.ok_or_else(|| anyhow!("Missing shader file\n\n{RENDER_USAGE}"))?;
+    Ok(Some(RenderArgs {
+        shader,
+        settings,
+        size_given,
+        frames,
+        software,
+    }))
+}
+
+fn render(args: &[String]) -> anyhow::Result<()> {
+    let Some(args) = parse_render_args(args)? else {
+        println!("{RENDER_USAGE}");
+        return Ok(());
+    };
+    let project = if args
+        .shader
+        .extension()
+        .is_some_and(|e| e == project::PROJECT_EXTENSION)
+    {
+        Project::load(&args.shader)?
+    } else {
+        let content = std::fs::read_to_string(&args.shader)
+            .with_context(|| format!("Failed to read {}", args.shader.display()))?;
+        Project::single(&args.shader, content)
+    };
+    let mut settings = args.settings;
+    if let (Some([width, height]), false) = (project.resolution, args.size_given) {
+        settings.width = width;
+        settings.height = height;
+    }
+    let program = Program::compile(
+        &project,
+        project::directory(&args.shader),
+        &mut MediaCache::default(),
+    )
+    .context("Error loading shader")?;
+    let (device, queue) = headless_device(args.software)?;
+    let mut job = RenderJob::new(&device, &queue, &program, &settings, args.frames)?;
+    while !job.step()? {
+        eprint!("\rFrame {}/{}", job.frame(), args.frames);
+    }
+    eprintln!(
+        "\rRendered {} frames to {}",
+        args.frames,
+        settings.path.display()
+    );
+    Ok(())
+}
+
+fn import(args: &[String]) -> anyhow::Result<()> {
+    let mut exports = Vec::new();
+    let mut output = PathBuf::from(".");
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{IMPORT_USAGE}");
+                return Ok(());
+            }
+            "-o" | "--output" => {
+                output = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| anyhow!("Missing value for {arg}"))?
+            }
+            _ if arg.starts_with('-') => bail!("Unknown option {arg}"),
+            _ => exports.push(PathBuf::from(arg)),
+        }
+    }
+    if exports.is_empty() {
+        bail!("Missing export file\n\n{IMPORT_USAGE}");
+    }
+    let mut failed = 0;
+    for export in &exports {
+        if let Err(e) = import_export(export, &output) {
+            eprintln!("{}: {e:#}", export.display());
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        bail!("{failed} of {} exports failed to import", exports.len());
+    }
+    Ok(())
+}
+
+fn import_export(export: &Path, output: &Path) -> anyhow::Result<()> {
+    let json = std::fs::read_to_string(export)
+        .with_context(|| format!("Failed to read {}", export.display()))?;
+    let directory = project::directory(export);
+    for shadertoy::Import {
+        project,
+        id,
+        warnings,
+    } in shadertoy::import(&json, directory)?
+    {
+        let name = file_stem(id.as_deref().unwrap_or(&project.name));
+        let path = output.join(&name).join(format!("{name}.json"));
+        let project_directory = project::directory(&path);
+        std::fs::create_dir_all(project_directory)?;
+        copy_media(&project, directory, project_directory)?;
+        project
+            .save(&path)
+            .with_context(|| format!("Failed to save {}", path.display()))?;
+        for warning in warnings {
+            eprintln!("{}: {warning}", path.display());
+        }
+        eprintln!("Imported {:?} to {}", project.name, path.display());
+    }
+    Ok(())
+}
+
+/// Copy the media the project reads from `from` into `to`, keeping the
+/// relative paths, so each imported project is self-contained.
+fn copy_media(project: &Project, from: &Path, to: &Path) -> anyhow::Result<()> {
+    let inputs = project
+        .passes
+        .iter()
+        .flat_map(|pass| pass.channels.iter().flatten());
+    for path in inputs.filter_map(|channel| channel.input.path()) {
+        let source = from.join(path);
+        if !source.is_file() {
+            continue;
+        }
+        let target = to.join(path);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&source, &target)
+            .with_context(|| format!("Failed to copy {}", source.display()))?;
+    }
+    Ok(())
+}
+
+/// `name` reduced to characters that are safe in a file name everywhere.
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if stem.trim_matches('_').is_empty() {
+        "shader".to_string()
+    } else {
+        stem
+    }
+}
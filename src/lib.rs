@@ -2,3 +2,5 @@
 
 mod app;
 pub use app::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
@@ -4,6 +4,14 @@ use shadertoy_rs::TemplateApp;
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result {
     use eframe::egui;
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if let Some(result) = shadertoy_rs::cli::run(&args) {
+        if let Err(e) = result {
+            eprintln!("error: {e:#}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 300.0])
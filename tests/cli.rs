@@ -0,0 +1,81 @@
+//! Command line arguments of `shadertoy_rs render`.
+use shadertoy_rs::cli::parse_render_args;
+use shadertoy_rs::export::ExportFormat;
+use std::path::Path;
+
+fn parse(args: &str) -> anyhow::Result<Option<shadertoy_rs::cli::RenderArgs>> {
+    let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+    parse_render_args(&args)
+}
+
+#[test]
+fn flags_fill_the_settings() {
+    let args = parse(
+        "shader.glsl -o out -f gif -W 320 --height 180 -n 7 --fps 24 -t 1.5 --scale 2 --software",
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(args.shader, Path::new("shader.glsl"));
+    assert_eq!(args.settings.path, Path::new("out.gif"));
+    assert_eq!(args.settings.format, ExportFormat::Gif);
+    assert_eq!((args.settings.width, args.settings.height), (320, 180));
+    assert!(args.size_given);
+    // The frame count is kept as given, not turned into a duration.
+    assert_eq!(args.frames, 7);
+    assert_eq!(args.settings.fps, 24);
+    assert_eq!(args.settings.first_frame(), 36);
+    assert_eq!(args.settings.scale, 2);
+    assert!(args.software);
+
+    let args = parse("project.json").unwrap().unwrap();
+    assert_eq!((args.settings.width, args.settings.height), (640, 360));
+    assert!(!args.size_given);
+    assert_eq!(args.frames, 1);
+    assert_eq!(args.settings.format, ExportFormat::PngSequence);
+    assert_eq!(args.settings.path, Path::new("render"));
+    assert!(!args.software);
+
+    assert!(parse("shader.glsl --help").unwrap().is_none());
+}
+
+#[test]
+fn zero_sizes_rates_and_counts_are_rejected() {
+    for args in [
+        "shader.glsl -W 0",
+        "shader.glsl -H 0",
+        "shader.glsl --fps 0",
+        "shader.glsl -n 0",
+    ] {
+        assert!(parse(args).is_err(), "{args}");
+    }
+    for args in [
+        "",
+        "shader.glsl other.glsl",
+        "shader.glsl --frames",
+        "shader.glsl -n many",
+        "shader.glsl -f mp4",
+        "shader.glsl --unknown",
+    ] {
+        assert!(parse(args).is_err(), "{args}");
+    }
+}
+
+#[test]
+fn sequences_need_a_directory() {
+    // Would otherwise be a directory named `out.png` full of frames.
+    for args in [
+        "shader.glsl -o out.png",
+        "shader.glsl -f exr -o out.exr",
+        "shader.glsl -f hdr -o frames/out.png",
+    ] {
+        let error = parse(args).unwrap_err();
+        assert!(
+            error.to_string().contains("names a file"),
+            "{args}: {error}"
+        );
+    }
+    let args = parse("shader.glsl -f png -o frames.v2").unwrap().unwrap();
+    assert_eq!(args.settings.path, Path::new("frames.v2"));
+    let args = parse("shader.glsl -f apng -o out.png").unwrap().unwrap();
+    assert_eq!(args.settings.format, ExportFormat::Apng);
+}
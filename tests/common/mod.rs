@@ -0,0 +1,65 @@
+//! Helpers shared by the integration tests. Each test crate uses only some.
+#![allow(dead_code)]
+use eframe::egui;
+use shadertoy_rs::project::Project;
+use std::path::{Path, PathBuf};
+
+/// A file of the project with buffer passes in `tests/golden/buffers`.
+pub fn buffers_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden/buffers")
+        .join(name)
+}
+
+/// The project with buffer passes, loaded from its manifest.
+pub fn buffers_project() -> Project {
+    Project::load(&buffers_file("buffers.json")).unwrap()
+}
+
+/// An empty directory under the system temp directory.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shadertoy_rs-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("temp dir is created");
+    dir
+}
+
+/// Run a frame of a focused code editor over `code`, with `events`. `before`
+/// runs ahead of the editor with its id, and `after` gets the editor's output
+/// along with what `before` returned. Both are handed `state`.
+pub fn editor_frame<S, T>(
+    ctx: &egui::Context,
+    state: &mut S,
+    code: &mut String,
+    events: Vec<egui::Event>,
+    before: impl Fn(&mut S, &mut egui::Ui, egui::Id, &mut String) -> T,
+    after: impl Fn(&mut S, &mut egui::Ui, &egui::text_edit::TextEditOutput, &mut String, T),
+) {
+    let input = egui::RawInput {
+        events,
+        ..Default::default()
+    };
+    let _ = ctx.run(input, |ctx| {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let id = egui::Id::new("code");
+            ui.memory_mut(|memory| memory.request_focus(id));
+            let before = before(state, ui, id, code);
+            let output = egui::TextEdit::multiline(code)
+                .id(id)
+                .code_editor()
+                .show(ui);
+            after(state, ui, &output, code, before);
+        });
+    });
+}
+
+/// Pressing `key` with `modifiers` held.
+pub fn key_press(key: egui::Key, modifiers: egui::Modifiers) -> egui::Event {
+    egui::Event::Key {
+        key,
+        physical_key: None,
+        pressed: true,
+        repeat: false,
+        modifiers,
+    }
+}
@@ -0,0 +1,62 @@
+//! Rewriting GLSL ES code from Shadertoy and WebGL for the desktop template.
+use shadertoy_rs::compat::rewrite;
+use shadertoy_rs::load_fragment_shader;
+
+const ES_SHADER: &str = "\
+#version 300 es
+precision highp float;
+uniform vec3 iResolution;
+uniform sampler2D iChannel0;
+
+vec4 blur(sampler2D tex, vec2 uv) {
+    float sample = 0.25;
+    return texture(tex, uv) * sample + texture2D(tex, uv + 0.01) * (1.0 - sample);
+}
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    mediump vec2 uv = fragCoord / iResolution.xy;
+    fragColor = blur(iChannel0, uv);
+}
+";
+
+#[test]
+fn es_code_compiles() {
+    let shader = load_fragment_shader(ES_SHADER).unwrap();
+    assert!(!shader.spirv.is_empty());
+    assert_eq!(shader.rewrites.len(), 7, "{:#?}", shader.rewrites);
+}
+
+#[test]
+fn rewrites_keep_lines_and_are_reported() {
+    let rewritten = rewrite(ES_SHADER);
+    assert_eq!(rewritten.code.lines().count(), ES_SHADER.lines().count());
+    let lines: Vec<&str> = rewritten.code.lines().collect();
+    assert_eq!(lines[0], "");
+    assert_eq!(
+        lines[5],
+        "vec4 blur(texture2D _tex_texture, sampler _tex_sampler, vec2 uv) {"
+    );
+    assert_eq!(lines[6], "    float sample_ = 0.25;");
+    assert_eq!(
+        lines[12],
+        "    fragColor = blur(_iChannel0_texture, _iChannel0_sampler, uv);"
+    );
+    let report: Vec<String> = rewritten.rewrites.iter().map(ToString::to_string).collect();
+    assert!(
+        report.contains(&"line 8: replaced texture2D with texture".to_string()),
+        "{report:#?}"
+    );
+    assert!(
+        report
+            .contains(&"lines 7, 8: renamed sample to sample_, a keyword in GLSL 4.50".to_string()),
+        "{report:#?}"
+    );
+}
+
+#[test]
+fn desktop_code_is_untouched() {
+    let code = "void mainImage(out vec4 o, in vec2 p) { o = texture(iChannel0, p); }\n";
+    let rewritten = rewrite(code);
+    assert_eq!(rewritten.code, code);
+    assert!(rewritten.rewrites.is_empty());
+}
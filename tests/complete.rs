@@ -0,0 +1,140 @@
+//! Completion and hover documentation in the code editor.
+use eframe::egui;
+use shadertoy_rs::complete::{
+    builtin, candidates, items, symbols, template_uniforms, word_at, Completion, Item,
+};
+use shadertoy_rs::highlight::SHADERTOY_UNIFORMS;
+use shadertoy_rs::project::Project;
+
+mod common;
+
+#[test]
+fn template_inputs_are_offered() {
+    let uniforms = template_uniforms();
+    let names: Vec<&str> = uniforms.iter().map(|item| item.name.as_str()).collect();
+    assert_eq!(names, SHADERTOY_UNIFORMS);
+    let resolution = &uniforms[0];
+    assert_eq!(resolution.detail, "vec2 iResolution");
+    assert!(uniforms.iter().all(|item| item.doc.is_some()));
+}
+
+#[test]
+fn builtins_are_documented() {
+    let smoothstep = builtin("smoothstep").unwrap();
+    assert!(smoothstep
+        .detail
+        .starts_with("genType smoothstep(genType edge0"));
+    assert!(smoothstep.doc.unwrap().contains("Hermite"));
+    assert!(builtin("mainImage").is_none());
+}
+
+#[test]
+fn functions_and_defines_are_found() {
+    let code = "#define PI 3.14159\n\
+                #define ROT(a) mat2(cos(a), sin(a), -sin(a), cos(a))\n\
+                // float commented(float x) {}\n\
+                float sdBox(vec3 p, vec3 b) {\n\
+                \x20   if (p.x > 0.0) return length(max(abs(p) - b, 0.0));\n\
+                \x20   else if (true) {}\n\
+                \x20   vec3 q = normalize(p);\n\
+                }\n\
+                Ray makeRay(in vec2 uv);\n\
+                highp vec3 shade(Ray ray,\n\
+                \x20                 vec3 n) {}\n";
+    let found: Vec<(String, String)> = symbols(code)
+        .into_iter()
+        .map(|item| (item.name, item.detail))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            ("PI", "#define PI 3.14159"),
+            (
+                "ROT",
+                "#define ROT(a) mat2(cos(a), sin(a), -sin(a), cos(a))"
+            ),
+            ("sdBox", "float sdBox(vec3 p, vec3 b)"),
+            ("makeRay", "Ray makeRay(in vec2 uv)"),
+            ("shade", "highp vec3 shade(Ray ray, …)"),
+        ]
+        .map(|(name, detail)| (name.to_string(), detail.to_string()))
+    );
+}
+
+#[test]
+fn completions_match_the_word_before_the_cursor() {
+    let project = Project::single("a.glsl", "float smoothMin(float a, float b) {}\n");
+    let items = items(&project);
+    let code = "col = smoo";
+    let word = word_at(code, code.len());
+    assert_eq!(&code[word.clone()], "smoo");
+    let names: Vec<&str> = candidates(&code[word], &items)
+        .into_iter()
+        .map(|item| item.name.as_str())
+        .collect();
+    assert_eq!(names, ["smoothMin", "smoothstep"]);
+
+    let names: Vec<&str> = candidates("ichannel", &items)
+        .into_iter()
+        .map(|item| item.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "iChannelTime",
+            "iChannelResolution",
+            "iChannel0",
+            "iChannel1",
+            "iChannel2",
+            "iChannel3"
+        ]
+    );
+    assert_eq!(word_at("a + iTime;", 6), 4..9);
+}
+
+/// Run a frame of an editor with completion over `code`, with `events`.
+fn editor_frame(
+    ctx: &egui::Context,
+    completion: &mut Completion,
+    code: &mut String,
+    items: &[Item],
+    events: Vec<egui::Event>,
+) {
+    common::editor_frame(
+        ctx,
+        completion,
+        code,
+        events,
+        |completion, ui, id, _| completion.consume_keys(ui, id),
+        |completion, ui, output, code, key| {
+            completion.ui(ui, output, code, items, key);
+        },
+    );
+}
+
+#[test]
+fn enter_accepts_the_completion() {
+    let ctx = egui::Context::default();
+    let items = items(&Project::single(
+        "a.glsl",
+        "float smoothMin(float a, float b) {}\n",
+    ));
+    let mut completion = Completion::default();
+    let mut code = String::new();
+    let enter = common::key_press(egui::Key::Enter, egui::Modifiers::NONE);
+    editor_frame(&ctx, &mut completion, &mut code, &items, vec![]);
+    let typed = vec![egui::Event::Text("x = sm".into())];
+    editor_frame(&ctx, &mut completion, &mut code, &items, typed);
+    editor_frame(
+        &ctx,
+        &mut completion,
+        &mut code,
+        &items,
+        vec![enter.clone()],
+    );
+    assert_eq!(code, "x = smoothMin");
+
+    // Closed once the word is complete, so Enter starts a new line again.
+    editor_frame(&ctx, &mut completion, &mut code, &items, vec![enter]);
+    assert_eq!(code, "x = smoothMin\n");
+}
@@ -0,0 +1,74 @@
+//! Typing at several cursors in the code editor.
+use eframe::egui;
+use shadertoy_rs::cursors::{apply, next_occurrence, Cursors, Edit, NEXT_OCCURRENCE_SHORTCUT};
+
+mod common;
+
+#[test]
+fn edits_are_made_at_every_cursor() {
+    let mut code = "a = 1;\nb = 2;\n".to_string();
+    let cursors = apply(&mut code, &[7..8, 0..1], &Edit::Insert("xy".into()));
+    assert_eq!(code, "xy = 1;\nxy = 2;\n");
+    assert_eq!(cursors, [10, 2]);
+    let cursors = apply(&mut code, &[2..2, 10..10], &Edit::Backspace);
+    assert_eq!(code, "x = 1;\nx = 2;\n");
+    assert_eq!(cursors, [1, 8]);
+    let cursors = apply(&mut code, &[0..0, 7..7], &Edit::Delete);
+    assert_eq!(code, " = 1;\n = 2;\n");
+    assert_eq!(cursors, [0, 6]);
+}
+
+#[test]
+fn overlapping_cursors_edit_once() {
+    let mut code = "abcdef".to_string();
+    let cursors = apply(&mut code, &[1..4, 2..5, 5..5], &Edit::Insert("_".into()));
+    assert_eq!(code, "a_f");
+    assert_eq!(cursors, [2, 2, 2]);
+    let mut code = "é".to_string();
+    let cursors = apply(&mut code, &[1..1, 1..1], &Edit::Insert("!".into()));
+    assert_eq!((code.as_str(), cursors), ("é!", vec![2, 2]));
+}
+
+#[test]
+fn occurrences_are_found_going_around() {
+    let code = "uv.x + uv.y + uv";
+    assert_eq!(next_occurrence(code, "uv", 2), Some(7..9));
+    assert_eq!(next_occurrence(code, "uv", 15), Some(0..2));
+    assert_eq!(next_occurrence(code, "st", 0), None);
+}
+
+/// Run a frame of a focused editor of `code` with `cursors`.
+fn editor_frame(
+    ctx: &egui::Context,
+    cursors: &mut Cursors,
+    code: &mut String,
+    events: Vec<egui::Event>,
+) {
+    common::editor_frame(
+        ctx,
+        cursors,
+        code,
+        events,
+        |cursors, ui, id, code| cursors.input(ui, id, code, 0),
+        |_, _, _, _, _| {},
+    );
+}
+
+#[test]
+fn typing_goes_to_every_occurrence() {
+    let ctx = egui::Context::default();
+    let mut cursors = Cursors::default();
+    let mut code = "t + t * t".to_string();
+    let shortcut = common::key_press(
+        NEXT_OCCURRENCE_SHORTCUT.logical_key,
+        NEXT_OCCURRENCE_SHORTCUT.modifiers,
+    );
+    editor_frame(&ctx, &mut cursors, &mut code, vec![]);
+    // Selects the word, then adds its next occurrences.
+    for _ in 0..3 {
+        editor_frame(&ctx, &mut cursors, &mut code, vec![shortcut.clone()]);
+    }
+    let typed = vec![egui::Event::Text("time".into())];
+    editor_frame(&ctx, &mut cursors, &mut code, typed);
+    assert_eq!(code, "time + time * time");
+}
@@ -0,0 +1,79 @@
+//! Files dropped onto the window.
+use eframe::egui::DroppedFile;
+use shadertoy_rs::dropped::{self, ChannelAction, Kind};
+use shadertoy_rs::passes::TextureImage;
+use shadertoy_rs::project::{ChannelInput, PassKind, Project};
+use std::path::PathBuf;
+
+mod common;
+
+/// A file as the web version gets it: a name and its content.
+fn uploaded(name: &str) -> DroppedFile {
+    DroppedFile {
+        name: name.to_string(),
+        bytes: Some(std::fs::read(common::buffers_file(name)).unwrap().into()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn files_are_told_apart_by_extension() {
+    assert_eq!(Kind::of("plasma.glsl"), Some(Kind::Shader));
+    assert_eq!(Kind::of("plasma.FRAG"), Some(Kind::Shader));
+    assert_eq!(Kind::of("buffers.json"), Some(Kind::Project));
+    assert_eq!(Kind::of("photo.JPG"), Some(Kind::Image));
+    assert_eq!(Kind::of("notes.txt"), None);
+    assert_eq!(Kind::of("Makefile"), None);
+}
+
+#[test]
+fn native_drops_are_named_after_their_path() {
+    let file = DroppedFile {
+        path: Some(common::buffers_file("image.glsl")),
+        ..Default::default()
+    };
+    assert_eq!(dropped::name(&file), "image.glsl");
+    assert!(dropped::read_text(&file).unwrap().contains("mainImage"));
+}
+
+#[test]
+fn manifests_load_with_the_sources_dropped_along() {
+    let files = [
+        uploaded("buffers.json"),
+        uploaded("buffer_a.glsl"),
+        uploaded("image.glsl"),
+        uploaded("quadrants.png"),
+    ];
+    let manifest = dropped::read_text(&files[0]).unwrap();
+    let project = dropped::project(&manifest, &files).unwrap();
+    assert_eq!(
+        project,
+        Project::load(&common::buffers_file("buffers.json")).unwrap()
+    );
+
+    let error = dropped::project(&manifest, &files[..2]).unwrap_err();
+    assert!(error.to_string().contains("image.glsl"), "{error}");
+}
+
+#[test]
+fn dropped_images_fill_the_picked_channel() {
+    let mut project = Project::single("a.glsl", "");
+    ChannelAction::Assign {
+        pass: PassKind::Image,
+        index: 2,
+        path: PathBuf::from("quadrants.png"),
+    }
+    .apply(&mut project);
+    let channel = project.image().channels[2].as_ref().unwrap();
+    assert_eq!(
+        channel.input,
+        ChannelInput::Texture {
+            path: PathBuf::from("quadrants.png")
+        }
+    );
+
+    let bytes = uploaded("quadrants.png").bytes.unwrap();
+    let image = TextureImage::decode(&bytes, false).unwrap();
+    assert_eq!(image.rgba.len(), (image.width * image.height * 4) as usize);
+    assert!(TextureImage::decode(b"not an image", false).is_err());
+}
@@ -0,0 +1,48 @@
+//! The bundled examples: every one compiles, and their media is built in.
+use shadertoy_rs::examples::{texture, EXAMPLES};
+use shadertoy_rs::passes::{MediaCache, Program};
+use std::path::Path;
+
+#[test]
+fn examples_compile() {
+    assert_eq!(EXAMPLES[0].file_name, "default.glsl");
+    for example in EXAMPLES {
+        let project = example.project();
+        assert!(
+            project.sources().all(|source| !source.code.is_empty()),
+            "{} has empty sources",
+            example.file_name
+        );
+        if let Err(e) = Program::compile(&project, Path::new("."), &mut MediaCache::default()) {
+            panic!("{}: {e:#}", example.file_name);
+        }
+    }
+}
+
+#[test]
+fn examples_have_titles() {
+    let titles: Vec<String> = EXAMPLES.iter().map(|example| example.title()).collect();
+    assert_eq!(
+        titles,
+        [
+            "Raymarching - Primitives",
+            "Truchet tiles",
+            "Raymarcher",
+            "Feedback fluid",
+            "Texture channel",
+            "Audio visualizer",
+        ]
+    );
+}
+
+#[test]
+fn example_images_are_built_in() {
+    // Opened from memory, the texture example resolves its image against
+    // the current directory, where it isn't.
+    let image = texture(Path::new("./grid.png"), false).expect("grid.png is bundled");
+    assert_eq!((image.width, image.height), (128, 128));
+    assert_eq!(image.rgba.len(), 128 * 128 * 4);
+    let flipped = texture(Path::new("grid.png"), true).unwrap();
+    assert_eq!(flipped.rgba[..512], image.rgba[image.rgba.len() - 512..]);
+    assert!(texture(Path::new("missing.png"), false).is_none());
+}
@@ -0,0 +1,269 @@
+//! Exported files: downscaling, palettes and dithering, and the bytes each
+//! writer produces.
+use shadertoy_rs::export::{
+    estimate_size, ApngWriter, Dither, ExportFormat, ExportSettings, FrameWriter, GifWriter,
+    Palette, PaletteSettings, Quantizer, Y4mWriter,
+};
+use shadertoy_rs::passes::{MediaCache, Program};
+use shadertoy_rs::project::Project;
+use shadertoy_rs::render::{headless_device, Frame, Pixels};
+use std::path::Path;
+
+/// A `width`×`height` frame of opaque `rgb` pixels.
+fn frame(width: u32, height: u32, rgb: &[[u8; 3]]) -> Frame {
+    assert_eq!(rgb.len(), (width * height) as usize);
+    Frame {
+        width,
+        height,
+        pixels: Pixels::Rgba8(rgb.iter().flat_map(|&[r, g, b]| [r, g, b, 255]).collect()),
+    }
+}
+
+/// `colors` entries of a uniform palette, without dithering unless `dither`.
+fn uniform(colors: u16, dither: Dither) -> PaletteSettings {
+    PaletteSettings {
+        quantizer: Quantizer::Uniform,
+        colors,
+        dither,
+    }
+}
+
+#[test]
+fn downscaling_averages_blocks() {
+    let image = frame(
+        4,
+        2,
+        &[
+            [0, 0, 0],
+            [255, 255, 255],
+            [10, 20, 30],
+            [10, 20, 30],
+            [255, 255, 255],
+            [0, 0, 0],
+            [30, 40, 50],
+            [30, 40, 50],
+        ],
+    );
+    let small = image.downscale(2);
+    assert_eq!((small.width, small.height), (2, 1));
+    assert_eq!(small.rgb(), [128, 128, 128, 20, 30, 40]);
+
+    let same = frame(1, 1, &[[1, 2, 3]]).downscale(1);
+    assert_eq!(same.rgb(), [1, 2, 3]);
+}
+
+#[test]
+fn float_frames_keep_their_range() {
+    let values = [4.0, -2.0, 0.5, 1.0, 0.0, 0.0, 0.5, 1.0, 0.0, 0.0, 0.5, 1.0];
+    let frame = Frame {
+        width: 3,
+        height: 1,
+        pixels: Pixels::Rgba32Float(values.to_vec()),
+    };
+    // Clamped for 8-bit formats only.
+    assert_eq!(frame.rgb()[..3], [255, 0, 128]);
+    // Pixels past the last whole block are dropped.
+    let small = frame.downscale(2);
+    assert_eq!((small.width, small.height), (1, 1));
+    assert_eq!(small.rgb_f32(), [2.0, -1.0, 0.5]);
+}
+
+#[test]
+fn y4m_has_a_header_and_planar_frames() {
+    let mut output = Vec::new();
+    let mut writer = Y4mWriter::new(&mut output, 2, 1, 30).unwrap();
+    writer
+        .write_frame(0, &frame(2, 1, &[[255, 255, 255], [255, 0, 0]]))
+        .unwrap();
+    writer.finish().unwrap();
+    let header = b"YUV4MPEG2 W2 H1 F30:1 Ip A1:1 C444\n";
+    assert_eq!(&output[..header.len()], header);
+    // BT.601 limited range: Y for both pixels, then Cb, then Cr.
+    assert_eq!(&output[header.len()..], b"FRAME\n\xeb\x51\x80\x5a\x80\xf0");
+}
+
+#[test]
+fn palettes_map_colors_onto_their_entries() {
+    let palette = Palette::new(&uniform(8, Dither::None), &[]);
+    assert_eq!(palette.len(), 8);
+    let rgb = palette.rgb();
+    let corners = [[0, 0, 0], [255, 255, 255], [250, 10, 5], [0, 200, 255]];
+    let indices = palette.quantize(&corners.concat(), 4, 1);
+    let mapped: Vec<&[u8]> = indices
+        .iter()
+        .map(|&i| &rgb[i as usize * 3..i as usize * 3 + 3])
+        .collect();
+    assert_eq!(
+        mapped,
+        [[0, 0, 0], [255, 255, 255], [255, 0, 0], [0, 255, 255]]
+    );
+
+    // Never more colors than asked for, whatever the count.
+    for colors in 2..=256 {
+        let palette = Palette::new(&uniform(colors, Dither::None), &[]);
+        assert!(palette.len() <= colors as usize, "{colors}");
+        assert!(palette.len() * 2 > colors as usize, "{colors}");
+        let indices = palette.quantize(&[0, 128, 255, 255, 0, 128], 2, 1);
+        assert!(indices.iter().all(|&i| (i as usize) < palette.len()));
+    }
+
+    // An adaptive palette has at most the colors asked for, and keeps those
+    // of an image with few colors.
+    let image = [[200, 30, 30], [20, 20, 220]].repeat(32).concat();
+    let adaptive = PaletteSettings {
+        quantizer: Quantizer::NeuQuant,
+        colors: 16,
+        dither: Dither::None,
+    };
+    let palette = Palette::new(&adaptive, &image);
+    assert!(palette.len() <= 16);
+    let rgb = palette.rgb();
+    for (pixel, index) in image.chunks(3).zip(palette.quantize(&image, 8, 8)) {
+        let entry = &rgb[index as usize * 3..index as usize * 3 + 3];
+        let distance = pixel.iter().zip(entry).map(|(a, b)| a.abs_diff(*b)).max();
+        assert!(distance.unwrap() <= 8, "{pixel:?} became {entry:?}");
+    }
+}
+
+/// Share of the pixels of a mid-gray 8×8 image that `dither` makes white,
+/// with a palette of the corners of the RGB cube, and the palette indices.
+fn white_share(dither: Dither) -> (f32, Vec<u8>) {
+    let palette = Palette::new(&uniform(8, dither), &[]);
+    let rgb = palette.rgb();
+    let indices = palette.quantize(&[128; 8 * 8 * 3], 8, 8);
+    let white = indices
+        .iter()
+        .filter(|&&i| rgb[i as usize * 3..i as usize * 3 + 3] == [255; 3])
+        .count();
+    (white as f32 / indices.len() as f32, indices)
+}
+
+#[test]
+fn dithering_keeps_the_average_color() {
+    assert_eq!(white_share(Dither::None).0, 1.0);
+    let (share, indices) = white_share(Dither::Ordered);
+    assert_eq!(share, 0.5);
+    // The Bayer pattern repeats every 4 pixels.
+    assert_eq!(indices[..4], indices[4..8]);
+    assert_eq!(indices[..8], indices[32..40]);
+    let (share, _) = white_share(Dither::FloydSteinberg);
+    assert!((share - 0.5).abs() <= 0.1, "{share}");
+}
+
+#[test]
+fn gif_delays_average_to_the_frame_rate() {
+    for (fps, frames) in [(30, 30), (60, 60), (24, 48)] {
+        let mut output = Vec::new();
+        let mut writer = GifWriter::new(&mut output, 1, 1, fps, uniform(8, Dither::None)).unwrap();
+        for index in 0..frames {
+            writer
+                .write_frame(index, &frame(1, 1, &[[0, 0, 0]]))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(output.as_slice()).unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays.len(), frames as usize);
+        // Whole centiseconds, alternating around 100 / fps.
+        let exact = 100.0 / fps as f32;
+        assert!(
+            delays
+                .iter()
+                .all(|&delay| (delay as f32 - exact).abs() < 1.0),
+            "{fps}: {delays:?}"
+        );
+        let total: u32 = delays.iter().map(|&delay| u32::from(delay)).sum();
+        assert_eq!(total, frames * 100 / fps, "{fps}");
+    }
+}
+
+/// The chunk types of a PNG file, in order.
+fn chunks(png: &[u8]) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut at = 8;
+    while at < png.len() {
+        let length = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+        chunks.push(String::from_utf8_lossy(&png[at + 4..at + 8]).into_owned());
+        at += 12 + length;
+    }
+    chunks
+}
+
+#[test]
+fn apng_chunks_are_in_order() {
+    for (quantizer, palette) in [
+        (Quantizer::None, vec![]),
+        (Quantizer::Uniform, vec!["PLTE"]),
+    ] {
+        let mut output = Vec::new();
+        let settings = PaletteSettings {
+            quantizer,
+            ..uniform(8, Dither::None)
+        };
+        let mut writer = ApngWriter::new(&mut output, 30, 3, settings);
+        for index in 0..3 {
+            let gray = index as u8 * 100;
+            writer
+                .write_frame(index, &frame(2, 2, &[[gray; 3]; 4]))
+                .unwrap();
+        }
+        writer.finish().unwrap();
+        drop(writer);
+        let mut expected = vec!["IHDR", "acTL"];
+        expected.extend(palette);
+        expected.extend(["fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]);
+        assert_eq!(chunks(&output), expected, "{quantizer:?}");
+    }
+}
+
+#[test]
+fn size_estimates_extrapolate_the_sampled_frames() {
+    let (device, queue) = match headless_device(true) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Skipping rendering test: {e}");
+            return;
+        }
+    };
+    let project = Project::single(
+        "shader.glsl",
+        "void mainImage(out vec4 c, in vec2 p) { c = vec4(p / iResolution.xy, 0.5, 1.0); }\n",
+    );
+    let program = Program::compile(&project, Path::new("."), &mut MediaCache::default()).unwrap();
+    let settings = ExportSettings {
+        width: 32,
+        height: 16,
+        fps: 10,
+        duration: 2.0,
+        format: ExportFormat::Y4m,
+        ..Default::default()
+    };
+    let estimate = estimate_size(&device, &queue, &program, &settings).unwrap();
+    // Every Y4M frame has the same size, so only the header is off.
+    let frame = "FRAME\n".len() + 32 * 16 * 3;
+    let header = "YUV4MPEG2 W32 H16 F10:1 Ip A1:1 C444\n".len();
+    let exact = header + 20 * frame;
+    assert!(
+        estimate as usize >= exact && estimate as usize <= exact + 5 * header,
+        "{estimate} for {exact}"
+    );
+
+    // Downscaled frames are smaller.
+    let scaled = ExportSettings {
+        scale: 2,
+        ..settings.clone()
+    };
+    let smaller = estimate_size(&device, &queue, &program, &scaled).unwrap();
+    assert!(smaller * 3 < estimate, "{smaller} for {estimate}");
+    let gif = ExportSettings {
+        format: ExportFormat::Gif,
+        ..settings
+    };
+    assert!(estimate_size(&device, &queue, &program, &gif).unwrap() > 0);
+}
@@ -0,0 +1,56 @@
+//! The list of recently opened files.
+use shadertoy_rs::file::{RecentFiles, MAX_RECENT_FILES};
+use std::path::{Path, PathBuf};
+
+fn paths(count: usize) -> Vec<PathBuf> {
+    (0..count)
+        .map(|i| PathBuf::from(format!("shader{i}.glsl")))
+        .collect()
+}
+
+#[test]
+fn recent_files_are_newest_first_without_duplicates() {
+    let mut recent = RecentFiles::default();
+    recent.add("a.glsl".into());
+    recent.add("b.glsl".into());
+    recent.add("c.glsl".into());
+    assert_eq!(
+        recent.paths(),
+        [
+            Path::new("c.glsl"),
+            Path::new("b.glsl"),
+            Path::new("a.glsl")
+        ]
+    );
+    // Opening a file again moves it to the front.
+    recent.add("a.glsl".into());
+    assert_eq!(
+        recent.paths(),
+        [
+            Path::new("a.glsl"),
+            Path::new("c.glsl"),
+            Path::new("b.glsl")
+        ]
+    );
+    recent.remove(Path::new("c.glsl"));
+    assert_eq!(recent.paths(), [Path::new("a.glsl"), Path::new("b.glsl")]);
+}
+
+#[test]
+fn recent_files_are_capped() {
+    let mut recent = RecentFiles::default();
+    for path in paths(MAX_RECENT_FILES + 3) {
+        recent.add(path);
+    }
+    assert_eq!(recent.paths().len(), MAX_RECENT_FILES);
+    // The oldest are dropped.
+    assert_eq!(
+        recent.paths()[0],
+        paths(MAX_RECENT_FILES + 3)[MAX_RECENT_FILES + 2]
+    );
+    assert!(!recent.paths().contains(&paths(1)[0]));
+
+    // Also when restored from a longer list.
+    let restored = RecentFiles::new(paths(MAX_RECENT_FILES * 2));
+    assert_eq!(restored.paths(), &paths(MAX_RECENT_FILES)[..]);
+}
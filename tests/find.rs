@@ -0,0 +1,69 @@
+//! Find and replace in the code editor.
+use shadertoy_rs::find::{next, previous, Query};
+
+fn query(text: &str, regex: bool, case_sensitive: bool) -> Query {
+    Query {
+        text: text.to_string(),
+        regex,
+        case_sensitive,
+    }
+}
+
+#[test]
+fn plain_text_and_regular_expressions_match() {
+    let code = "float sdBox(vec3 p) { return length(p); }\nfloat SDBOX;\n";
+    let found = |query: Query| {
+        let matches = query.matches(code).unwrap();
+        matches
+            .into_iter()
+            .map(|range| &code[range])
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(found(query("sdbox", false, false)), ["sdBox", "SDBOX"]);
+    assert_eq!(found(query("sdbox", false, true)), Vec::<&str>::new());
+    // Plain text isn't a pattern.
+    assert_eq!(found(query("(p)", false, true)), ["(p)"]);
+    assert_eq!(
+        found(query(r"^float \w+", true, true)),
+        ["float sdBox", "float SDBOX"]
+    );
+    // Empty matches are no use to step through.
+    assert!(query("x*", true, true).matches("abc").unwrap().is_empty());
+    assert!(query("(", true, true).matches(code).is_err());
+}
+
+#[test]
+fn replacements_expand_groups_only_in_regular_expressions() {
+    let code = "vec2 a = b.xy; vec2 c = d.xy;";
+    let regex = query(r"(\w)\.xy", true, true);
+    assert_eq!(
+        regex.replace_all(code, "$1.yx").unwrap(),
+        ("vec2 a = b.yx; vec2 c = d.yx;".to_string(), 2)
+    );
+    assert_eq!(regex.replacement(code, 9..13, "${1}_").unwrap(), "b_");
+    let plain = query(".xy", false, true);
+    assert_eq!(
+        plain.replace_all(code, "$1").unwrap(),
+        ("vec2 a = b$1; vec2 c = d$1;".to_string(), 2)
+    );
+    // Only the matches that are highlighted are replaced.
+    assert_eq!(
+        query("x*", true, true).replace_all("axbxx", "-").unwrap(),
+        ("a-b-".to_string(), 2)
+    );
+    assert_eq!(
+        query(r"\s*", true, true).replace_all("a  b", "_").unwrap(),
+        ("a_b".to_string(), 1)
+    );
+}
+
+#[test]
+fn stepping_through_matches_goes_around() {
+    let matches = [2..4, 10..12, 20..22];
+    assert_eq!(next(&matches, &(0..0)), Some(2..4));
+    assert_eq!(next(&matches, &(2..4)), Some(10..12));
+    assert_eq!(next(&matches, &(20..22)), Some(2..4));
+    assert_eq!(previous(&matches, &(10..12)), Some(2..4));
+    assert_eq!(previous(&matches, &(2..4)), Some(20..22));
+    assert_eq!(next(&[], &(0..0)), None);
+}
@@ -0,0 +1,112 @@
+//! Formatting GLSL in the editor.
+use shadertoy_rs::examples::EXAMPLES;
+use shadertoy_rs::format::{format, verified};
+use shadertoy_rs::include::Includes;
+use shadertoy_rs::project::{PassKind, Project};
+
+#[test]
+fn code_is_laid_out_consistently() {
+    let code = "\
+float sdf(vec3 p){return length(p)-1.0;}
+void mainImage( out vec4 fragColor , in vec2 fragCoord )
+{
+\tvec2 uv=fragCoord/iResolution.xy;
+  for(int i=0;i<4;i++)
+  {
+      if (uv.x>0.5) uv.x-=0.5;
+  }
+  }
+  else {
+";
+    let formatted = format(code);
+    assert_eq!(
+        formatted,
+        "\
+float sdf(vec3 p) { return length(p) - 1.0; }
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 uv = fragCoord / iResolution.xy;
+    for (int i = 0; i < 4; i++) {
+        if (uv.x > 0.5) uv.x -= 0.5;
+    }
+} else {
+"
+    );
+    assert_eq!(format(&formatted), formatted);
+}
+
+#[test]
+fn operators_comments_and_directives_are_kept() {
+    let code = "\
+#define  SCALE(x)  ((x)*2.0)
+/* A block
+   comment, as it is. */
+float f(float a,float b) {
+return a- -b+ ++a*-1e-3;   // Trailing.
+}
+";
+    assert_eq!(
+        format(code),
+        "\
+#define  SCALE(x)  ((x)*2.0)
+/* A block
+   comment, as it is. */
+float f(float a, float b) {
+    return a - -b + ++a * -1e-3;   // Trailing.
+}
+"
+    );
+}
+
+#[test]
+fn examples_format_to_the_same_shader() {
+    for example in EXAMPLES {
+        let project = example.project();
+        for (index, source) in project.sources().enumerate() {
+            let formatted = format(&source.code);
+            assert_eq!(format(&formatted), formatted, "{}", example.file_name);
+            match verified(&project, index, &Includes::default()) {
+                Ok(Some(code)) => assert_eq!(code, formatted),
+                Ok(None) => assert_eq!(source.code, formatted),
+                Err(e) => panic!("{}: {e:#}", example.file_name),
+            }
+        }
+    }
+    // The default shader is pasted with mixed indentation.
+    let default = EXAMPLES[0].project();
+    assert!(verified(&default, 0, &Includes::default())
+        .unwrap()
+        .is_some());
+}
+
+#[test]
+fn changes_to_the_shader_are_refused() {
+    let project = Project::single(
+        "image.glsl",
+        "void mainImage(out vec4 c, in vec2 p) {c = vec4(undefined_name);}\n",
+    );
+    assert!(verified(&project, 0, &Includes::default()).is_err());
+    let project = Project::single(
+        "image.glsl",
+        "void mainImage(out vec4 c, in vec2 p)\n{\nc = vec4(1.0);\n}\n",
+    );
+    let code = verified(&project, 0, &Includes::default())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        code,
+        "void mainImage(out vec4 c, in vec2 p) {\n    c = vec4(1.0);\n}\n"
+    );
+
+    // Sound passes aren't compiled, so there's nothing to check against.
+    let mut project = project;
+    project.add_pass(
+        PassKind::Sound,
+        "vec2 mainSound(int s,float t){return vec2(0.0);}\n",
+    );
+    let sound = project
+        .passes
+        .iter()
+        .position(|pass| pass.kind == PassKind::Sound)
+        .unwrap();
+    assert!(verified(&project, sound, &Includes::default()).is_err());
+}
@@ -0,0 +1,100 @@
+//! The gallery: finding shaders and searching their header metadata.
+use shadertoy_rs::gallery::{Gallery, Metadata, Origin};
+use std::path::Path;
+
+fn fixtures() -> &'static Path {
+    Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/gallery"))
+}
+
+#[test]
+fn header_comments_are_metadata() {
+    let metadata = Metadata::parse(include_str!("../src/app/examples/default.glsl"));
+    assert_eq!(metadata.title.as_deref(), Some("Raymarching - Primitives"));
+    assert_eq!(metadata.author.as_deref(), Some("Inigo Quilez"));
+    assert_eq!(metadata.tags, ["raymarching", "sdf", "3d"]);
+    assert_eq!(metadata.license.as_deref(), Some("MIT"));
+
+    // Only the comments before the code count.
+    let metadata = Metadata::parse("void main() {}\n// Title: Late\n");
+    assert_eq!(metadata, Metadata::default());
+}
+
+#[test]
+fn scan_lists_bundled_examples_then_files() {
+    let gallery = Gallery::scan(Some(fixtures()));
+    let names: Vec<_> = gallery.entries.iter().map(|e| e.name.as_str()).collect();
+    // rings.glsl is part of the rings.json project.
+    assert_eq!(
+        names,
+        [
+            "default.glsl",
+            "pattern.glsl",
+            "raymarcher.glsl",
+            "fluid.json",
+            "texture.json",
+            "audio.json",
+            "noise.frag",
+            "plasma.glsl",
+            "rings.json"
+        ]
+    );
+    let plasma = gallery
+        .entries
+        .iter()
+        .find(|entry| entry.name == "plasma.glsl")
+        .unwrap();
+    assert_eq!(plasma.origin, Origin::File(fixtures().join("plasma.glsl")));
+    assert_eq!(plasma.metadata.license.as_deref(), Some("CC0"));
+    assert_eq!(gallery.entries[0].origin, Origin::Bundled);
+}
+
+#[test]
+fn search_covers_names_and_metadata() {
+    let gallery = Gallery::scan(Some(fixtures()));
+    let search = |query: &str| -> Vec<&str> {
+        gallery
+            .entries
+            .iter()
+            .filter(|entry| entry.matches(query))
+            .map(|entry| entry.name.as_str())
+            .collect()
+    };
+    assert_eq!(search("").len(), 9);
+    assert_eq!(search("PALETTE"), ["plasma.glsl"]);
+    assert_eq!(search("quilez sdf"), ["default.glsl"]);
+    assert_eq!(search(".frag"), ["noise.frag"]);
+    assert_eq!(search("project"), ["rings.json"]);
+    assert_eq!(search("roe cc0"), Vec::<&str>::new());
+}
+
+#[test]
+fn thumbnails_are_drawn_for_egui_to_sample() {
+    use shadertoy_rs::gallery::THUMBNAIL_SIZE;
+    use shadertoy_rs::passes::{MediaCache, Program};
+    use shadertoy_rs::render::{headless_device, OffscreenRenderer, OFFSCREEN_FORMAT};
+    use shadertoy_rs::WgpuUniform;
+    let (device, queue) = match headless_device(true) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Skipping rendering test: {e}");
+            return;
+        }
+    };
+    let gallery = Gallery::scan(None);
+    let program = Program::compile(
+        &gallery.entries[0].project,
+        Path::new("."),
+        &mut MediaCache::default(),
+    )
+    .unwrap();
+    let [width, height] = THUMBNAIL_SIZE;
+    let mut renderer =
+        OffscreenRenderer::new(&device, &queue, &program, OFFSCREEN_FORMAT, width, height);
+    let target = renderer.create_srgb_target();
+    renderer.draw(&WgpuUniform::at_frame(0, 10, width, height), &target);
+    // Drawing leaves the renderer ready for the next frame.
+    let frame = renderer
+        .render(&WgpuUniform::at_frame(1, 10, width, height))
+        .unwrap();
+    assert!(frame.rgb().iter().any(|&c| c != 0));
+}
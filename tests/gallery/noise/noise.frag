@@ -0,0 +1,9 @@
+// Title: Value noise
+// Author: John Roe
+// Tags: noise
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    vec2 cell = floor(fragCoord / 8.0);
+    float n = fract(sin(dot(cell, vec2(12.9898, 78.233))) * 43758.5453);
+    fragColor = vec4(vec3(n), 1.0);
+}
@@ -0,0 +1,7 @@
+// Title: Rings
+// Tags: project
+void mainImage(out vec4 fragColor, in vec2 fragCoord)
+{
+    float d = length(fragCoord - 0.5 * iResolution.xy);
+    fragColor = vec4(vec3(0.5 + 0.5 * sin(d * 0.2 - iTime)), 1.0);
+}
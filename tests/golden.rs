@@ -0,0 +1,136 @@
+//! Golden-image tests: render sample shaders through the headless path on a
+//! software adapter and compare them with reference PNGs in `tests/golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to write new reference images instead.
+use shadertoy_rs::passes::{MediaCache, Program};
+use shadertoy_rs::project::Project;
+use shadertoy_rs::render::{headless_device, Frame, OffscreenRenderer, OFFSCREEN_FORMAT};
+use shadertoy_rs::WgpuUniform;
+use std::path::Path;
+
+const WIDTH: u32 = 96;
+const HEIGHT: u32 = 54;
+const FPS: u32 = 60;
+
+/// Largest per-pixel color distance (0 to 1) that still counts as equal.
+const PIXEL_TOLERANCE: f32 = 0.05;
+/// Share of pixels allowed to exceed `PIXEL_TOLERANCE`, for edges that
+/// rasterize differently between drivers.
+const OUTLIER_RATIO: f32 = 0.01;
+
+/// Weighted ("redmean") RGB distance, a cheap approximation of perceived
+/// color difference, scaled to 0..1.
+fn color_distance(a: [u8; 3], b: [u8; 3]) -> f32 {
+    let r_mean = (a[0] as f32 + b[0] as f32) / 2.0;
+    let [dr, dg, db] = [0, 1, 2].map(|c| a[c] as f32 - b[c] as f32);
+    let distance = ((2.0 + r_mean / 256.0) * dr * dr
+        + 4.0 * dg * dg
+        + (2.0 + (255.0 - r_mean) / 256.0) * db * db)
+        .sqrt();
+    distance / (3.0 * 255.0)
+}
+
+fn compare(name: &str, frame: &Frame, reference: &image::RgbImage) -> Result<(), String> {
+    if (frame.width, frame.height) != reference.dimensions() {
+        return Err(format!(
+            "{name}: size {}x{} differs from reference {:?}",
+            frame.width,
+            frame.height,
+            reference.dimensions()
+        ));
+    }
+    let rgb = frame.rgb();
+    let distances = rgb
+        .chunks_exact(3)
+        .zip(reference.pixels())
+        .map(|(a, b)| color_distance([a[0], a[1], a[2]], b.0))
+        .collect::<Vec<_>>();
+    let outliers = distances.iter().filter(|d| **d > PIXEL_TOLERANCE).count();
+    let max = distances.iter().copied().fold(0.0, f32::max);
+    if outliers as f32 > OUTLIER_RATIO * distances.len() as f32 {
+        return Err(format!(
+            "{name}: {outliers} of {} pixels differ from the reference (max distance {max:.3})",
+            distances.len()
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn golden_images() {
+    let (device, queue) = match headless_device(true) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Skipping golden-image tests: {e}");
+            return;
+        }
+    };
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let single = |source: &str| Project::single("shader.glsl", source);
+    let buffers = Project::load(&golden.join("buffers/buffers.json")).expect("project loads");
+    // Name, project, time of the frame to compare, and how many frames to
+    // render up to it, for projects with feedback.
+    let cases = [
+        (
+            "gradient",
+            single(include_str!("golden/gradient.glsl")),
+            0.5,
+            1,
+        ),
+        (
+            "circles",
+            single(include_str!("golden/circles.glsl")),
+            1.25,
+            1,
+        ),
+        (
+            "default",
+            single(include_str!("../src/app/examples/default.glsl")),
+            1.0,
+            1,
+        ),
+        ("buffers", buffers, 20.0 / FPS as f32, 21),
+    ];
+    let mut failures = Vec::new();
+    for (name, project, time, frames) in cases {
+        let program = Program::compile(
+            &project,
+            &golden.join("buffers"),
+            &mut MediaCache::default(),
+        )
+        .unwrap_or_else(|e| panic!("{name}: shaders failed to compile: {e:#}"));
+        let mut renderer =
+            OffscreenRenderer::new(&device, &queue, &program, OFFSCREEN_FORMAT, WIDTH, HEIGHT);
+        let last_frame = (time * FPS as f32).round() as u32;
+        let mut frame = None;
+        for index in last_frame + 1 - frames..=last_frame {
+            frame = Some(
+                renderer
+                    .render(&WgpuUniform::at_frame(index, FPS, WIDTH, HEIGHT))
+                    .unwrap_or_else(|e| panic!("{name}: rendering failed: {e}")),
+            );
+        }
+        let frame = frame.expect("at least one frame is rendered");
+
+        let path = golden.join(format!("{name}.png"));
+        if update {
+            image::save_buffer(
+                &path,
+                &frame.rgb(),
+                WIDTH,
+                HEIGHT,
+                image::ExtendedColorType::Rgb8,
+            )
+            .expect("reference image is written");
+            continue;
+        }
+        let reference = image::open(&path)
+            .unwrap_or_else(|e| panic!("{name}: missing reference {}: {e}", path.display()))
+            .to_rgb8();
+        if let Err(failure) = compare(name, &frame, &reference) {
+            failures.push(failure);
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
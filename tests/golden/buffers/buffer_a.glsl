@@ -0,0 +1,10 @@
+// A square moving up and to the right by one pixel per frame, on top of a
+// fading copy of the previous frame.
+void mainImage( out vec4 fragColor, in vec2 fragCoord )
+{
+    vec4 previous = texelFetch(iChannel0, ivec2(fragCoord), 0);
+    vec2 square = vec2(4.0 + iFrame);
+    vec2 d = abs(fragCoord - square);
+    float inside = step(max(d.x, d.y), 2.0);
+    fragColor = max(previous * 0.9, vec4(inside, 0.5 * inside, 0.0, 1.0));
+}
@@ -0,0 +1,11 @@
+// Buffer A on the left, the texture on the right. Both must come out with
+// their bottom row at the bottom.
+void mainImage( out vec4 fragColor, in vec2 fragCoord )
+{
+    vec2 uv = fragCoord / iResolution.xy;
+    if (uv.x < 0.5) {
+        fragColor = texture(iChannel0, uv);
+    } else {
+        fragColor = texture(iChannel1, vec2(uv.x * 2.0 - 1.0, uv.y));
+    }
+}
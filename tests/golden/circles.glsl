@@ -0,0 +1,8 @@
+// Anti-aliased rings moving with iTime, exercising smoothstep and fract.
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 p = (2.0 * fragCoord - iResolution.xy) / iResolution.y;
+    float d = length(p - vec2(0.3 * cos(iTime), 0.3 * sin(iTime)));
+    float rings = smoothstep(0.45, 0.5, fract(d * 4.0 - iTime));
+    vec3 color = mix(vec3(0.1, 0.2, 0.4), vec3(1.0, 0.8, 0.3), rings);
+    fragColor = vec4(color * exp(-d), 1.0);
+}
@@ -0,0 +1,82 @@
+//! GLSL syntax highlighting in the editor.
+use shadertoy_rs::highlight::{tokens, user_uniforms, Token};
+use shadertoy_rs::project::Project;
+
+/// The text and kind of every token that isn't plain.
+fn highlighted<'a>(code: &'a str, uniforms: &[String]) -> Vec<(&'a str, Token)> {
+    tokens(code, uniforms)
+        .into_iter()
+        .filter(|(_, token)| *token != Token::Plain)
+        .map(|(range, token)| (&code[range], token))
+        .collect()
+}
+
+#[test]
+fn glsl_words_are_told_apart() {
+    let code = "uniform float speed; // per second\n\
+                void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n\
+                \x20   vec2 uv = fragCoord / iResolution.xy;\n\
+                \x20   if (uv.x > .5e+1) discard;\n\
+                \x20   fragColor = texture(iChannel0, uv) * sin(iTime * speed) + gl_FragCoord;\n\
+                }\n";
+    let uniforms = vec!["speed".to_string()];
+    assert_eq!(
+        highlighted(code, &uniforms),
+        [
+            ("uniform", Token::Qualifier),
+            ("float", Token::Type),
+            ("speed", Token::UserUniform),
+            ("// per second", Token::Comment),
+            ("void", Token::Type),
+            ("out", Token::Qualifier),
+            ("vec4", Token::Type),
+            ("in", Token::Qualifier),
+            ("vec2", Token::Type),
+            ("vec2", Token::Type),
+            ("iResolution", Token::ShadertoyUniform),
+            ("if", Token::Keyword),
+            (".5e+1", Token::Number),
+            ("discard", Token::Keyword),
+            ("texture", Token::Builtin),
+            ("iChannel0", Token::ShadertoyUniform),
+            ("sin", Token::Builtin),
+            ("iTime", Token::ShadertoyUniform),
+            ("speed", Token::UserUniform),
+            ("gl_FragCoord", Token::Builtin),
+        ]
+    );
+}
+
+#[test]
+fn directives_and_comments_run_to_their_end() {
+    let code = "#define PI 3.14 // pi\n/* a\nb */ float x = 0x1F;\nx # y";
+    assert_eq!(
+        highlighted(code, &[]),
+        [
+            ("#define PI 3.14 ", Token::Preprocessor),
+            ("// pi", Token::Comment),
+            ("/* a\nb */", Token::Comment),
+            ("float", Token::Type),
+            ("0x1F", Token::Number),
+        ]
+    );
+}
+
+#[test]
+fn tokens_cover_the_code() {
+    let code = "// é\nvec3 café = vec3(1.0); /* unterminated";
+    let ranges: Vec<_> = tokens(code, &[]).into_iter().map(|(r, _)| r).collect();
+    assert_eq!(ranges.first().unwrap().start, 0);
+    assert_eq!(ranges.last().unwrap().end, code.len());
+    assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+}
+
+#[test]
+fn uniforms_come_from_declarations_and_values() {
+    let mut project = Project::single("a.glsl", "uniform vec3 tint;\nuniform float gains[4];\n");
+    project.uniforms.insert(
+        "speed".to_string(),
+        shadertoy_rs::project::UniformValue::Float(1.0),
+    );
+    assert_eq!(user_uniforms(&project), ["gains", "speed", "tint"]);
+}
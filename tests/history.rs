@@ -0,0 +1,105 @@
+//! Revision history: recording compiles, diffs and storage.
+use shadertoy_rs::history::{diff, Change, History, Thumbnail, COALESCE_SECONDS, MAX_REVISIONS};
+use shadertoy_rs::project::Project;
+
+fn project(code: &str) -> Project {
+    Project::single("shader.glsl", code)
+}
+
+#[test]
+fn compiles_become_revisions() {
+    let mut history = History::default();
+    history.record(&project("a"), "shader.glsl", None, 100);
+    // The same code again, e.g. after a template change, adds nothing.
+    history.record(&project("a"), "shader.glsl", None, 200);
+    history.record(&project("b"), "shader.glsl", None, 300);
+    // Typing right after a compile replaces the revision it made.
+    history.record(
+        &project("bc"),
+        "shader.glsl",
+        None,
+        300 + COALESCE_SECONDS - 1,
+    );
+
+    let codes: Vec<_> = history
+        .revisions()
+        .iter()
+        .map(|revision| revision.project().image().source.code.clone())
+        .collect();
+    assert_eq!(codes, ["a", "bc"]);
+    let ids: Vec<_> = history.revisions().iter().map(|r| r.id).collect();
+    assert!(ids[0] < ids[1]);
+}
+
+#[test]
+fn oldest_revisions_are_dropped() {
+    let mut history = History::default();
+    for i in 0..MAX_REVISIONS as u64 + 3 {
+        history.record(&project(&i.to_string()), "shader.glsl", None, i * 60);
+    }
+    assert_eq!(history.revisions().len(), MAX_REVISIONS);
+    assert_eq!(history.revisions()[0].project().image().source.code, "3");
+}
+
+#[test]
+fn thumbnails_wait_for_the_revision_to_settle() {
+    let mut history = History::default();
+    let first = history.record(&project("a"), "shader.glsl", None, 100);
+    assert_eq!(history.settled(100 + COALESCE_SECONDS - 1), None);
+    // Replaced while typing, so no thumbnail was needed for it.
+    let second = history.record(&project("ab"), "shader.glsl", None, 102);
+    assert_ne!(first, second);
+    assert_eq!(history.settled(102 + COALESCE_SECONDS), Some(second));
+
+    let thumbnail = Thumbnail {
+        width: 1,
+        height: 1,
+        rgb: vec![1, 2, 3],
+    };
+    history.set_thumbnail(second, thumbnail.clone());
+    assert_eq!(history.get(second).unwrap().thumbnail, Some(thumbnail));
+    // Recording the same project again keeps its revision and thumbnail.
+    assert_eq!(
+        history.record(&project("ab"), "shader.glsl", None, 200),
+        second
+    );
+    assert!(history.get(second).unwrap().thumbnail.is_some());
+}
+
+#[test]
+fn history_round_trips() {
+    let mut history = History::default();
+    let thumbnail = Thumbnail {
+        width: 2,
+        height: 1,
+        rgb: vec![0, 127, 255, 16, 32, 64],
+    };
+    history.record(
+        &project("void main() {}\n"),
+        "shader.glsl",
+        Some(thumbnail.clone()),
+        100,
+    );
+
+    let restored = History::parse(&history.to_json().unwrap()).unwrap();
+    let revision = &restored.revisions()[0];
+    assert_eq!(revision.project(), project("void main() {}\n"));
+    assert_eq!(revision.thumbnail.as_ref(), Some(&thumbnail));
+    assert_eq!(revision.time, 100);
+}
+
+#[test]
+fn diff_marks_changed_lines() {
+    let old = "float a = 1.0;\nfloat b = 2.0;\nfloat c = 3.0;\n";
+    let new = "float a = 1.0;\nfloat b = 2.5;\nfloat c = 3.0;\nfloat d = 4.0;\n";
+    assert_eq!(
+        diff(old, new),
+        [
+            Change::Same("float a = 1.0;"),
+            Change::Removed("float b = 2.0;"),
+            Change::Added("float b = 2.5;"),
+            Change::Same("float c = 3.0;"),
+            Change::Added("float d = 4.0;"),
+        ]
+    );
+}
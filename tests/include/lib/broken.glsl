@@ -0,0 +1,3 @@
+float broken(vec2 p) {
+    return undefined_name * p.x;
+}
@@ -0,0 +1,11 @@
+// Signed distance functions.
+#include "util.glsl"
+
+float sdCircle(vec2 p, float r) {
+    return length(p) - r;
+}
+
+float sdBox(vec2 p, vec2 b) {
+    vec2 d = abs(p) - b;
+    return length(max(d, 0.0)) + min(max2(d), 0.0);
+}
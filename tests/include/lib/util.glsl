@@ -0,0 +1,3 @@
+float max2(vec2 v) {
+    return max(v.x, v.y);
+}
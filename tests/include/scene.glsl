@@ -0,0 +1,5 @@
+#include <sdf>
+
+float scene(vec2 p) {
+    return min(sdCircle(p, 0.5), sdBox(p - 0.5, vec2(0.25)));
+}
@@ -0,0 +1,87 @@
+//! Going to lines and definitions in the code editor.
+use shadertoy_rs::include::Includes;
+use shadertoy_rs::load_pass_shader;
+use shadertoy_rs::navigate::{definition, diagnostics, line_range, source_index, Diagnostic};
+use shadertoy_rs::project::{PassKind, Project, Source};
+
+fn project() -> Project {
+    let mut project = Project::single(
+        "image.glsl",
+        "float sdf(vec3 p);\n\
+         void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n\
+         \x20   fragColor = vec4(sdf(vec3(fragCoord, 0.0)) * SCALE);\n\
+         }\n\
+         float sdf(vec3 p) { return length(p) - RADIUS; }\n",
+    );
+    project.add_pass(PassKind::BufferA, "#define SCALE 2.0\n");
+    project.common = Some(Source::new(
+        "common.glsl",
+        "// Shared.\n#define RADIUS 1.0\n",
+    ));
+    project
+}
+
+#[test]
+fn compile_errors_name_their_line() {
+    let error = load_pass_shader(
+        PassKind::BufferA,
+        "float f() { return 1.0; }\n",
+        "void mainImage(out vec4 c, in vec2 p) {\n    c = vec4(undefined_name);\n}\n",
+        &Includes::default(),
+    )
+    .err()
+    .unwrap();
+    let found = diagnostics(&format!("{error:#}"));
+    assert_eq!(found.len(), 1, "{error:#}");
+    assert_eq!((found[0].file.as_str(), found[0].line), ("Buffer A", 2));
+
+    let output = "Image:12: error: 'x' : undeclared identifier\n\
+                  lib/noise.glsl:3: warning: unused\n\
+                  2 errors generated.\n";
+    assert_eq!(
+        diagnostics(output)[1],
+        Diagnostic {
+            file: "lib/noise.glsl".to_string(),
+            line: 3,
+            message: "warning: unused".to_string(),
+        }
+    );
+    assert_eq!(diagnostics(output).len(), 2);
+}
+
+#[test]
+fn sources_are_found_by_name() {
+    let project = project();
+    assert_eq!(source_index(&project, "Common"), Some(0));
+    // Buffers come before the image pass.
+    assert_eq!(source_index(&project, "Buffer A"), Some(1));
+    assert_eq!(source_index(&project, "Image"), Some(2));
+    assert_eq!(source_index(&project, "lib/noise.glsl"), None);
+}
+
+#[test]
+fn definitions_win_over_prototypes() {
+    let project = project();
+    let code = |source: usize| &project.sources().nth(source).unwrap().code;
+    let (source, range) = definition(&project, "sdf").unwrap();
+    assert_eq!(source, 2);
+    assert_eq!(range.start, code(2).rfind("sdf(vec3 p) {").unwrap());
+    assert_eq!(&code(2)[range], "sdf");
+    let (source, range) = definition(&project, "RADIUS").unwrap();
+    assert_eq!((source, &code(source)[range]), (0, "RADIUS"));
+    assert_eq!(
+        definition(&project, "SCALE").map(|(source, _)| source),
+        Some(1)
+    );
+    assert_eq!(definition(&project, "length"), None);
+}
+
+#[test]
+fn lines_are_counted_from_one() {
+    let code = "a\r\nbc\n\nd";
+    assert_eq!(line_range(code, 1), Some(0..1));
+    assert_eq!(line_range(code, 2), Some(3..5));
+    assert_eq!(line_range(code, 3), Some(6..6));
+    assert_eq!(line_range(code, 4), Some(7..8));
+    assert_eq!(line_range(code, 5), None);
+}
@@ -0,0 +1,71 @@
+//! Project manifests: exact round trips, versioning and relative paths.
+use common::temp_dir;
+use shadertoy_rs::project::{ChannelInput, PassKind, Project, VERSION};
+use std::path::PathBuf;
+
+mod common;
+
+fn fixture() -> PathBuf {
+    common::buffers_file("buffers.json")
+}
+
+#[test]
+fn manifest_round_trips_exactly() {
+    let json = std::fs::read_to_string(fixture()).unwrap();
+    let project = Project::from_manifest(&json).unwrap();
+    assert_eq!(project.to_manifest().unwrap(), json);
+}
+
+#[test]
+fn save_writes_back_what_was_loaded() {
+    let project = Project::load(&fixture()).unwrap();
+    assert!(project
+        .pass(PassKind::BufferA)
+        .unwrap()
+        .source
+        .code
+        .contains("mainImage"));
+
+    let dir = temp_dir("save");
+    let path = dir.join("buffers.json");
+    project.save(&path).unwrap();
+    for name in ["buffers.json", "buffer_a.glsl", "image.glsl"] {
+        let original = fixture().with_file_name(name);
+        assert_eq!(
+            std::fs::read(dir.join(name)).unwrap(),
+            std::fs::read(original).unwrap(),
+            "{name} differs"
+        );
+    }
+    assert_eq!(Project::load(&path).unwrap(), project);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let json = std::fs::read_to_string(fixture()).unwrap().replacen(
+        "\"version\": 1",
+        &format!("\"version\": {}", VERSION + 1),
+        1,
+    );
+    let error = Project::from_manifest(&json).unwrap_err().to_string();
+    assert!(error.contains("newer"), "{error}");
+}
+
+#[test]
+fn relocating_keeps_assets_reachable() {
+    let mut project = Project::load(&fixture()).unwrap();
+    let from = fixture().parent().unwrap().to_path_buf();
+    let to = temp_dir("relocate");
+    project.relocate(&from, &to);
+    let image = project.image();
+    let Some(ChannelInput::Texture { path }) = image.channels[1].as_ref().map(|c| &c.input) else {
+        panic!("image pass reads a texture on iChannel1");
+    };
+    assert!(
+        to.join(path).exists(),
+        "{} is not reachable",
+        path.display()
+    );
+    std::fs::remove_dir_all(to).unwrap();
+}
@@ -0,0 +1,98 @@
+//! Autosave snapshots and recovery after a session that didn't shut down.
+use common::temp_dir;
+use shadertoy_rs::project::PassKind;
+use shadertoy_rs::recovery::Recovery;
+use shadertoy_rs::state::State;
+
+mod common;
+
+fn edited_state() -> State {
+    let mut project = common::buffers_project();
+    let image = project.pass_mut(PassKind::Image).unwrap();
+    image.source.code.push_str("// unsaved edit\n");
+    let mut state = State::new(&project);
+    state.dirty = true;
+    state
+}
+
+/// The id of a process that has exited.
+fn exited_pid() -> u32 {
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+        .arg("--list")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    child.wait().unwrap();
+    child.id()
+}
+
+#[test]
+fn crashed_session_is_recovered() {
+    let dir = temp_dir("recovery-crash");
+    let state = edited_state();
+    let session = Recovery::in_directory(&dir, exited_pid());
+    assert!(session.start().unwrap().is_none());
+    session.snapshot(&state).unwrap();
+    // No `finish`: the session crashed.
+
+    let next = Recovery::in_directory(&dir, std::process::id());
+    let recovered = next.start().unwrap().expect("snapshot is offered");
+    assert!(recovered.dirty);
+    assert_eq!(recovered.project(), state.project());
+    // Taken over by the next session, so it's offered once.
+    let after = Recovery::in_directory(&dir, exited_pid());
+    assert!(after.start().unwrap().is_none());
+}
+
+#[test]
+#[cfg(unix)]
+fn running_session_is_left_alone() {
+    let dir = temp_dir("recovery-running");
+    let mut running = std::process::Command::new("sleep")
+        .arg("60")
+        .spawn()
+        .unwrap();
+    let first = Recovery::in_directory(&dir, running.id());
+    first.start().unwrap();
+    first.snapshot(&edited_state()).unwrap();
+
+    let second = Recovery::in_directory(&dir, std::process::id());
+    assert!(second.start().unwrap().is_none());
+    second.snapshot(&edited_state()).unwrap();
+    second.finish().unwrap();
+
+    // The first session's snapshot survives the second one shutting down,
+    // and is offered once its process is gone.
+    running.kill().unwrap();
+    running.wait().unwrap();
+    let third = Recovery::in_directory(&dir, std::process::id());
+    assert!(third.start().unwrap().is_some());
+}
+
+#[test]
+fn clean_shutdown_leaves_nothing_to_recover() {
+    let dir = temp_dir("recovery-clean");
+    let session = Recovery::in_directory(&dir, std::process::id());
+    session.start().unwrap();
+    session.snapshot(&edited_state()).unwrap();
+    session.finish().unwrap();
+
+    assert!(Recovery::in_directory(&dir, std::process::id())
+        .start()
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn discarded_snapshot_is_not_offered() {
+    let dir = temp_dir("recovery-discard");
+    let session = Recovery::in_directory(&dir, std::process::id());
+    session.start().unwrap();
+    session.snapshot(&edited_state()).unwrap();
+    session.discard().unwrap();
+
+    assert!(Recovery::in_directory(&dir, std::process::id())
+        .start()
+        .unwrap()
+        .is_none());
+}
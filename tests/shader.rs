@@ -0,0 +1,183 @@
+//! Compiling pass code into the fragment template, with common code and
+//! includes.
+use shadertoy_rs::examples::EXAMPLES;
+use shadertoy_rs::include::Includes;
+use shadertoy_rs::passes::Program;
+use shadertoy_rs::project::PassKind;
+use shadertoy_rs::render::{headless_device, OffscreenRenderer, OFFSCREEN_FORMAT};
+use shadertoy_rs::{
+    included_files, load_pass_shader, load_pass_shader_with_naga, load_vertex_shader, WgpuUniform,
+};
+use std::path::Path;
+
+const COMMON: &str = "\
+// Shared helpers.
+vec3 palette(float t) {
+    return 0.5 + 0.5 * cos(6.28318 * (t + vec3(0.0, 0.33, 0.67)));
+}
+";
+
+const IMAGE: &str = "\
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(palette(fragCoord.x / iResolution.x), 1.0);
+}
+";
+
+#[test]
+fn common_code_is_shared() {
+    for kind in [PassKind::BufferA, PassKind::Image] {
+        let shader = load_pass_shader(kind, COMMON, IMAGE, &Includes::default()).unwrap();
+        assert!(!shader.spirv.is_empty());
+    }
+}
+
+#[test]
+fn errors_point_into_common() {
+    let common = COMMON.replace("0.5 + 0.5", "0.5 + undefined_name");
+    let error = load_pass_shader(PassKind::Image, &common, IMAGE, &Includes::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Common:3:"), "{error}");
+    assert!(!error.contains("shader.glsl"), "{error}");
+}
+
+#[test]
+fn errors_point_into_the_pass() {
+    let image = IMAGE.replace("fragColor = ", "fragColor = undefined_name + ");
+    let error = load_pass_shader(PassKind::BufferA, COMMON, &image, &Includes::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Buffer A:2:"), "{error}");
+}
+
+fn includes() -> Includes {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/include");
+    Includes::new(&directory, &["lib".into()])
+}
+
+#[test]
+fn includes_are_resolved_once() {
+    let image = "\
+#include \"scene.glsl\"
+#include <sdf.glsl>
+
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(vec3(scene(fragCoord / iResolution.xy)), 1.0);
+}
+";
+    let shader = load_pass_shader(PassKind::Image, "#include <sdf>\n", image, &includes()).unwrap();
+    let mut names: Vec<_> = shader
+        .includes
+        .iter()
+        .map(|path| path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["scene.glsl", "sdf.glsl", "util.glsl"]);
+}
+
+#[test]
+fn errors_point_into_included_files() {
+    let image = "\
+// The library is broken.
+#include <broken>
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(broken(fragCoord));
+}
+";
+    let error = load_pass_shader(PassKind::Image, "", image, &includes())
+        .err()
+        .unwrap();
+    // The broken file is still watched, to compile again once it's fixed.
+    let files = included_files(&error);
+    assert!(
+        files.len() == 1 && files[0].ends_with("lib/broken.glsl"),
+        "{files:?}"
+    );
+    let error = error.to_string();
+    assert!(error.contains("broken:2:"), "{error}");
+
+    let error = load_pass_shader(PassKind::Image, "", "#include <missing>\n", &includes())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(
+        error.contains("Image:1:") && error.contains("<missing>"),
+        "{error}"
+    );
+}
+
+#[test]
+fn naga_compiles_the_examples() {
+    for example in EXAMPLES {
+        let project = example.project();
+        let common = project.common.as_ref().map_or("", |common| &common.code);
+        for pass in &project.passes {
+            if pass.kind.is_buffer() || pass.kind == PassKind::Image {
+                load_pass_shader_with_naga(
+                    pass.kind,
+                    common,
+                    &pass.source.code,
+                    &Includes::default(),
+                )
+                .unwrap_or_else(|e| panic!("{} {}: {e:#}", example.file_name, pass.kind.name()));
+            }
+        }
+    }
+}
+
+#[test]
+fn naga_errors_point_into_the_code() {
+    let image = IMAGE.replace("fragColor = ", "fragColor = undefined_name + ");
+    for common in [COMMON, ""] {
+        let error =
+            load_pass_shader_with_naga(PassKind::Image, common, &image, &Includes::default())
+                .err()
+                .unwrap()
+                .to_string();
+        assert!(error.contains("Image:2:"), "{error}");
+    }
+    let common = COMMON.replace("vec3 palette", "vec3 palette(float t) {} vec3 palette");
+    let error = load_pass_shader_with_naga(PassKind::Image, &common, IMAGE, &Includes::default())
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(error.contains("Common:"), "{error}");
+}
+
+#[test]
+fn naga_renders_like_shaderc() {
+    let (device, queue) = match headless_device(true) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Skipping rendering test: {e}");
+            return;
+        }
+    };
+    let image = "\
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec3 color = palette(fragCoord.x / iResolution.x);
+    fragColor = vec4(color, fragCoord.y < 2.0 ? 1.0 : 0.5);
+}
+";
+    let frames: Vec<Vec<u8>> = [load_pass_shader, load_pass_shader_with_naga]
+        .into_iter()
+        .map(|load| {
+            let fragment = load(PassKind::Image, COMMON, image, &Includes::default())
+                .unwrap_or_else(|e| panic!("{e:#}"));
+            let program = Program::single(load_vertex_shader().unwrap(), fragment.spirv);
+            let mut renderer =
+                OffscreenRenderer::new(&device, &queue, &program, OFFSCREEN_FORMAT, 8, 4);
+            let frame = renderer
+                .render(&WgpuUniform::at_frame(0, 60, 8, 4))
+                .unwrap();
+            frame.rgb()
+        })
+        .collect();
+    assert_eq!(frames[0].len(), frames[1].len());
+    assert!(frames[0].iter().any(|&value| value > 64), "{frames:?}");
+    for (shaderc, naga) in frames[0].iter().zip(&frames[1]) {
+        assert!(shaderc.abs_diff(*naga) <= 1, "{frames:?}");
+    }
+}
@@ -0,0 +1,148 @@
+//! Importing Shadertoy JSON exports.
+use shadertoy_rs::project::{ChannelInput, Filter, PassKind, Wrap};
+use shadertoy_rs::shadertoy;
+use std::path::{Path, PathBuf};
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/shadertoy")
+}
+
+fn import_fixture() -> shadertoy::Import {
+    let json = std::fs::read_to_string(fixture().join("export.json")).unwrap();
+    let mut imports = shadertoy::import(&json, &fixture()).unwrap();
+    assert_eq!(imports.len(), 1);
+    imports.remove(0)
+}
+
+#[test]
+fn passes_and_channels_are_mapped() {
+    let import = import_fixture();
+    let project = &import.project;
+    assert_eq!(import.id.as_deref(), Some("XsX3Rn"));
+    assert_eq!(project.name, "Feedback test");
+    assert_eq!(project.author, "someone");
+    assert!(project.common.as_ref().unwrap().code.contains("shade"));
+    let kinds: Vec<_> = project.passes.iter().map(|pass| pass.kind).collect();
+    assert_eq!(kinds, [PassKind::BufferA, PassKind::Image]);
+
+    let buffer = project.pass(PassKind::BufferA).unwrap();
+    let feedback = buffer.channels[0].as_ref().unwrap();
+    assert_eq!(
+        feedback.input,
+        ChannelInput::Buffer {
+            pass: PassKind::BufferA
+        }
+    );
+    assert_eq!(feedback.sampler.filter, Filter::Linear);
+    assert_eq!(feedback.sampler.wrap, Wrap::Clamp);
+    // Found next to the export by its file name.
+    let texture = buffer.channels[1].as_ref().unwrap();
+    assert_eq!(
+        texture.input,
+        ChannelInput::Texture {
+            path: "quadrants.png".into()
+        }
+    );
+    assert_eq!(texture.sampler.filter, Filter::Nearest);
+    assert!(!texture.sampler.vflip);
+}
+
+#[test]
+fn unavailable_media_is_reported() {
+    let import = import_fixture();
+    let image = import.project.image();
+    assert_eq!(
+        image.channels[2].as_ref().unwrap().input,
+        ChannelInput::Texture {
+            path: "media/a/0123456789abcdef.png".into()
+        }
+    );
+    assert!(matches!(
+        image.channels[3].as_ref().unwrap().input,
+        ChannelInput::Texture { .. }
+    ));
+    assert_eq!(import.warnings.len(), 2, "{:?}", import.warnings);
+    assert!(import.warnings[0].contains("0123456789abcdef.png"));
+    assert!(import.warnings[1].contains("webcam"));
+}
+
+#[test]
+fn projects_are_rejected() {
+    let manifest =
+        std::fs::read_to_string(fixture().join("../golden/buffers/buffers.json")).unwrap();
+    assert!(!shadertoy::is_shadertoy_json(&manifest));
+    assert!(shadertoy::import(&manifest, &fixture()).is_err());
+}
+
+#[test]
+fn exports_import_back() {
+    let project = import_fixture().project;
+    let export = shadertoy::export(&project).unwrap();
+    let mut imports = shadertoy::import(&export.json, &fixture()).unwrap();
+    let round_trip = imports.remove(0).project;
+    assert_eq!(round_trip.passes.len(), project.passes.len());
+    for (pass, original) in round_trip.passes.iter().zip(&project.passes) {
+        assert_eq!(pass.kind, original.kind);
+        assert_eq!(pass.source.code.trim_end(), original.source.code);
+    }
+    assert_eq!(
+        round_trip.pass(PassKind::BufferA).unwrap().channels[0],
+        project.pass(PassKind::BufferA).unwrap().channels[0]
+    );
+    // The local texture and the webcam placeholder have no Shadertoy media.
+    assert_eq!(export.warnings.len(), 2, "{:?}", export.warnings);
+    assert!(export.warnings[0].contains("quadrants.png"));
+}
+
+#[test]
+fn scaffolding_and_uniforms_are_rewritten() {
+    let code = "\
+#version 450
+layout (binding = 0, std140) uniform Uniforms {
+    vec2 iResolution;
+};
+uniform float speed; // @range(0, 4)
+uniform vec3 tint;
+void mainImage(out vec4 o, in vec2 p) { o = vec4(tint * speed, 1.0); }
+";
+    let mut project = shadertoy_rs::project::Project::single("image.glsl", code);
+    project.uniforms.insert(
+        "speed".into(),
+        shadertoy_rs::project::UniformValue::Float(2.0),
+    );
+    let export = shadertoy::export(&project).unwrap();
+    let imported = shadertoy::import(&export.json, &fixture()).unwrap();
+    assert_eq!(
+        imported[0].project.image().source.code,
+        "\
+const float speed = 2.0; // @range(0, 4)
+const vec3 tint = vec3(0.0);
+void mainImage(out vec4 o, in vec2 p) { o = vec4(tint * speed, 1.0); }
+"
+    );
+    assert_eq!(export.warnings.len(), 2, "{:?}", export.warnings);
+    assert!(export.warnings.iter().all(|w| w.contains("constant")));
+}
+
+#[test]
+fn non_finite_uniforms_are_not_made_constants() {
+    let code = "uniform float speed;\nuniform vec2 offset;\n";
+    let mut project = shadertoy_rs::project::Project::single("image.glsl", code);
+    project.uniforms.insert(
+        "speed".into(),
+        shadertoy_rs::project::UniformValue::Float(f32::INFINITY),
+    );
+    project.uniforms.insert(
+        "offset".into(),
+        shadertoy_rs::project::UniformValue::Vector(vec![1.0, f32::NAN]),
+    );
+    let export = shadertoy::export(&project).unwrap();
+    let imported = shadertoy::import(&export.json, &fixture()).unwrap();
+    // Kept as they are, with a warning, rather than as `inf.0` or `NaN.0`.
+    assert_eq!(imported[0].project.image().source.code, code);
+    assert_eq!(export.warnings.len(), 2, "{:?}", export.warnings);
+    assert!(export
+        .warnings
+        .iter()
+        .all(|w| w.contains("no Shadertoy equivalent") && !w.contains("constant")));
+}
@@ -0,0 +1,44 @@
+//! Share links carrying the project in the URL fragment.
+use shadertoy_rs::project::{Project, UniformValue};
+use shadertoy_rs::share::{decode, encode, PREFIX};
+
+mod common;
+
+#[test]
+fn shader_round_trips() {
+    let project = Project::single("plasma.glsl", "void mainImage(out vec4 c, in vec2 p) {}\n");
+    let fragment = encode("plasma.glsl", &project).unwrap();
+    assert!(fragment.starts_with(PREFIX));
+    assert!(fragment
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || b"=-_".contains(&b)));
+    let (name, shared) = decode(&format!("#{fragment}")).unwrap().unwrap();
+    assert_eq!(name, "plasma.glsl");
+    assert_eq!(shared, project);
+}
+
+#[test]
+fn project_round_trips() {
+    let mut project = common::buffers_project();
+    project
+        .uniforms
+        .insert("speed".to_string(), UniformValue::Float(2.5));
+    let fragment = encode("buffers.json", &project).unwrap();
+    let (name, shared) = decode(&fragment).unwrap().unwrap();
+    assert_eq!(name, "buffers.json");
+    assert_eq!(shared, project);
+}
+
+#[test]
+fn other_fragments_are_ignored() {
+    assert!(decode("").unwrap().is_none());
+    assert!(decode("#section-2").unwrap().is_none());
+}
+
+#[test]
+fn broken_links_are_rejected() {
+    assert!(decode(&format!("#{PREFIX}not base64!")).is_err());
+    assert!(decode(&format!("#{PREFIX}AAAA")).is_err());
+    let fragment = encode("a.glsl", &Project::single("a.glsl", "// a")).unwrap();
+    assert!(decode(&fragment[..fragment.len() - 4]).is_err());
+}
@@ -0,0 +1,60 @@
+//! Editor state kept in `eframe::Storage` between runs.
+use shadertoy_rs::project::PassKind;
+use shadertoy_rs::state::{State, KEY};
+use std::collections::HashMap;
+
+mod common;
+
+#[derive(Default)]
+struct MemoryStorage(HashMap<String, String>);
+
+impl eframe::Storage for MemoryStorage {
+    fn get_string(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+
+    fn set_string(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), value);
+    }
+
+    fn flush(&mut self) {}
+}
+
+#[test]
+fn state_round_trips() {
+    let mut project = common::buffers_project();
+    let image = project.pass_mut(PassKind::Image).unwrap();
+    image.source.code.push_str("// unsaved edit\n");
+    let mut state = State::new(&project);
+    state.shader_editor = false;
+    state.dirty = true;
+    state.file_path = Some("buffers.json".into());
+
+    let mut storage = MemoryStorage::default();
+    state.save(&mut storage);
+    let restored = State::load(&storage).unwrap().unwrap();
+    assert_eq!(restored.project(), project);
+    assert!(!restored.shader_editor);
+    assert!(restored.dirty);
+    assert_eq!(restored.file_path, state.file_path);
+}
+
+#[test]
+fn missing_state_is_not_an_error() {
+    assert!(State::load(&MemoryStorage::default()).unwrap().is_none());
+}
+
+#[test]
+fn corrupt_state_is_rejected() {
+    let mut storage = MemoryStorage::default();
+    storage
+        .0
+        .insert(KEY.to_string(), "{\"version\": 1, \"project\": [".into());
+    assert!(State::load(&storage).is_err());
+
+    // Readable, but the code doesn't fit the project.
+    let mut state = State::new(&common::buffers_project());
+    state.code.pop();
+    state.save(&mut storage);
+    assert!(State::load(&storage).is_err());
+}
@@ -0,0 +1,83 @@
+//! Alt-dragging number literals in the editor.
+use shadertoy_rs::include::Includes;
+use shadertoy_rs::load_pass_shader;
+use shadertoy_rs::project::PassKind;
+use shadertoy_rs::tweak::{Kind, Literal};
+
+/// The literal at the first occurrence of `needle` in `code`, with its text.
+fn literal_at<'a>(code: &'a str, needle: &str) -> Option<(&'a str, Literal)> {
+    let literal = Literal::at(code, code.find(needle).unwrap())?;
+    Some((&code[literal.range.clone()], literal))
+}
+
+#[test]
+fn decimal_literals_are_found() {
+    let code = "float a = 1.50 * b - 2.0f + x[3] * 4u; vec2 c = vec2(-0.25, 1.);";
+    let (text, literal) = literal_at(code, "1.50").unwrap();
+    assert_eq!(
+        (text, literal.kind, literal.value),
+        ("1.50", Kind::Float { decimals: 2 }, 1.5)
+    );
+    assert!((literal.step() - 0.01).abs() < 1e-12);
+    // Subtracted, so the minus isn't the literal's.
+    let (text, _) = literal_at(code, "2.0f").unwrap();
+    assert_eq!(text, "2.0f");
+    let (text, literal) = literal_at(code, "3]").unwrap();
+    assert_eq!((text, literal.kind, literal.step()), ("3", Kind::Int, 1.0));
+    let (text, literal) = literal_at(code, "4u").unwrap();
+    assert_eq!((text, literal.kind), ("4u", Kind::Uint));
+    let (text, literal) = literal_at(code, "0.25").unwrap();
+    assert_eq!((text, literal.value), ("-0.25", -0.25));
+    let (text, literal) = literal_at(code, "1.)").unwrap();
+    assert_eq!((text, literal.kind), ("1.", Kind::Float { decimals: 0 }));
+}
+
+#[test]
+fn other_literal_forms_are_left_alone() {
+    for code in ["0x1F", "017", "1e3", "2.5e-1", "1.0lf", "name2"] {
+        assert!(Literal::at(code, 0).is_none(), "{code}");
+    }
+}
+
+#[test]
+fn values_are_written_like_the_literal() {
+    let mut code = "x = 1.50 * b - 2.0f + 3 - 1.;".to_string();
+    let (_, mut literal) = literal_at(&code, "1.50").unwrap();
+    literal.write(&mut code, 1.5 + 0.01 * 7.0);
+    assert_eq!(code, "x = 1.57 * b - 2.0f + 3 - 1.;");
+    literal.write(&mut code, -0.0001);
+    assert_eq!(code, "x = 0.00 * b - 2.0f + 3 - 1.;");
+    let (_, mut literal) = literal_at(&code, "2.0f").unwrap();
+    literal.write(&mut code, -1.0);
+    assert_eq!(code, "x = 0.00 * b - (-1.0f) + 3 - 1.;");
+    let (_, mut literal) = literal_at(&code, "3").unwrap();
+    literal.write(&mut code, 12.0);
+    assert_eq!(code, "x = 0.00 * b - (-1.0f) + 12 - 1.;");
+    let (_, mut literal) = literal_at(&code, "1.;").unwrap();
+    literal.write(&mut code, 4.0);
+    assert_eq!(code, "x = 0.00 * b - (-1.0f) + 12 - 4.;");
+}
+
+#[test]
+fn promoted_literals_read_the_uniform() {
+    let image = "\
+const int STEPS = 4;
+float weights[3];
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    float v = 0.0;
+    for (int i = 0; i < 8; i++) v += 0.125;
+    fragColor = vec4(v * 2.0, -1.0, float(STEPS), 1.0);
+}
+";
+    let compile = |needle: &str| {
+        let (_, literal) = literal_at(image, needle).unwrap();
+        let mut code = image.to_string();
+        code.replace_range(literal.range.clone(), &literal.expression());
+        load_pass_shader(PassKind::Image, "", &code, &Includes::default())
+    };
+    for needle in ["0.125", "2.0", "1.0,", "8;"] {
+        assert!(compile(needle).is_ok(), "{needle}");
+    }
+    // Array sizes have to be constant.
+    assert!(compile("3]").is_err());
+}
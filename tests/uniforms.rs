@@ -0,0 +1,123 @@
+//! Uniforms declared in a shader's own code, with values from the project.
+use shadertoy_rs::include::Includes;
+use shadertoy_rs::load_pass_shader;
+use shadertoy_rs::passes::{MediaCache, Program};
+use shadertoy_rs::project::{PassKind, Project, UniformValue};
+use shadertoy_rs::render::{headless_device, OffscreenRenderer, OFFSCREEN_FORMAT};
+use shadertoy_rs::uniforms::{declarations, strip, Layout, UniformType};
+use shadertoy_rs::WgpuUniform;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const SHADER: &str = "\
+uniform float speed;
+uniform bool enabled; // Toggled in the editor.
+uniform vec3 tint;
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    fragColor = vec4(enabled ? tint * speed : vec3(0.0), 1.0);
+}
+";
+
+#[test]
+fn declarations_are_taken_out_of_the_code() {
+    let declared = declarations(SHADER);
+    let found: Vec<(&str, UniformType)> =
+        declared.iter().map(|d| (d.name.as_str(), d.ty)).collect();
+    assert_eq!(
+        found,
+        [
+            ("speed", UniformType::Float),
+            ("enabled", UniformType::Bool),
+            ("tint", UniformType::Vec3),
+        ]
+    );
+    let stripped = strip(SHADER);
+    assert_eq!(stripped.lines().count(), SHADER.lines().count());
+    assert!(stripped.starts_with("\n#define enabled (_enabled != 0u)\n\nvoid mainImage"));
+    // Samplers and uniforms sharing a line with other code are left alone.
+    assert!(declarations("uniform sampler2D tex;\nuniform float a; float b;\n").is_empty());
+}
+
+#[test]
+fn values_are_packed_by_the_std140_rules() {
+    let layout = Layout::new(&declarations(SHADER)).unwrap();
+    assert_eq!(layout.block(), "float speed; uint _enabled; vec3 tint;");
+    assert_eq!(layout.size(), 32);
+    let values = BTreeMap::from([
+        ("speed".to_string(), UniformValue::Float(0.5)),
+        ("enabled".to_string(), UniformValue::Bool(true)),
+        // Stored as another type, so converted.
+        ("tint".to_string(), UniformValue::Float(2.0)),
+    ]);
+    let bytes = layout.pack(&values);
+    let word = |offset: usize| <[u8; 4]>::try_from(&bytes[offset..offset + 4]).unwrap();
+    assert_eq!(f32::from_ne_bytes(word(0)), 0.5);
+    assert_eq!(u32::from_ne_bytes(word(4)), 1);
+    assert_eq!(f32::from_ne_bytes(word(16)), 2.0);
+    assert_eq!(f32::from_ne_bytes(word(20)), 0.0);
+    // Uniforms without a value are zero.
+    assert!(layout.pack(&BTreeMap::new()).iter().all(|&b| b == 0));
+
+    let conflicting = declarations("uniform float a;\nuniform int a;\n");
+    assert!(Layout::new(&conflicting).is_err());
+}
+
+#[test]
+fn declared_uniforms_compile() {
+    let shader = load_pass_shader(
+        PassKind::Image,
+        "uniform float speed;\n",
+        SHADER,
+        &Includes::default(),
+    )
+    .unwrap_or_else(|e| panic!("{e:#}"));
+    assert_eq!(shader.uniforms, Layout::new(&declarations(SHADER)).unwrap());
+    // Errors still point at the right line.
+    let error = load_pass_shader(
+        PassKind::Image,
+        "",
+        "uniform float speed;\nvoid mainImage(out vec4 c, in vec2 p) {\n    c = vec4(undefined_x);\n}\n",
+        &Includes::default(),
+    )
+    .err()
+    .unwrap();
+    assert!(format!("{error:#}").contains("Image:3:"), "{error:#}");
+}
+
+#[test]
+fn values_reach_the_shader() {
+    let (device, queue) = match headless_device(true) {
+        Ok(device) => device,
+        Err(e) => {
+            eprintln!("Skipping rendering test: {e}");
+            return;
+        }
+    };
+    let mut project = Project::single("shader.glsl", SHADER);
+    project.uniforms = BTreeMap::from([
+        ("speed".to_string(), UniformValue::Float(0.5)),
+        ("enabled".to_string(), UniformValue::Bool(true)),
+        (
+            "tint".to_string(),
+            UniformValue::Vector(vec![1.0, 0.0, 2.0]),
+        ),
+    ]);
+    let program = Program::compile(&project, Path::new("."), &mut MediaCache::default())
+        .unwrap_or_else(|e| panic!("{e:#}"));
+    let mut renderer = OffscreenRenderer::new(&device, &queue, &program, OFFSCREEN_FORMAT, 4, 4);
+    let frame = renderer
+        .render(&WgpuUniform::at_frame(0, 60, 4, 4))
+        .unwrap();
+    let rgb = frame.rgb();
+    assert!(rgb[0].abs_diff(128) <= 2, "{:?}", &rgb[..3]);
+    assert_eq!((rgb[1], rgb[2]), (0, 255));
+
+    project
+        .uniforms
+        .insert("enabled".to_string(), UniformValue::Bool(false));
+    renderer.set_uniform_values(&project.uniforms);
+    let frame = renderer
+        .render(&WgpuUniform::at_frame(1, 60, 4, 4))
+        .unwrap();
+    assert_eq!(&frame.rgb()[..3], [0, 0, 0]);
+}
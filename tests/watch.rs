@@ -0,0 +1,64 @@
+//! Watching files for changes made outside the editor.
+use shadertoy_rs::watch::{normalize, FileWatcher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+mod common;
+
+/// An empty directory, as the watcher names it.
+fn temp_dir(name: &str) -> PathBuf {
+    common::temp_dir(name).canonicalize().unwrap()
+}
+
+/// The watched files that change within a few seconds.
+fn wait_for_changes(watcher: &mut FileWatcher) -> Vec<PathBuf> {
+    let start = Instant::now();
+    loop {
+        let changed = watcher.changed_files();
+        if !changed.is_empty() || start.elapsed() > Duration::from_secs(5) {
+            return changed;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn directories_are_watched_while_any_of_their_files_is() {
+    let dir = temp_dir("watch-count");
+    let sub = dir.join("sub");
+    std::fs::create_dir(&sub).unwrap();
+    let [a, b, c] = [dir.join("a.glsl"), dir.join("b.glsl"), sub.join("c.glsl")];
+    let mut watcher = FileWatcher::new().unwrap();
+    for path in [&a, &b, &c] {
+        watcher.watch(path).unwrap();
+    }
+    // Watching a file twice counts once.
+    watcher.watch(&a).unwrap();
+    assert_eq!(watcher.directories(), [dir.as_path(), sub.as_path()]);
+
+    watcher.unwatch(&a);
+    assert_eq!(watcher.directories(), [dir.as_path(), sub.as_path()]);
+    watcher.unwatch(&a);
+    watcher.unwatch(&c);
+    assert_eq!(watcher.directories(), [dir.as_path()]);
+    watcher.unwatch(&b);
+    assert!(watcher.directories().is_empty());
+}
+
+#[test]
+fn only_watched_files_are_reported() {
+    let dir = temp_dir("watch-changes");
+    let [a, b] = [dir.join("a.glsl"), dir.join("b.glsl")];
+    std::fs::write(&a, "").unwrap();
+    std::fs::write(&b, "").unwrap();
+    let mut watcher = FileWatcher::new().unwrap();
+    watcher.watch(&a).unwrap();
+    watcher.watch(&b).unwrap();
+    watcher.unwatch(&b);
+
+    std::fs::write(&b, "// Not watched.\n").unwrap();
+    std::fs::write(&a, "// Changed.\n").unwrap();
+    assert_eq!(wait_for_changes(&mut watcher), [a.clone()]);
+    // Other spellings of the path name the same file.
+    assert_eq!(normalize(&dir.join(".").join("a.glsl")).unwrap(), a);
+}